@@ -1,7 +1,11 @@
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use chimney::{
-    config::{self, Config, Format, LogLevel, Site},
+    config::{self, Config, Format, LogLevel, Site, vercel, vercel::ImportFormat},
     config_log_debug, config_log_warn, filesystem,
     server::Server,
     tls::{CertRequestOptions, LETS_ENCRYPT_PRODUCTION_URL, LETS_ENCRYPT_STAGING_URL},
@@ -10,7 +14,7 @@ use clap::{Parser, Subcommand};
 
 use crate::{
     error::{self, CliError},
-    format::FormatType,
+    format::{FormatType, ImportFormatType},
 };
 
 /// A constant array of default configuration file paths to use if none is provided.
@@ -64,6 +68,49 @@ pub enum Commands {
         format: FormatType,
     },
 
+    /// Convert the routing configuration of a `vercel.json` or `serve.json` file into a site
+    /// configuration
+    ///
+    /// Redirects, rewrites and headers are converted, anything that can't be is reported as a
+    /// warning so it can be ported by hand.
+    #[command(
+        arg_required_else_help = true,
+        about = "Convert a vercel.json or serve.json file into a Chimney site configuration"
+    )]
+    Import {
+        /// Path to the file to convert
+        #[arg(help = "Path to the vercel.json or serve.json file to convert")]
+        file: PathBuf,
+
+        /// The format of the file, detected from its name if not provided
+        #[arg(
+            short,
+            long,
+            help = "The format of the file to convert (detected from the file name by default)"
+        )]
+        from: Option<ImportFormatType>,
+
+        /// Domain name(s) the site responds to (can be specified multiple times)
+        #[arg(short, long = "domain", help = "Domain name(s) the site responds to")]
+        domains: Vec<String>,
+
+        /// Where to write the site configuration
+        #[arg(
+            short,
+            long,
+            help = "Path to write the site configuration to (default: chimney.toml next to the converted file)"
+        )]
+        output: Option<PathBuf>,
+
+        /// Overwrite the output file if it already exists
+        #[arg(
+            long,
+            default_value = "false",
+            help = "Overwrite the output file if it already exists"
+        )]
+        force: bool,
+    },
+
     /// Print the version of the Chimney CLI
     #[command(about = "Print the version of the Chimney CLI")]
     Version,
@@ -192,6 +239,16 @@ impl Cli {
                 self.set_log_level(self.log_level.clone());
                 self.generate_default_config(path.clone(), format)
             }
+            Commands::Import {
+                file,
+                from,
+                domains,
+                output,
+                force,
+            } => {
+                self.set_log_level(self.log_level.clone());
+                self.import_site_config(file, *from, domains, output.clone(), *force)
+            }
             Commands::Version => {
                 println!("Chimney CLI version: {}", env!("CARGO_PKG_VERSION"));
                 Ok(())
@@ -440,6 +497,69 @@ impl Cli {
         Ok(())
     }
 
+    /// Convert a `vercel.json` or `serve.json` file into a site configuration file.
+    fn import_site_config(
+        &self,
+        file: &Path,
+        from: Option<ImportFormatType>,
+        domains: &[String],
+        output: Option<PathBuf>,
+        force: bool,
+    ) -> Result<(), CliError> {
+        let format = from.map(ImportFormat::from).or_else(|| {
+            file.file_name()
+                .and_then(|name| ImportFormat::from_filename(&name.to_string_lossy()))
+        });
+        let Some(format) = format else {
+            return Err(CliError::Generic(format!(
+                "Unable to detect the format of {}, use --from to provide it",
+                file.display()
+            )));
+        };
+
+        let directory = file
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let output = output.unwrap_or_else(|| directory.join("chimney.toml"));
+        if output.exists() && !force {
+            return Err(CliError::Generic(format!(
+                "{} already exists, use --force to overwrite it",
+                output.display()
+            )));
+        }
+
+        // The site is named after its directory, like sites in the sites directory are
+        let name = directory
+            .canonicalize()
+            .ok()
+            .and_then(|directory| {
+                directory
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "site".to_string());
+
+        let input = std::fs::read_to_string(file).map_err(CliError::Read)?;
+        let mut conversion = vercel::convert(format, name, &input)?;
+        for domain in domains {
+            conversion.site.add_domain_name(domain.clone());
+        }
+
+        for warning in &conversion.warnings {
+            log::warn!("{}: {warning}", format.filename());
+        }
+
+        std::fs::write(&output, conversion.site.to_toml_string()?)?;
+        log::info!(
+            "Converted {} into a site configuration at: {}",
+            file.display(),
+            output.display()
+        );
+
+        Ok(())
+    }
+
     /// Generate a default Chimney configuration file in the specified target directory.
     fn generate_default_config(&self, path: PathBuf, format: &FormatType) -> Result<(), CliError> {
         let config = Config::default();
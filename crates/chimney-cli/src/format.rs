@@ -1,4 +1,4 @@
-use chimney::config::{Format, toml};
+use chimney::config::{Format, toml, vercel::ImportFormat};
 use clap::ValueEnum;
 use serde::Serialize;
 
@@ -18,3 +18,22 @@ impl FormatType {
         }
     }
 }
+
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportFormatType {
+    /// `vercel.json`
+    Vercel,
+
+    /// `serve.json`
+    Serve,
+}
+
+impl From<ImportFormatType> for ImportFormat {
+    fn from(format: ImportFormatType) -> Self {
+        match format {
+            ImportFormatType::Vercel => ImportFormat::Vercel,
+            ImportFormatType::Serve => ImportFormat::Serve,
+        }
+    }
+}
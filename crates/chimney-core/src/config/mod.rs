@@ -2,6 +2,8 @@
 pub mod toml;
 
 pub mod macros;
+pub mod netlify;
+pub mod vercel;
 
 mod format;
 pub mod types;
@@ -0,0 +1,234 @@
+//! Support for the `_redirects` and `_headers` files used by Netlify and Cloudflare Pages
+//!
+//! Both files live in the root of a site and are imported into the site's configuration when it
+//! is loaded, see [`Site::import_site_files`](super::Site::import_site_files).
+use hyper::header::{HeaderName, HeaderValue};
+use indexmap::IndexMap;
+
+use crate::error::ChimneyError;
+
+use super::{HeaderRule, REDIRECT_STATUS_CODES, RedirectRule, RewriteRule, RoutePattern, Template};
+
+/// The name of the file redirects and rewrites are imported from
+pub const REDIRECTS_FILE: &str = "_redirects";
+
+/// The name of the file header rules are imported from
+pub const HEADERS_FILE: &str = "_headers";
+
+/// The rules read from a `_redirects` file
+#[derive(Debug, Default, Clone)]
+pub struct Redirects {
+    /// Rules with a redirect status code (`3xx` or `410`), in the order they were declared
+    ///
+    /// As on Netlify, rules without the `!` suffix only apply to paths that don't match a file.
+    pub redirects: IndexMap<String, RedirectRule>,
+
+    /// Rules with a `200` status code, in the order they were declared, which also only apply to
+    /// paths that don't match a file unless they have the `!` suffix
+    pub rewrites: IndexMap<String, RewriteRule>,
+
+    /// The target of a catch-all `/* /index.html 200` rule
+    ///
+    /// Without the `!` suffix, such a rule only applies to paths that don't match a file, which
+    /// is what the site's `fallback_file` does.
+    pub fallback: Option<String>,
+}
+
+/// Parses the content of a `_redirects` file
+///
+/// Every line is a `<from> <to> [status][!]` rule, where the status defaults to `301` and both
+/// paths use the same splat (`*`) and parameter (`:name`) syntax as the site's own rules. Rules
+/// are shadowed by existing files unless they are forced with `!`. When several rules share a
+/// source, the first one wins. Query parameter matching, conditions (like
+/// `Country=`) and proxying to other origins are not supported.
+pub fn parse_redirects(input: &str) -> Result<Redirects, ChimneyError> {
+    let mut redirects = Redirects::default();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: String| ChimneyError::ParseError {
+            field: format!("{REDIRECTS_FILE}:{}", index + 1),
+            message,
+        };
+
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let (from, to, rest) = match tokens.as_slice() {
+            [from, to, rest @ ..] => (*from, *to, rest),
+            _ => {
+                return Err(error(format!(
+                    "expected `<from> <to> [status]`, got `{line}`"
+                )));
+            }
+        };
+
+        if !from.starts_with('/') {
+            return Err(error(format!(
+                "`{from}` is not supported, only paths starting with `/` can be redirected"
+            )));
+        }
+
+        if !is_target(to) {
+            return Err(error(if to.contains('=') {
+                format!("query parameter matching (`{to}`) is not supported")
+            } else {
+                format!("`{to}` is not a path or URL to redirect to")
+            }));
+        }
+
+        let (status, forced) = match rest {
+            [] => (301, false),
+            [status, conditions @ ..] => {
+                if let Some(condition) = conditions.first() {
+                    return Err(error(format!(
+                        "conditions (`{condition}`) are not supported"
+                    )));
+                }
+
+                let (code, forced) = match status.strip_suffix('!') {
+                    Some(code) => (code, true),
+                    None => (*status, false),
+                };
+
+                let code = code
+                    .parse::<u16>()
+                    .map_err(|_| error(format!("`{status}` is not a valid status code")))?;
+                (code, forced)
+            }
+        };
+
+        RoutePattern::parse(from).map_err(|e| error(e.to_string()))?;
+        Template::parse(to).map_err(|e| error(e.to_string()))?;
+
+        match status {
+            200 if to.starts_with("http://") || to.starts_with("https://") => {
+                return Err(error(format!(
+                    "proxying to another origin (`{to}`) is not supported"
+                )));
+            }
+            200 if from == "/*" && !forced && !RoutePattern::is_pattern(to) => {
+                redirects.fallback.get_or_insert_with(|| to.to_string());
+            }
+            200 => {
+                let rule = match forced {
+                    true => RewriteRule::new(to.to_string()),
+                    false => RewriteRule::new(to.to_string()).with_files_first(true),
+                };
+
+                redirects.rewrites.entry(from.to_string()).or_insert(rule);
+            }
+            status if REDIRECT_STATUS_CODES.contains(&status) => {
+                let rule = match status {
+                    301 => RedirectRule::Target(to.to_string()),
+                    status => RedirectRule::Target(to.to_string()).with_status(status),
+                };
+                let rule = match forced {
+                    true => rule,
+                    false => rule.with_files_first(true),
+                };
+
+                redirects.redirects.entry(from.to_string()).or_insert(rule);
+            }
+            status => {
+                return Err(error(format!("status code `{status}` is not supported")));
+            }
+        }
+    }
+
+    Ok(redirects)
+}
+
+/// Parses the content of a `_headers` file into header rules, in the order they were declared
+///
+/// A line starting with a path (which may contain splats and `:name` parameters) starts a new
+/// rule, and the indented `Name: value` lines below it are the headers to set. Repeated headers
+/// are joined with a comma, and `! Name` removes a header, as on Cloudflare Pages.
+pub fn parse_headers(input: &str) -> Result<Vec<HeaderRule>, ChimneyError> {
+    let mut rules: Vec<HeaderRule> = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: String| ChimneyError::ParseError {
+            field: format!("{HEADERS_FILE}:{}", index + 1),
+            message,
+        };
+
+        // Paths start at the beginning of a line, the headers for them are indented
+        if !line.starts_with(char::is_whitespace) {
+            if !trimmed.starts_with('/') {
+                return Err(error(format!(
+                    "`{trimmed}` is not supported, only paths starting with `/` can have headers"
+                )));
+            }
+
+            rules.push(HeaderRule::path(path_to_glob(trimmed)));
+            continue;
+        }
+
+        let Some(rule) = rules.last_mut() else {
+            return Err(error(format!("`{trimmed}` is not preceded by a path")));
+        };
+
+        if let Some(name) = trimmed.strip_prefix('!') {
+            let name = name.trim();
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| error(format!("`{name}` is not a valid header name")))?;
+            rule.remove.push(name.to_string());
+            continue;
+        }
+
+        let Some((name, value)) = trimmed.split_once(':') else {
+            return Err(error(format!("expected `Name: value`, got `{trimmed}`")));
+        };
+
+        let (name, value) = (name.trim(), value.trim());
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| error(format!("`{name}` is not a valid header name")))?;
+        HeaderValue::from_str(value)
+            .map_err(|_| error(format!("`{value}` is not a valid header value")))?;
+        Template::parse(value).map_err(|e| error(e.to_string()))?;
+
+        match rule
+            .set
+            .iter_mut()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        {
+            Some((_, existing)) => {
+                existing.push_str(", ");
+                existing.push_str(value);
+            }
+            None => {
+                rule.set.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    Ok(rules)
+}
+
+/// Whether the second token of a rule is a target rather than a query parameter to match
+fn is_target(token: &str) -> bool {
+    token.starts_with('/') || token.starts_with("http://") || token.starts_with("https://")
+}
+
+/// Converts a `_headers` path to a glob, a splat matches anything (including `/`) and a
+/// parameter matches a single segment
+fn path_to_glob(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if segment.starts_with(':') {
+                return "*".to_string();
+            }
+
+            segment.replace("**", "*").replace('*', "**")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
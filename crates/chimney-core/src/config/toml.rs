@@ -69,12 +69,15 @@ impl<'a> Format<'a> for Toml<'a> {
             message: format!("Failed to parse global TOML configuration: {e}"),
         })?;
 
-        if let Some(sites) = parsed.get("sites") {
-            if let Some(sites) = sites.as_table() {
-                self.parse_sites(&mut config, sites)?;
-            }
+        if let Some(sites) = parsed.get("sites")
+            && let Some(sites) = sites.as_table()
+        {
+            self.parse_sites(&mut config, sites)?;
         }
 
+        // The MIME types files are part of the configuration, a missing or invalid one fails it
+        config.load_mime_types()?;
+
         Ok(config)
     }
 
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::with_leading_slash;
+
+use super::{glob_matches, glob_matches_ignore_case};
+
+/// Paths that are never served, so a site whose `root` is its own directory doesn't expose its
+/// configuration, version control or environment files
+///
+/// This covers dotfiles and dot-directories (`.env`, `.git/config`, `.chimney/certs`), the
+/// site's `chimney.toml` and the `_redirects` and `_headers` files imported from its root.
+pub const DEFAULT_DENIED_PATHS: [&str; 5] = [
+    "/**/.*",
+    "/**/.*/**",
+    "/**/chimney.toml",
+    "/_redirects",
+    "/_headers",
+];
+
+/// Per-site rules for paths that are never served, on top of [`DEFAULT_DENIED_PATHS`]
+///
+/// Patterns are globs, matched the same way as header rule paths (see
+/// [`glob_matches`](super::glob_matches)), except that denied paths are matched regardless of
+/// their case.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccessRules {
+    /// Additional path patterns that are never served (e.g. `["/drafts/**", "*.map"]`)
+    #[serde(default)]
+    pub deny: Vec<String>,
+
+    /// Path patterns that are served even if they are denied (default: `["/.well-known/**"]`)
+    #[serde(default = "AccessRules::default_allow")]
+    pub allow: Vec<String>,
+
+    /// The status code denied paths are answered with, either 404 or 403 (default: 404)
+    ///
+    /// `404 Not Found` doesn't reveal whether the path exists at all.
+    #[serde(default = "AccessRules::default_status")]
+    pub status: u16,
+}
+
+impl Default for AccessRules {
+    fn default() -> Self {
+        Self {
+            deny: Vec::new(),
+            allow: Self::default_allow(),
+            status: Self::default_status(),
+        }
+    }
+}
+
+impl AccessRules {
+    /// `.well-known` is public by convention (`security.txt`, ACME challenges, app links)
+    pub fn default_allow() -> Vec<String> {
+        vec!["/.well-known/**".to_string()]
+    }
+
+    pub fn default_status() -> u16 {
+        404
+    }
+
+    /// Adds a path pattern that is never served
+    pub fn deny(mut self, pattern: impl Into<String>) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    /// Adds a path pattern that is served even if it is denied
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    /// Sets the status code denied paths are answered with
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Whether the path, relative to the site's root, must not be served
+    pub fn is_denied(&self, path: &str) -> bool {
+        let path = with_leading_slash!(path);
+
+        let denied = DEFAULT_DENIED_PATHS
+            .into_iter()
+            .chain(self.deny.iter().map(String::as_str))
+            .any(|pattern| glob_matches_ignore_case(pattern, &path));
+
+        denied
+            && !self
+                .allow
+                .iter()
+                .any(|pattern| glob_matches(pattern, &path))
+    }
+}
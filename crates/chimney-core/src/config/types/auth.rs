@@ -0,0 +1,114 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::with_leading_slash;
+
+use super::glob_matches_ignore_case;
+
+/// Per-site HTTP Basic authentication
+///
+/// Requests for protected paths need the name and password of one of the users, whose
+/// passwords are stored as bcrypt (`$2y$...`) or Argon2 (`$argon2id$...`) hashes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Auth {
+    /// The realm browsers show when asking for credentials (default: "Restricted")
+    #[serde(default = "Auth::default_realm")]
+    pub realm: String,
+
+    /// Path patterns that require authentication, like `["/internal/**"]` (default: every path)
+    ///
+    /// Patterns are globs, matched like denied paths, ignoring case (see
+    /// [`glob_matches_ignore_case`](super::glob_matches_ignore_case)), so a case-insensitive
+    /// filesystem serving `/INTERNAL/` doesn't get around `/internal/**`.
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// Users and the hashes of their passwords
+    #[serde(default)]
+    pub users: IndexMap<String, String>,
+
+    /// An `htpasswd` file with more users, relative to the site's directory
+    ///
+    /// Users configured in `users` take precedence over the ones in the file. The file has to be
+    /// inside the site's directory, and is never served.
+    #[serde(default)]
+    pub htpasswd_file: Option<String>,
+
+    /// The users read from `htpasswd_file` when the configuration is loaded, see
+    /// [`Site::load_htpasswd_file`](super::Site::load_htpasswd_file)
+    #[serde(skip_serializing, skip_deserializing)]
+    pub htpasswd_users: IndexMap<String, String>,
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Self {
+            realm: Self::default_realm(),
+            paths: Vec::new(),
+            users: IndexMap::new(),
+            htpasswd_file: None,
+            htpasswd_users: IndexMap::new(),
+        }
+    }
+}
+
+impl Auth {
+    pub fn default_realm() -> String {
+        "Restricted".to_string()
+    }
+
+    /// Creates an authentication configuration with the given realm, protecting every path
+    pub fn new(realm: impl Into<String>) -> Self {
+        Self {
+            realm: realm.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a path pattern that requires authentication
+    pub fn path(mut self, pattern: impl Into<String>) -> Self {
+        self.paths.push(pattern.into());
+        self
+    }
+
+    /// Adds a user with the hash of their password
+    pub fn user(mut self, name: impl Into<String>, password_hash: impl Into<String>) -> Self {
+        self.users.insert(name.into(), password_hash.into());
+        self
+    }
+
+    /// Sets the `htpasswd` file to read more users from
+    pub fn with_htpasswd_file(mut self, file: impl Into<String>) -> Self {
+        self.htpasswd_file = Some(file.into());
+        self
+    }
+
+    /// Whether the site requires authentication at all
+    pub fn is_enabled(&self) -> bool {
+        !self.users.is_empty() || self.htpasswd_file.is_some()
+    }
+
+    /// Returns the password hash of a user, from `users` or the `htpasswd` file
+    pub fn password_hash(&self, user: &str) -> Option<&str> {
+        self.users
+            .get(user)
+            .or_else(|| self.htpasswd_users.get(user))
+            .map(String::as_str)
+    }
+
+    /// Whether the path, relative to the site's root, requires authentication
+    ///
+    /// A directory counts as part of the patterns covering its contents, so `/internal/**` also
+    /// protects `/internal` (and the index file served for it).
+    pub fn is_protected(&self, path: &str) -> bool {
+        let path = with_leading_slash!(path);
+        let directory = format!("{}/", path.trim_end_matches('/'));
+
+        self.is_enabled()
+            && (self.paths.is_empty()
+                || self.paths.iter().any(|pattern| {
+                    glob_matches_ignore_case(pattern, &path)
+                        || glob_matches_ignore_case(pattern, &directory)
+                }))
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::with_leading_slash;
+
+/// Per-site directory listing configuration
+///
+/// Directory listings are only ever generated for directories without an index file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Autoindex {
+    /// Whether directory listings are enabled for the whole site (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The path prefixes directory listings are enabled for, when they are not enabled for the
+    /// whole site (e.g. `["/artifacts"]`)
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// Whether hidden files (names starting with a dot) are included in listings (default: false)
+    ///
+    /// Files denied by the site's `access` rules, which include dotfiles unless they are allowed,
+    /// are never listed.
+    #[serde(default)]
+    pub show_hidden: bool,
+}
+
+impl Autoindex {
+    /// Enables directory listings for the whole site
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// Enables directory listings for the given path prefix
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Sets whether hidden files are included in listings
+    pub fn with_hidden(mut self, show_hidden: bool) -> Self {
+        self.show_hidden = show_hidden;
+        self
+    }
+
+    /// Whether a directory listing may be generated for the given request path
+    ///
+    /// Prefixes only match whole path segments, so `/artifacts` matches `/artifacts/v1` but not
+    /// `/artifacts-old`.
+    pub fn is_enabled_for(&self, path: &str) -> bool {
+        if self.enabled {
+            return true;
+        }
+
+        let path = with_leading_slash!(path);
+        self.paths.iter().any(|prefix| {
+            let prefix = with_leading_slash!(prefix.trim_end_matches('/'));
+            prefix == "/"
+                || path == prefix
+                || path
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+}
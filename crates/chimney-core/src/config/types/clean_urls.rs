@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Per-site clean URL configuration
+///
+/// With clean URLs, `/about` is served from `about.html` when there is no file or directory
+/// index for it, so links don't need to carry the extension.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CleanUrls {
+    /// Whether paths without a matching file are resolved to `.html` files (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Whether requests for `/about.html` and `/about/index.html` are redirected to `/about` with
+    /// a `301 Moved Permanently` (default: false)
+    #[serde(default)]
+    pub redirect: bool,
+}
+
+impl CleanUrls {
+    /// Enables clean URLs for the site, without redirecting to them
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets whether paths with an `.html` extension are redirected to their clean form
+    pub fn with_redirect(mut self, redirect: bool) -> Self {
+        self.redirect = redirect;
+        self
+    }
+
+    /// Returns the clean form of a request path to an HTML file, if it should be redirected
+    ///
+    /// `/about.html` becomes `/about`, and a directory's index file (`/about/index.html`, or any
+    /// other of `index_files`) becomes the directory itself (`/about`, or `/` for the root).
+    pub fn canonical_path(&self, path: &str, index_files: &[&str]) -> Option<String> {
+        if !self.enabled || !self.redirect {
+            return None;
+        }
+
+        // Leading slashes are collapsed, so `//example.com/index.html` can't turn into a
+        // protocol-relative redirect to another host
+        if let Some(directory) = index_files
+            .iter()
+            .filter_map(|file| path.strip_suffix(file))
+            .find(|directory| directory.ends_with('/'))
+        {
+            return Some(format!("/{}", directory.trim_matches('/')));
+        }
+
+        let stem = path.strip_suffix(".html")?;
+        if stem.is_empty() || stem.ends_with('/') {
+            return None;
+        }
+
+        Some(format!("/{}", stem.trim_start_matches('/')))
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+/// A content coding that files can be served with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Brotli (`br`)
+    #[serde(rename = "br", alias = "brotli")]
+    Brotli,
+
+    /// Zstandard (`zstd`)
+    #[serde(rename = "zstd", alias = "zst")]
+    Zstd,
+
+    /// Gzip (`gzip`)
+    #[serde(rename = "gzip", alias = "gz")]
+    Gzip,
+}
+
+impl Encoding {
+    /// The content coding token used in the `Accept-Encoding` and `Content-Encoding` headers
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// The file extension used for precompressed files with this encoding
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zst",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    /// Parses a content coding token, as found in the `Accept-Encoding` header
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "br" => Some(Encoding::Brotli),
+            "zstd" => Some(Encoding::Zstd),
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Where compressed variants of files are cached when compressing on the fly
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionCache {
+    /// Compress every response again (default)
+    #[default]
+    None,
+
+    /// Keep compressed variants in memory, shared by all sites and bounded in size
+    Memory,
+
+    /// Keep compressed variants as files in the given directory, bounded to 1 GiB by evicting the
+    /// oldest ones
+    Disk(String),
+}
+
+/// Per-site compression configuration
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Compression {
+    /// The precompressed sidecar files to look for, in order of preference (default: br, zstd,
+    /// gzip)
+    ///
+    /// When a client accepts one of these encodings and a sibling file with the matching extension
+    /// exists (e.g. `app.js.br` next to `app.js`), the sibling is served instead with the
+    /// appropriate `Content-Encoding`. Set this to an empty list to disable the lookup.
+    #[serde(default = "Compression::default_precompressed")]
+    pub precompressed: Vec<Encoding>,
+
+    /// Whether compressible files (text, JSON, JavaScript, SVG, wasm, ...) without a precompressed
+    /// sibling are compressed on the fly (default: false)
+    #[serde(default)]
+    pub dynamic: bool,
+
+    /// The encodings used to compress files on the fly, in order of preference (default: br,
+    /// zstd, gzip)
+    #[serde(default = "Compression::default_encodings")]
+    pub encodings: Vec<Encoding>,
+
+    /// Files smaller than this many bytes are not compressed on the fly (default: 1024)
+    ///
+    /// Compressing tiny files costs more than it saves, and can even make them larger.
+    #[serde(default = "Compression::default_min_size")]
+    pub min_size: u64,
+
+    /// Files larger than this many bytes are not compressed on the fly (default: 10 MiB)
+    ///
+    /// Compressing a file requires reading it into memory, so this keeps memory usage bounded.
+    #[serde(default = "Compression::default_max_size")]
+    pub max_size: u64,
+
+    /// Where compressed variants are cached, keyed by the file's path and last modified time
+    /// (default: "none")
+    #[serde(default)]
+    pub cache: CompressionCache,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            precompressed: Self::default_precompressed(),
+            dynamic: false,
+            encodings: Self::default_encodings(),
+            min_size: Self::default_min_size(),
+            max_size: Self::default_max_size(),
+            cache: CompressionCache::default(),
+        }
+    }
+}
+
+impl Compression {
+    pub fn default_precompressed() -> Vec<Encoding> {
+        vec![Encoding::Brotli, Encoding::Zstd, Encoding::Gzip]
+    }
+
+    pub fn default_encodings() -> Vec<Encoding> {
+        vec![Encoding::Brotli, Encoding::Zstd, Encoding::Gzip]
+    }
+
+    pub fn default_min_size() -> u64 {
+        1024
+    }
+
+    pub fn default_max_size() -> u64 {
+        10 * 1024 * 1024
+    }
+
+    /// Sets the precompressed encodings to look for, in order of preference
+    pub fn with_precompressed(mut self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+        self.precompressed = encodings.into_iter().collect();
+        self
+    }
+
+    /// Enables or disables on-the-fly compression
+    pub fn with_dynamic(mut self, dynamic: bool) -> Self {
+        self.dynamic = dynamic;
+        self
+    }
+
+    /// Sets the encodings used for on-the-fly compression, in order of preference
+    pub fn with_encodings(mut self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+        self.encodings = encodings.into_iter().collect();
+        self
+    }
+
+    /// Sets the minimum size of files compressed on the fly
+    pub fn with_min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Sets the maximum size of files compressed on the fly
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Sets where compressed variants are cached
+    pub fn with_cache(mut self, cache: CompressionCache) -> Self {
+        self.cache = cache;
+        self
+    }
+}
@@ -8,9 +8,11 @@ use std::{
 use crate::{
     config::Format,
     error::{ChimneyError, ServerError},
+    filesystem::Filesystem,
+    server::mimetype::{DEFAULT_CHARSET, MimeRegistry},
 };
 
-use super::{LogLevel, Sites};
+use super::{LogLevel, Site, Sites};
 
 pub type ConfigSender = tokio::sync::watch::Sender<Arc<Config>>;
 pub type ConfigReceiver = tokio::sync::watch::Receiver<Arc<Config>>;
@@ -185,6 +187,19 @@ pub struct Config {
     #[serde(default)]
     pub log_level: Option<LogLevel>,
 
+    /// Files in the `/etc/mime.types` format to load extra MIME types from (default: none)
+    ///
+    /// Later files take precedence over earlier ones, and all of them over the built-in types.
+    /// Sites can still override single extensions with their own `mime_types`.
+    #[serde(default)]
+    pub mime_types_files: Vec<PathBuf>,
+
+    /// The charset appended to the `Content-Type` of text files (default: "utf-8")
+    ///
+    /// An empty string leaves text files without a charset.
+    #[serde(default = "Config::default_charset")]
+    pub default_charset: String,
+
     /// The various site configurations
     #[serde(skip_deserializing, skip_serializing_if = "Sites::is_empty")]
     pub sites: Sites,
@@ -193,6 +208,10 @@ pub struct Config {
     /// This serves as a cache for automatic detection
     #[serde(skip_serializing, skip_deserializing)]
     resolved_host_header: Option<String>,
+
+    /// The MIME types loaded from `mime_types_files`, with the `default_charset`
+    #[serde(skip_serializing, skip_deserializing)]
+    mime_types: MimeRegistry,
 }
 
 impl Default for Config {
@@ -204,8 +223,11 @@ impl Default for Config {
             host_detection: HostDetectionStrategy::default(),
             sites_directory: Config::default_sites_dir(),
             log_level: Some(LogLevel::default()),
+            mime_types_files: Vec::new(),
+            default_charset: Config::default_charset(),
             sites: Sites::default(),
             resolved_host_header: None,
+            mime_types: MimeRegistry::default(),
         }
     }
 }
@@ -224,6 +246,10 @@ impl Config {
         Some(8443)
     }
 
+    pub fn default_charset() -> String {
+        DEFAULT_CHARSET.to_string()
+    }
+
     pub fn default_sites_dir() -> String {
         // NOTE: there are cases where this can fail but the changes of hitting either are rare, so
         // we should be fine here
@@ -251,6 +277,82 @@ impl Config {
     }
 }
 
+// Site implementations
+impl Config {
+    /// Resolves the root directory of a site, relative to the sites directory
+    pub fn site_root(&self, site: &Site) -> PathBuf {
+        PathBuf::from(&self.sites_directory)
+            .join(&site.name)
+            .join(&site.root)
+    }
+
+    /// Imports the `_redirects` and `_headers` files of every site, and loads their `htpasswd`
+    /// files
+    ///
+    /// See [`Site::import_site_files`] for how the imported rules are merged, and
+    /// [`Site::load_htpasswd_file`] for the users. A [`Service`](crate::server::service::Service)
+    /// does this with its filesystem when it is created.
+    pub fn import_site_files(&mut self, filesystem: &dyn Filesystem) -> Result<(), ChimneyError> {
+        let names = self
+            .sites
+            .values()
+            .map(|site| site.name.clone())
+            .collect::<Vec<_>>();
+
+        for name in names {
+            let Some(mut site) = self.sites.get(&name).cloned() else {
+                continue;
+            };
+
+            let root = self.site_root(&site);
+            site.import_site_files(filesystem, &root)?;
+            site.load_htpasswd_file(
+                filesystem,
+                &PathBuf::from(&self.sites_directory).join(&site.name),
+            )?;
+            self.sites.update(site)?;
+        }
+
+        Ok(())
+    }
+}
+
+// MIME type implementations
+impl Config {
+    /// Returns the MIME types used to serve files
+    pub fn mime_types(&self) -> &MimeRegistry {
+        &self.mime_types
+    }
+
+    /// Loads the `mime_types_files` and applies the `default_charset`
+    ///
+    /// Configurations parsed from a file have them loaded already. This has to be called again
+    /// whenever either of them changes, the built-in types and the default charset are used
+    /// until then.
+    pub fn load_mime_types(&mut self) -> Result<(), ChimneyError> {
+        let charset = Some(self.default_charset.trim()).filter(|charset| !charset.is_empty());
+        if let Some(charset) = charset
+            && !charset
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+        {
+            return Err(ChimneyError::ConfigError {
+                field: "default_charset".to_string(),
+                message: format!("`{charset}` is not a valid charset"),
+            });
+        }
+
+        let mut registry = MimeRegistry::new().with_charset(charset);
+
+        for path in &self.mime_types_files {
+            registry.load_file(path)?;
+        }
+
+        self.mime_types = registry;
+        Ok(())
+    }
+}
+
 // Host header resolution implementations
 impl Config {
     /// Checks if we already have cached target headers
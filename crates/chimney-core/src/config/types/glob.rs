@@ -0,0 +1,59 @@
+/// Matches a request path against a glob pattern
+///
+/// - `*` matches any number of characters within a single path segment
+/// - `**` matches any number of characters, including `/`
+/// - `?` matches a single character other than `/`
+///
+/// Patterns that start with a `/` are matched against the whole path (e.g. `/assets/**`), while
+/// patterns without a `/` are matched against the last segment of the path, so `*.html` matches
+/// HTML files in any directory.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    matches_path(pattern, path, false)
+}
+
+/// Matches a request path against a glob pattern like [`glob_matches`], ignoring ASCII case
+///
+/// Case-insensitive filesystems serve `/Chimney.TOML` just like `/chimney.toml`, so patterns that
+/// keep files from being served have to be matched this way.
+pub fn glob_matches_ignore_case(pattern: &str, path: &str) -> bool {
+    matches_path(pattern, path, true)
+}
+
+fn matches_path(pattern: &str, path: &str, ignore_case: bool) -> bool {
+    if pattern.contains('/') {
+        return matches_from(pattern.as_bytes(), path.as_bytes(), ignore_case);
+    }
+
+    let name = path.rsplit('/').next().unwrap_or(path);
+    matches_from(pattern.as_bytes(), name.as_bytes(), ignore_case)
+}
+
+fn matches_from(pattern: &[u8], text: &[u8], ignore_case: bool) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `/**/` also matches a single `/`, so `/a/**/b` matches `/a/b`
+            if let [b'/', after @ ..] = rest
+                && matches_from(after, text, ignore_case)
+            {
+                return true;
+            }
+
+            (0..=text.len()).any(|skip| matches_from(rest, &text[skip..], ignore_case))
+        }
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|c| *c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|skip| matches_from(rest, &text[skip..], ignore_case))
+        }
+        [b'?', rest @ ..] => match text {
+            [c, remaining @ ..] if *c != b'/' => matches_from(rest, remaining, ignore_case),
+            _ => false,
+        },
+        [c, rest @ ..] => match text {
+            [t, remaining @ ..] if t == c || (ignore_case && t.eq_ignore_ascii_case(c)) => {
+                matches_from(rest, remaining, ignore_case)
+            }
+            _ => false,
+        },
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::glob_matches;
+
+/// A rule that changes the response headers for matching requests
+///
+/// Rules are applied in the order they are declared, after the site's `response_headers`, so a
+/// later rule can override what an earlier one did. Within a rule, headers are removed first,
+/// then set, then appended.
+///
+/// A rule without a `path` or `mime` matches every response.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct HeaderRule {
+    /// A glob matched against the request path (e.g. `/assets/**`, or `*.html` for HTML files in
+    /// any directory)
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// The MIME type of the response (e.g. `text/html`, or `image/*` for every image)
+    #[serde(default)]
+    pub mime: Option<String>,
+
+    /// Headers to set, replacing any existing value
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub set: HashMap<String, String>,
+
+    /// Headers to add, keeping any existing value
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub append: HashMap<String, String>,
+
+    /// Headers to remove
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+}
+
+impl HeaderRule {
+    /// Creates a rule that matches every response
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a rule for request paths matching the glob
+    pub fn path(pattern: impl Into<String>) -> Self {
+        Self {
+            path: Some(pattern.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a rule for responses with the given MIME type
+    pub fn mime(mime: impl Into<String>) -> Self {
+        Self {
+            mime: Some(mime.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Only applies the rule to request paths matching the glob
+    pub fn with_path(mut self, pattern: impl Into<String>) -> Self {
+        self.path = Some(pattern.into());
+        self
+    }
+
+    /// Only applies the rule to responses with the given MIME type
+    pub fn with_mime(mut self, mime: impl Into<String>) -> Self {
+        self.mime = Some(mime.into());
+        self
+    }
+
+    /// Sets a header, replacing any existing value
+    pub fn set(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds a header, keeping any existing value
+    pub fn append(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.append.insert(name.into(), value.into());
+        self
+    }
+
+    /// Removes a header
+    pub fn remove(mut self, name: impl Into<String>) -> Self {
+        self.remove.push(name.into());
+        self
+    }
+
+    /// Whether the rule applies to a response for the request path with the given MIME type
+    pub fn matches(&self, path: &str, mime: &str) -> bool {
+        let path_matches = self
+            .path
+            .as_deref()
+            .is_none_or(|pattern| glob_matches(pattern, path));
+
+        let mime_matches = self.mime.as_deref().is_none_or(|expected| {
+            // Parameters like `charset` are ignored, only the type itself is compared
+            let essence = mime.split(';').next().unwrap_or_default().trim();
+            match expected.trim().strip_suffix("/*") {
+                Some(kind) => essence
+                    .split('/')
+                    .next()
+                    .is_some_and(|actual| actual.eq_ignore_ascii_case(kind)),
+                None => expected == "*" || essence.eq_ignore_ascii_case(expected.trim()),
+            }
+        });
+
+        path_matches && mime_matches
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// How a request for the site's root is sent to the subtree of the negotiated locale
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LocaleStrategy {
+    /// Redirect to the locale's subtree (`/` to `/fr/`) with a `302 Found` (default)
+    #[default]
+    Redirect,
+
+    /// Serve the locale's subtree at `/` without changing the URL
+    Rewrite,
+}
+
+/// Per-site localization configuration
+///
+/// Each locale is served from the directory with the same name at the site's root, like `/en/`
+/// and `/fr/`. Requests for `/` are sent to the locale picked from the `Accept-Language` header,
+/// unless the cookie names one of the locales.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct I18n {
+    /// The locales the site is available in, like `["en", "fr", "de"]` (default: none, which
+    /// disables localization)
+    #[serde(default)]
+    pub locales: Vec<String>,
+
+    /// The locale used when the client doesn't accept any of the others (default: the first
+    /// locale)
+    #[serde(default)]
+    pub default_locale: Option<String>,
+
+    /// The name of the cookie that overrides the `Accept-Language` header, like `"locale"`
+    /// (default: none)
+    #[serde(default)]
+    pub cookie: Option<String>,
+
+    /// Whether requests for `/` are redirected (`"redirect"`) or rewritten (`"rewrite"`) to the
+    /// locale's subtree (default: "redirect")
+    #[serde(default)]
+    pub strategy: LocaleStrategy,
+}
+
+impl I18n {
+    /// Creates a localization configuration for the given locales, the first one being the
+    /// default
+    pub fn new<I, S>(locales: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            locales: locales.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the locale used when the client doesn't accept any of the others
+    pub fn with_default_locale(mut self, locale: impl Into<String>) -> Self {
+        self.default_locale = Some(locale.into());
+        self
+    }
+
+    /// Sets the name of the cookie that overrides the `Accept-Language` header
+    pub fn with_cookie(mut self, cookie: impl Into<String>) -> Self {
+        self.cookie = Some(cookie.into());
+        self
+    }
+
+    /// Sets whether requests for `/` are redirected or rewritten to the locale's subtree
+    pub fn with_strategy(mut self, strategy: LocaleStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Whether the site is localized at all
+    pub fn is_enabled(&self) -> bool {
+        !self.locales.is_empty()
+    }
+
+    /// Returns the locale used when the client doesn't accept any of the others
+    pub fn default_locale(&self) -> Option<&str> {
+        self.default_locale
+            .as_deref()
+            .or_else(|| self.locales.first().map(String::as_str))
+    }
+
+    /// Returns the locale whose subtree the path is in, if any (`fr` for `/fr/about`)
+    pub fn locale_of(&self, path: &str) -> Option<&str> {
+        let segment = path.trim_start_matches('/').split('/').next()?;
+        self.locales
+            .iter()
+            .find(|locale| locale.eq_ignore_ascii_case(segment))
+            .map(String::as_str)
+    }
+}
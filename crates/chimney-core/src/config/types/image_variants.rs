@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// An image format that can be served in place of the requested image
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// AVIF (`image/avif`)
+    Avif,
+
+    /// WebP (`image/webp`)
+    Webp,
+
+    /// JPEG XL (`image/jxl`)
+    Jxl,
+}
+
+impl ImageFormat {
+    /// The MIME type clients list in their `Accept` header to signal support for the format
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "image/avif",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Jxl => "image/jxl",
+        }
+    }
+
+    /// The file extension of images in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "avif",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Jxl => "jxl",
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// Per-site configuration of the image variants served in place of the requested images
+///
+/// When a client accepts one of the `formats` and a sibling file in that format exists (e.g.
+/// `photo.avif` next to `photo.jpg`), the sibling is served instead, at the same URL.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImageVariants {
+    /// The formats to look for, in order of preference (default: none, which disables variants)
+    #[serde(default)]
+    pub formats: Vec<ImageFormat>,
+
+    /// The extensions of the images variants are looked for (default: jpg, jpeg, png, gif)
+    #[serde(default = "ImageVariants::default_extensions")]
+    pub extensions: Vec<String>,
+}
+
+impl Default for ImageVariants {
+    fn default() -> Self {
+        Self {
+            formats: Vec::new(),
+            extensions: Self::default_extensions(),
+        }
+    }
+}
+
+impl ImageVariants {
+    pub fn default_extensions() -> Vec<String> {
+        ["jpg", "jpeg", "png", "gif"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Looks for variants in the given formats, in order of preference
+    pub fn new(formats: impl IntoIterator<Item = ImageFormat>) -> Self {
+        Self {
+            formats: formats.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the extensions of the images variants are looked for
+    pub fn with_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether variants are looked for the given file
+    pub fn applies_to(&self, file: &Path) -> bool {
+        let Some(extension) = file.extension().and_then(|extension| extension.to_str()) else {
+            return false;
+        };
+
+        !self.formats.is_empty()
+            && self.extensions.iter().any(|candidate| {
+                candidate
+                    .trim_start_matches('.')
+                    .eq_ignore_ascii_case(extension)
+            })
+    }
+}
@@ -1,11 +1,33 @@
+mod access;
+mod auth;
+mod autoindex;
 mod certificate;
+mod clean_urls;
+mod compression;
 mod config;
 mod domain;
+mod glob;
+mod headers;
+mod i18n;
+mod image_variants;
 mod log;
+mod route;
 mod site;
+mod template;
 
+pub use access::*;
+pub use auth::*;
+pub use autoindex::*;
 pub use certificate::*;
+pub use clean_urls::*;
+pub use compression::*;
 pub use config::*;
 pub use domain::*;
+pub use glob::*;
+pub use headers::*;
+pub use i18n::*;
+pub use image_variants::*;
 pub use log::*;
+pub use route::*;
 pub use site::*;
+pub use template::*;
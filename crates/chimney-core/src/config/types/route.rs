@@ -0,0 +1,337 @@
+use log::debug;
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use regex::Regex;
+
+use crate::error::ChimneyError;
+
+/// Characters encoded in captured values when they are filled into a target
+///
+/// Requests are matched on their decoded path, so this keeps a captured `%3F` or `%23` from
+/// turning into a query or fragment (and a literal `%` from being decoded twice). Slashes are
+/// kept, splats span several segments.
+const CAPTURE_VALUE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'?')
+    .add(b'\\');
+
+/// A segment of a path pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternSegment {
+    /// A segment that has to match exactly
+    Literal(String),
+
+    /// `:name`, matches a single non-empty segment
+    Param(String),
+
+    /// `*`, matches the rest of the path (including nothing at all), only allowed at the end
+    Splat,
+}
+
+/// How the source of a redirect or rewrite rule is matched against a request path
+#[derive(Debug, Clone)]
+pub enum RoutePattern {
+    /// A plain path, e.g. `/about`
+    Exact(String),
+
+    /// A path with named parameters and/or a splat, e.g. `/posts/:slug` or `/blog/*`
+    Segments(Vec<PatternSegment>),
+
+    /// A regular expression, for sources starting with `^`, e.g. `^/posts/(\d+)$`
+    Regex(Regex),
+}
+
+/// The compiled sources of a set of rules that are patterns, in the order they are declared
+///
+/// Sites compile them once when they are loaded or their rules change, so matching a request
+/// never has to parse a pattern.
+#[derive(Debug, Default, Clone)]
+pub struct RoutePatterns {
+    patterns: Vec<(String, RoutePattern)>,
+}
+
+/// The values captured from a request path by a pattern
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Captures {
+    values: Vec<(String, String)>,
+}
+
+impl RoutePattern {
+    /// Whether the source of a rule is a pattern rather than a plain path
+    pub fn is_pattern(source: &str) -> bool {
+        source.starts_with('^')
+            || source
+                .split('/')
+                .any(|segment| segment == "*" || segment.starts_with(':'))
+    }
+
+    /// Parses the source of a rule
+    pub fn parse(source: &str) -> Result<Self, ChimneyError> {
+        let invalid = |message: String| ChimneyError::InvalidRoutePattern {
+            pattern: source.to_string(),
+            message,
+        };
+
+        if source.starts_with('^') {
+            let regex = Regex::new(source).map_err(|e| invalid(e.to_string()))?;
+            return Ok(RoutePattern::Regex(regex));
+        }
+
+        if !Self::is_pattern(source) {
+            return Ok(RoutePattern::Exact(source.to_string()));
+        }
+
+        let parts = source.split('/').collect::<Vec<_>>();
+        let mut segments = Vec::with_capacity(parts.len());
+        for (index, part) in parts.iter().enumerate() {
+            let segment = if *part == "*" {
+                if index != parts.len() - 1 {
+                    return Err(invalid(
+                        "`*` is only allowed as the last segment".to_string(),
+                    ));
+                }
+                PatternSegment::Splat
+            } else if let Some(name) = part.strip_prefix(':') {
+                if !is_identifier(name) {
+                    return Err(invalid(format!("`:{name}` is not a valid parameter name")));
+                }
+                PatternSegment::Param(name.to_string())
+            } else {
+                PatternSegment::Literal(part.to_string())
+            };
+
+            segments.push(segment);
+        }
+
+        Ok(RoutePattern::Segments(segments))
+    }
+
+    /// Matches a request path, returning the captured values on success
+    ///
+    /// A single trailing slash is optional, so `/posts/:slug` also matches `/posts/hello/`.
+    pub fn matches(&self, path: &str) -> Option<Captures> {
+        self.matches_exactly(path).or_else(|| {
+            let trimmed = path
+                .strip_suffix('/')
+                .filter(|trimmed| !trimmed.is_empty())?;
+            self.matches_exactly(trimmed)
+        })
+    }
+
+    fn matches_exactly(&self, path: &str) -> Option<Captures> {
+        match self {
+            RoutePattern::Exact(exact) => (exact == path).then(Captures::default),
+            RoutePattern::Regex(regex) => {
+                let matched = regex.captures(path)?;
+                let mut captures = Captures::default();
+
+                for (index, name) in regex.capture_names().enumerate().skip(1) {
+                    // Optional groups that didn't participate in the match are empty
+                    let value = matched.get(index).map_or("", |value| value.as_str());
+
+                    captures.push(index.to_string(), value);
+                    if let Some(name) = name {
+                        captures.push(name, value);
+                    }
+                }
+
+                Some(captures)
+            }
+            RoutePattern::Segments(segments) => {
+                let mut captures = Captures::default();
+                let mut rest = path;
+
+                // Parameters and the splat are also numbered in order, like regex groups
+                let mut position = 0;
+                let mut capture = |captures: &mut Captures, name: &str, value: &str| {
+                    position += 1;
+                    captures.push(name, value);
+                    captures.push(position.to_string(), value);
+                };
+
+                for (index, segment) in segments.iter().enumerate() {
+                    // The leading slash produces an empty first segment
+                    let (part, remaining) = match (index, rest.split_once('/')) {
+                        (0, _) => match rest.strip_prefix('/') {
+                            Some(remaining) => ("", Some(remaining)),
+                            None => (rest, None),
+                        },
+                        (_, Some((part, remaining))) => (part, Some(remaining)),
+                        (_, None) => (rest, None),
+                    };
+
+                    match segment {
+                        PatternSegment::Splat => {
+                            capture(&mut captures, "splat", rest);
+                            return Some(captures);
+                        }
+                        PatternSegment::Literal(literal) if literal == part => {}
+                        PatternSegment::Param(name) if !part.is_empty() => {
+                            capture(&mut captures, name, part);
+                        }
+                        _ => return None,
+                    }
+
+                    match remaining {
+                        Some(remaining) => rest = remaining,
+                        None => {
+                            // `/blog/*` also matches `/blog`, with an empty splat
+                            return match &segments[index + 1..] {
+                                [] => Some(captures),
+                                [PatternSegment::Splat] => {
+                                    capture(&mut captures, "splat", "");
+                                    Some(captures)
+                                }
+                                _ => None,
+                            };
+                        }
+                    }
+                }
+
+                // There is more to the path than the pattern covers
+                None
+            }
+        }
+    }
+}
+
+impl RoutePatterns {
+    /// Compiles the sources that are patterns, skipping any invalid ones
+    ///
+    /// Sites loaded from a configuration reject invalid patterns up front, see
+    /// [`Site::from_table`](super::Site::from_table).
+    pub fn compile<'a>(sources: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut patterns = Self::default();
+        for source in sources {
+            match RoutePattern::parse(source) {
+                Ok(pattern) => patterns.push(source, pattern),
+                Err(e) => debug!("Skipping invalid pattern: {e}"),
+            }
+        }
+
+        patterns
+    }
+
+    /// Adds the compiled source of a rule, plain paths are skipped since they are looked up
+    /// directly
+    pub fn push(&mut self, source: &str, pattern: RoutePattern) {
+        if !matches!(pattern, RoutePattern::Exact(_)) {
+            self.patterns.push((source.to_string(), pattern));
+        }
+    }
+
+    /// Returns the source of the first pattern matching a request path, along with the captured
+    /// values
+    pub fn find(&self, path: &str) -> Option<(&str, Captures)> {
+        self.patterns.iter().find_map(|(source, pattern)| {
+            pattern
+                .matches(path)
+                .map(|captures| (source.as_str(), captures))
+        })
+    }
+
+    /// Returns the number of compiled patterns
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Whether none of the sources are patterns
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+impl Captures {
+    fn push(&mut self, name: impl Into<String>, value: &str) {
+        self.values.push((name.into(), value.to_string()));
+    }
+
+    /// Returns the value captured under the given name (`splat`, a parameter name or the index
+    /// or name of a regex group)
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether nothing was captured
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Fills the captured values into the target of a rule
+    ///
+    /// `:name` is replaced with a parameter, `*` and `:splat` with the splat, and `$1`, `$name`
+    /// or `${name}` with a regex group (or the parameters and splat, numbered in order).
+    /// Placeholders without a matching capture are kept as is.
+    ///
+    /// Captured values are percent-encoded where they would change the meaning of the target,
+    /// and a target that only starts with `//` because of a captured value (which browsers
+    /// treat as a different host) is collapsed to a single leading slash.
+    pub fn substitute(&self, target: &str) -> String {
+        if self.is_empty() {
+            return target.to_string();
+        }
+
+        let mut output = String::with_capacity(target.len());
+        let mut rest = target;
+
+        while let Some(index) = rest.find([':', '$', '*']) {
+            output.push_str(&rest[..index]);
+            let marker = &rest[index..index + 1];
+            let after = &rest[index + 1..];
+
+            let (name, consumed) = match marker {
+                "*" => (Some("splat"), 0),
+                "$" if after.starts_with('{') => match after.find('}') {
+                    Some(end) => (Some(&after[1..end]), end + 1),
+                    None => (None, 0),
+                },
+                _ => {
+                    let end = after
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(after.len());
+                    // `:` is only followed by parameter names, which can't start with a digit (so
+                    // ports in absolute URLs are left alone)
+                    let name = &after[..end];
+                    let valid = match marker {
+                        ":" => is_identifier(name),
+                        _ => !name.is_empty(),
+                    };
+                    (valid.then_some(name), end)
+                }
+            };
+
+            match name.and_then(|name| self.get(name)) {
+                Some(value) => {
+                    output.extend(utf8_percent_encode(value, CAPTURE_VALUE));
+                    rest = &after[consumed..];
+                }
+                None => {
+                    output.push_str(marker);
+                    rest = after;
+                }
+            }
+        }
+
+        output.push_str(rest);
+
+        // `/go/* -> /:splat` must not redirect `/go//evil.com` to `//evil.com`
+        if target.starts_with('/') && !target.starts_with("//") && output.starts_with("//") {
+            return format!("/{}", output.trim_start_matches('/'));
+        }
+
+        output
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -1,12 +1,26 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::{Component, Path},
+};
 
+use hyper::header::{HeaderName, HeaderValue};
+use indexmap::IndexMap;
 use log::debug;
 use serde::{Deserialize, Serialize};
 use toml::Table;
 
-use crate::{error::ChimneyError, with_leading_slash};
+use crate::{
+    config::netlify,
+    error::ChimneyError,
+    filesystem::Filesystem,
+    server::{auth, mimetype::is_valid_mime_type},
+    with_leading_slash,
+};
 
-use super::{Certificate, Domain, DomainIndex};
+use super::{
+    AccessRules, Auth, Autoindex, Captures, Certificate, CleanUrls, Compression, Domain,
+    DomainIndex, HeaderRule, I18n, ImageVariants, RoutePattern, RoutePatterns, Template, Templates,
+};
 
 /// Per-site HTTPS configuration overrides.
 ///
@@ -74,16 +88,91 @@ impl Https {
     }
 }
 
+/// The status codes a redirect rule can respond with
+pub const REDIRECT_STATUS_CODES: [u16; 6] = [301, 302, 303, 307, 308, 410];
+
+/// How the query string of a request is carried over to the target of a redirect
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryHandling {
+    /// Keep the request's query string, unless the target has its own (default)
+    #[default]
+    Preserve,
+
+    /// Combine both, parameters of the target take precedence over the request's
+    Merge,
+
+    /// Drop the request's query string
+    Drop,
+}
+
+impl QueryHandling {
+    /// Whether this is the default, keeping the request's query string
+    pub fn is_preserve(&self) -> bool {
+        *self == QueryHandling::Preserve
+    }
+
+    /// Applies the request's query string (without the leading `?`) to a target
+    pub fn apply(&self, target: &str, query: Option<&str>) -> String {
+        let query = query.unwrap_or_default();
+        if query.is_empty() || *self == QueryHandling::Drop {
+            return target.to_string();
+        }
+
+        // The query string has to come before the fragment, if there is one
+        let (target, fragment) = match target.split_once('#') {
+            Some((target, fragment)) => (target, Some(fragment)),
+            None => (target, None),
+        };
+
+        let mut output = match (self, target.split_once('?')) {
+            (_, None) => format!("{target}?{query}"),
+            (QueryHandling::Merge, Some((_, existing))) => {
+                let keys = existing
+                    .split('&')
+                    .map(|pair| pair.split('=').next().unwrap_or_default())
+                    .collect::<Vec<_>>();
+
+                let extra = query
+                    .split('&')
+                    .filter(|pair| {
+                        let key = pair.split('=').next().unwrap_or_default();
+                        !pair.is_empty() && !keys.contains(&key)
+                    })
+                    .collect::<Vec<_>>();
+
+                match (existing.is_empty(), extra.is_empty()) {
+                    (_, true) => target.to_string(),
+                    (true, false) => format!("{target}{}", extra.join("&")),
+                    (false, false) => format!("{target}&{}", extra.join("&")),
+                }
+            }
+            (_, Some(_)) => target.to_string(),
+        };
+
+        if let Some(fragment) = fragment {
+            output.push('#');
+            output.push_str(fragment);
+        }
+
+        output
+    }
+}
+
 /// Represents a redirect rule found for a path
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum RedirectRule {
-    /// A redirect rule with a target URL
+    /// A redirect rule with a target URL, which can contain variables like `{request.query}`
     Target(String),
 
     /// A redirect rule with a configuration
     Config {
-        /// The target URL or path to redirect to
+        /// The target URL or path to redirect to, which can contain variables like
+        /// `{request.host}` or `{env.NAME}`
+        ///
+        /// This can only be left out for `410 Gone`, which has no target.
+        #[serde(default)]
         to: String,
 
         /// Whether the redirect is a temporary redirect
@@ -98,6 +187,25 @@ pub enum RedirectRule {
         /// a form or other actions that change state.
         #[serde(default = "RedirectRule::default_replay")]
         replay: bool,
+
+        /// An explicit status code (301, 302, 303, 307, 308 or 410), which takes precedence over
+        /// `temporary` and `replay`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+
+        /// How the query string of the request is carried over to the target (default:
+        /// "preserve")
+        #[serde(default, skip_serializing_if = "QueryHandling::is_preserve")]
+        query: QueryHandling,
+
+        /// Whether a target path (e.g. `/new`) is sent as an absolute URL built from the
+        /// request's scheme and host (default: false)
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        absolute: bool,
+
+        /// Whether the rule only applies to paths that don't match a file (default: false)
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        files_first: bool,
     },
 }
 
@@ -108,9 +216,18 @@ impl RedirectRule {
             to,
             temporary,
             replay,
+            status: None,
+            query: QueryHandling::default(),
+            absolute: false,
+            files_first: false,
         }
     }
 
+    /// Constructs a `RedirectRule` that responds with `410 Gone`
+    pub fn gone() -> Self {
+        Self::new(String::new(), false, false).with_status(410)
+    }
+
     pub fn default_temporary_redirect() -> bool {
         false
     }
@@ -135,6 +252,57 @@ impl RedirectRule {
         }
     }
 
+    /// Returns the status code the redirect responds with
+    ///
+    /// Without an explicit status code, this is derived from `temporary` and `replay`:
+    ///
+    /// | temporary | replay | status                 |
+    /// |-----------|--------|------------------------|
+    /// | false     | false  | 301 Moved Permanently  |
+    /// | true      | false  | 302 Found              |
+    /// | false     | true   | 308 Permanent Redirect |
+    /// | true      | true   | 307 Temporary Redirect |
+    pub fn status_code(&self) -> u16 {
+        if let RedirectRule::Config {
+            status: Some(status),
+            ..
+        } = self
+        {
+            return *status;
+        }
+
+        match (self.is_temporary(), self.is_replay()) {
+            (true, true) => 307,
+            (false, true) => 308,
+            (true, false) => 302,
+            (false, false) => 301,
+        }
+    }
+
+    /// How the query string of the request is carried over to the target
+    pub fn query_handling(&self) -> QueryHandling {
+        match self {
+            RedirectRule::Target(_) => QueryHandling::default(),
+            RedirectRule::Config { query, .. } => *query,
+        }
+    }
+
+    /// Whether target paths are sent as absolute URLs
+    pub fn is_absolute(&self) -> bool {
+        match self {
+            RedirectRule::Target(_) => false,
+            RedirectRule::Config { absolute, .. } => *absolute,
+        }
+    }
+
+    /// Whether the rule only applies to paths that don't match a file
+    pub fn is_files_first(&self) -> bool {
+        match self {
+            RedirectRule::Target(_) => false,
+            RedirectRule::Config { files_first, .. } => *files_first,
+        }
+    }
+
     /// Returns the target URL or path of the redirect rule
     pub fn target(&self) -> String {
         match self {
@@ -142,14 +310,173 @@ impl RedirectRule {
             RedirectRule::Config { to, .. } => to.clone(),
         }
     }
+
+    /// Returns the same rule with a different target
+    pub fn with_target(mut self, target: String) -> Self {
+        match &mut self {
+            RedirectRule::Target(to) | RedirectRule::Config { to, .. } => *to = target,
+        }
+
+        self
+    }
+
+    /// Sets an explicit status code for the redirect
+    pub fn with_status(self, code: u16) -> Self {
+        let mut rule = self.into_config();
+        if let RedirectRule::Config { status, .. } = &mut rule {
+            *status = Some(code);
+        }
+
+        rule
+    }
+
+    /// Sets how the query string of the request is carried over to the target
+    pub fn with_query(self, handling: QueryHandling) -> Self {
+        let mut rule = self.into_config();
+        if let RedirectRule::Config { query, .. } = &mut rule {
+            *query = handling;
+        }
+
+        rule
+    }
+
+    /// Sets whether target paths are sent as absolute URLs
+    pub fn with_absolute(self, value: bool) -> Self {
+        let mut rule = self.into_config();
+        if let RedirectRule::Config { absolute, .. } = &mut rule {
+            *absolute = value;
+        }
+
+        rule
+    }
+
+    /// Sets whether the rule only applies to paths that don't match a file
+    pub fn with_files_first(self, value: bool) -> Self {
+        let mut rule = self.into_config();
+        if let RedirectRule::Config { files_first, .. } = &mut rule {
+            *files_first = value;
+        }
+
+        rule
+    }
+
+    /// Converts the rule to its configuration form, so any of its options can be set
+    fn into_config(self) -> Self {
+        match self {
+            RedirectRule::Target(to) => Self::new(to, false, false),
+            rule => rule,
+        }
+    }
+
+    /// Checks that the status code is supported and that a target is set when one is needed
+    pub fn validate(&self) -> Result<(), String> {
+        let status = self.status_code();
+        if !REDIRECT_STATUS_CODES.contains(&status) {
+            return Err(format!(
+                "`{status}` is not a supported redirect status code (expected one of 301, 302, 303, 307, 308 or 410)"
+            ));
+        }
+
+        if status != 410 && self.target().is_empty() {
+            return Err("a target is required for redirects other than 410".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// How entity tags are generated for files served by a site
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EtagStrategy {
+    /// A weak entity tag derived from the file's size and last modified time (default)
+    ///
+    /// This is cheap to compute since it only requires the file's metadata.
+    #[default]
+    Weak,
+
+    /// A strong entity tag derived from a SHA-256 hash of the file's content
+    ///
+    /// Tags are cached by the file's size and last modified time, so a file is only read again
+    /// to compute its tag once it changes. Files whose last modified time is unknown are read on
+    /// every request.
+    Strong,
+
+    /// Do not send entity tags at all
+    Disabled,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Whether request paths are expected to end with a slash
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    /// Paths to directories are redirected to end with a slash (`/docs` to `/docs/`), so relative
+    /// links in their index pages resolve against the directory
+    Always,
+
+    /// Paths ending with a slash are redirected to the path without it (`/docs/` to `/docs`)
+    Never,
+
+    /// Paths are served as requested, with or without a slash (default)
+    #[default]
+    Preserve,
+}
+
+impl TrailingSlash {
+    /// Returns the path a request should be redirected to, if it doesn't follow the policy
+    ///
+    /// `is_directory` is whether the path points to a directory, which only matters for
+    /// [`TrailingSlash::Always`]. The root path (`/`) is never redirected.
+    pub fn normalize(&self, path: &str, is_directory: bool) -> Option<String> {
+        // Collapsing the leading slashes keeps `//example.com/` from turning into a
+        // protocol-relative redirect to another host
+        let trimmed = path.trim_matches('/');
+        if trimmed.is_empty() {
+            return None;
+        }
+
+        match self {
+            Self::Always if is_directory && !path.ends_with('/') => Some(format!("/{trimmed}/")),
+            Self::Never if path.ends_with('/') => Some(format!("/{trimmed}")),
+            _ => None,
+        }
+    }
+}
+
+/// Which symlinks are followed when serving files from a site's root
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Symlinks are followed wherever they point to
+    Follow,
+
+    /// Symlinks are followed as long as they point to something inside the site's root (default)
+    #[default]
+    WithinRoot,
+
+    /// Files are only served if no symlink has to be followed to reach them from the site's root
+    Never,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged)]
 /// Represents a rewrite rule found for a path
 pub enum RewriteRule {
     /// A rewrite rule with a target URL
     Target(String),
+
+    /// A rewrite rule with a configuration
+    Config {
+        /// The target path to rewrite to
+        to: String,
+
+        /// Whether the rule only applies to paths that don't match a file (default: false)
+        ///
+        /// This makes it possible to rewrite everything under `/app/*` to a single page, while
+        /// still serving the assets that exist under `/app/`.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        files_first: bool,
+    },
 }
 
 impl RewriteRule {
@@ -161,11 +488,44 @@ impl RewriteRule {
     /// Returns the target URL or path of the rewrite rule
     pub fn target(&self) -> String {
         match self {
-            RewriteRule::Target(target) => target.to_string(),
+            RewriteRule::Target(target) | RewriteRule::Config { to: target, .. } => {
+                target.to_string()
+            }
+        }
+    }
+
+    /// Whether the rule only applies to paths that don't match a file
+    pub fn is_files_first(&self) -> bool {
+        match self {
+            RewriteRule::Target(_) => false,
+            RewriteRule::Config { files_first, .. } => *files_first,
+        }
+    }
+
+    /// Returns the same rule with a different target
+    pub fn with_target(self, target: String) -> Self {
+        match self {
+            RewriteRule::Target(_) => RewriteRule::Target(target),
+            RewriteRule::Config { files_first, .. } => RewriteRule::Config {
+                to: target,
+                files_first,
+            },
         }
     }
+
+    /// Sets whether the rule only applies to paths that don't match a file
+    pub fn with_files_first(self, files_first: bool) -> Self {
+        let (RewriteRule::Target(to) | RewriteRule::Config { to, .. }) = self;
+        RewriteRule::Config { to, files_first }
+    }
 }
 
+/// The methods every site responds to, regardless of its `allowed_methods`
+pub const DEFAULT_ALLOWED_METHODS: [&str; 3] = ["GET", "HEAD", "OPTIONS"];
+
+/// The index file served when a directory is requested, for sites that don't configure one
+pub const DEFAULT_INDEX_FILE: &str = "index.html";
+
 /// Represents a site configuration
 ///
 /// A site configuration could be:
@@ -195,28 +555,157 @@ pub struct Site {
     /// For example, if a request is made to `/`, the server will look for this file in the root directory. If it was made to `/about/`, it will look for this file in the `/about/` directory.
     pub default_index_file: Option<String>,
 
+    /// The index files to try, in order, when a directory is requested
+    ///
+    /// The first one that exists in the directory is served (e.g. `["index.html", "index.htm",
+    /// "README.html"]`). This cannot be combined with `default_index_file`.
+    pub default_index_files: Option<Vec<String>>,
+
+    /// Whether directories without an index file are served as a listing of their content
+    #[serde(default)]
+    pub autoindex: Autoindex,
+
+    /// Whether paths without a matching file are resolved to `.html` files, so `/about` serves
+    /// `about.html`
+    ///
+    /// With `redirect = true`, `/about.html` and `/about/index.html` are also redirected to
+    /// `/about`.
+    #[serde(default)]
+    pub clean_urls: CleanUrls,
+
+    /// Whether paths are redirected to end with a slash (`"always"`, only for directories), to
+    /// never end with one (`"never"`), or served as requested (default: "preserve")
+    #[serde(default)]
+    pub trailing_slash: TrailingSlash,
+
+    /// Paths that are never served, on top of dotfiles and chimney's own configuration files,
+    /// and the exceptions to them
+    ///
+    /// For example, `deny = ["/drafts/**"]` hides everything in `drafts`, and
+    /// `allow = ["/.well-known/**"]` (the default) keeps `.well-known` reachable.
+    #[serde(default)]
+    pub access: AccessRules,
+
+    /// The users allowed to access the site, or only some of its paths, with HTTP Basic
+    /// authentication
+    ///
+    /// For example, `paths = ["/internal/**"]` with `users = { alice = "$2y$..." }` only lets
+    /// `alice` into `internal`. Without any `paths`, the whole site is protected.
+    #[serde(default)]
+    pub auth: Auth,
+
+    /// Which symlinks are followed: `"follow"` (all of them), `"within_root"` (only those that
+    /// point inside the site's root) or `"never"` (default: "within_root")
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+
+    /// Custom error pages, mapping a status code to an HTML file relative to the site's `root`
+    ///
+    /// For example, `404 = "404.html"` serves `404.html` with a `404 Not Found` status. Status
+    /// codes without a page get a built-in default page, still with the correct status code.
+    #[serde(default)]
+    pub error_pages: HashMap<String, String>,
+
+    /// MIME types by file extension, taking precedence over the built-in and configured types
+    ///
+    /// For example, `ts = "video/mp2t"` serves `.ts` files as video streams instead of
+    /// TypeScript. Text types get the configured charset, unless they set their own
+    /// (`txt = "text/plain; charset=iso-8859-1"`).
+    #[serde(default)]
+    pub mime_types: HashMap<String, String>,
+
+    /// Whether the MIME type of files with a missing or unknown extension is guessed from their
+    /// first bytes (default: false)
+    ///
+    /// Only HTML, PNG, JPEG, GIF, WebP, PDF, JSON and WebAssembly are recognized, anything else
+    /// is still served as `application/octet-stream`.
+    #[serde(default)]
+    pub content_sniffing: bool,
+
+    /// The image formats served in place of the requested images when the client accepts them,
+    /// like `formats = ["avif", "webp"]` to serve `photo.avif` or `photo.webp` for `photo.jpg`
+    #[serde(default)]
+    pub image_variants: ImageVariants,
+
+    /// The locales the site is available in, each served from its own directory (`/en/`,
+    /// `/fr/`)
+    ///
+    /// Requests for `/` are redirected or rewritten to the locale picked from the
+    /// `Accept-Language` header, a cookie or the default locale.
+    #[serde(default)]
+    pub i18n: I18n,
+
     /// The HTTPS configuration for the site
     pub https_config: Option<Https>,
 
+    /// Extra HTTP methods the site responds to, on top of `GET`, `HEAD` and `OPTIONS`
+    ///
+    /// Requests with an allowed method are served exactly like a `GET` request, which is useful to
+    /// for example let a form `POST` to a static "thank you" page. Any other method is answered
+    /// with `405 Method Not Allowed`.
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+
+    /// The strategy used to generate `ETag` headers for served files (default: "weak")
+    #[serde(default)]
+    pub etag: EtagStrategy,
+
+    /// How responses are compressed, covering both precompressed files and on-the-fly compression
+    #[serde(default)]
+    pub compression: Compression,
+
     /// The list of extra headers to include in the response
     /// Variables can be used here to fill in values dynamically from the request or the environment itself
+    ///
+    /// The available variables are `{request.scheme}`, `{request.host}`, `{request.path}`,
+    /// `{request.query}`, `{request.method}` and `{env.NAME}` for environment variables, e.g.
+    /// `X-Release = "{env.RELEASE}"`.
     #[serde(default)]
     pub response_headers: HashMap<String, String>,
 
+    /// Ordered rules that set, append or remove response headers for matching paths or MIME
+    /// types, applied after `response_headers`
+    ///
+    /// For example, `/assets/**` can get a long-lived `Cache-Control` header while `*.html` gets
+    /// `no-cache`.
+    #[serde(default)]
+    pub header_rules: Vec<HeaderRule>,
+
     /// A redirects mapping that maps a source path to a destination path
     /// A redirect is a permanent or temporary redirect from one URL to another, this makes proper
     /// use of the HTTP status codes and conforms to the HTTP standards.
     ///
     /// For example, a request to `/old-path` can be redirected to `/new-path`
+    ///
+    /// Sources can also be patterns with a splat (`/blog/*`), named parameters (`/posts/:slug`)
+    /// or a regular expression starting with `^` (`^/posts/(\d+)$`), whose captures are filled
+    /// into the target (`/news/:splat`, `/articles/:slug`, `/p/$1`). Exact paths always take
+    /// precedence, patterns are then tried in the order they are declared.
     #[serde(default)]
-    pub redirects: HashMap<String, RedirectRule>,
+    pub redirects: IndexMap<String, RedirectRule>,
 
     /// A rewrites mapping that maps a source path to a destination path
     /// A rewrite is a way to change the target of a request without changing the source URL behind the scenes.
     ///
     /// For example, a request to `/old-path` can be rewritten to `/new-path` without the client knowing about it.
+    ///
+    /// Sources can be patterns just like redirects, so `/docs/:version/*` can be rewritten to
+    /// `/docs-:version/*` to map clean public URLs onto the layout of the build output.
     #[serde(default)]
-    pub rewrites: HashMap<String, RewriteRule>,
+    pub rewrites: IndexMap<String, RewriteRule>,
+
+    /// The compiled sources of `redirects` that are patterns, see [`Site::compile_routes`]
+    #[serde(skip_serializing, skip_deserializing)]
+    redirect_patterns: RoutePatterns,
+
+    /// The compiled sources of `rewrites` that are patterns, see [`Site::compile_routes`]
+    #[serde(skip_serializing, skip_deserializing)]
+    rewrite_patterns: RoutePatterns,
+
+    /// The parsed templates of the response headers and redirect targets, see
+    /// [`Site::compile_templates`]
+    #[serde(skip_serializing, skip_deserializing)]
+    templates: Templates,
 }
 
 impl Site {
@@ -240,6 +729,28 @@ impl Site {
         Self::from_table(name, table)
     }
 
+    /// Converts the site to the TOML representation used in a site's `chimney.toml`
+    ///
+    /// Only the settings that differ from the defaults are included, so the result stays readable.
+    pub fn to_toml_string(&self) -> Result<String, ChimneyError> {
+        let error = |e: toml::ser::Error| {
+            ChimneyError::GenericError(format!(
+                "Failed to convert site `{}` to TOML string: {e}",
+                self.name
+            ))
+        };
+
+        let mut table = Table::try_from(self).map_err(error)?;
+        let defaults =
+            Table::try_from(SiteBuilder::new(self.name.clone()).build()).map_err(error)?;
+
+        // The name of a site comes from its directory or its key in the root configuration
+        table.remove("name");
+        table.retain(|key, value| key == "domain_names" || defaults.get(key) != Some(value));
+
+        toml::to_string(&table).map_err(error)
+    }
+
     ///  Constructs a `Site` from a TOML table
     pub fn from_table(name: String, table: Table) -> Result<Self, ChimneyError> {
         let mut site: Self = table.try_into().map_err(|e| ChimneyError::ParseError {
@@ -257,14 +768,443 @@ impl Site {
             });
         }
 
-        Ok(site)
+        if let Some(code) = site.error_pages.keys().find(|code| {
+            !code
+                .parse()
+                .is_ok_and(|code: u16| (400..=599).contains(&code))
+        }) {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.error_pages"),
+                message: format!("`{code}` is not an error status code (expected 400-599)"),
+            });
+        }
+
+        if !matches!(site.access.status, 403 | 404) {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.access.status"),
+                message: format!(
+                    "`{}` can't be used for denied paths (expected 403 or 404)",
+                    site.access.status
+                ),
+            });
+        }
+
+        if HeaderValue::from_str(&auth::challenge(&site.auth.realm)).is_err() {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.auth.realm"),
+                message: format!("`{}` is not a valid realm", site.auth.realm),
+            });
+        }
+
+        if let Some((user, hash)) = site.auth.users.iter().find(|(user, hash)| {
+            user.is_empty() || user.contains(':') || !auth::is_supported_hash(hash)
+        }) {
+            let message = if auth::is_supported_hash(hash) {
+                format!("`{user}` is not a valid user name")
+            } else {
+                "Passwords have to be bcrypt or Argon2 hashes".to_string()
+            };
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.auth.users.{user}"),
+                message,
+            });
+        }
+
+        if !site.auth.paths.is_empty() && !site.auth.is_enabled() {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.auth"),
+                message: "At least one user or an htpasswd file is required".to_string(),
+            });
+        }
+
+        if let Some(file) = &site.auth.htpasswd_file
+            && !Self::is_inside_directory(file)
+        {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.auth.htpasswd_file"),
+                message: format!("`{file}` has to be a relative path inside the site's directory"),
+            });
+        }
+
+        if let Some((extension, mime_type)) = site.mime_types.iter().find(|(_, mime_type)| {
+            !is_valid_mime_type(mime_type) || HeaderValue::from_str(mime_type).is_err()
+        }) {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.mime_types.{extension}"),
+                message: format!("`{mime_type}` is not a valid MIME type"),
+            });
+        }
+
+        if let Some(locale) = site.i18n.locales.iter().find(|locale| {
+            locale.is_empty()
+                || !locale
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }) {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.i18n.locales"),
+                message: format!("`{locale}` is not a valid locale"),
+            });
+        }
+
+        if let Some(locale) = &site.i18n.default_locale
+            && !site.i18n.locales.contains(locale)
+        {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.i18n.default_locale"),
+                message: format!("`{locale}` is not one of the site's locales"),
+            });
+        }
+
+        if let Some(files) = &site.default_index_files {
+            if files.is_empty() {
+                return Err(ChimneyError::ConfigError {
+                    field: format!("sites.{name}.default_index_files"),
+                    message: "At least one index file is required".to_string(),
+                });
+            }
+
+            if site.default_index_file.is_some() {
+                return Err(ChimneyError::ConfigError {
+                    field: format!("sites.{name}.default_index_files"),
+                    message: "Cannot be combined with `default_index_file`".to_string(),
+                });
+            }
+        }
+
+        // Templates are only rendered per request, so any mistake in them has to be caught here
+        let templates = site
+            .response_headers
+            .iter()
+            .map(|(header, value)| (format!("response_headers.{header}"), value))
+            .chain(
+                site.header_rules
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, rule)| {
+                        rule.set
+                            .iter()
+                            .chain(rule.append.iter())
+                            .map(move |(header, value)| {
+                                (format!("header_rules[{index}].{header}"), value)
+                            })
+                    }),
+            )
+            .chain(site.redirects.iter().map(|(path, rule)| {
+                (
+                    format!("redirects.{path}"),
+                    match rule {
+                        RedirectRule::Target(to) | RedirectRule::Config { to, .. } => to,
+                    },
+                )
+            }));
+
+        for (source, rule) in &site.redirects {
+            let field = format!("sites.{name}.redirects.{source}");
+            let pattern = RoutePattern::parse(source).map_err(|e| ChimneyError::ConfigError {
+                field: field.clone(),
+                message: e.to_string(),
+            })?;
+            rule.validate()
+                .map_err(|message| ChimneyError::ConfigError { field, message })?;
+            site.redirect_patterns.push(source, pattern);
+        }
+
+        for source in site.rewrites.keys() {
+            let pattern = RoutePattern::parse(source).map_err(|e| ChimneyError::ConfigError {
+                field: format!("sites.{name}.rewrites.{source}"),
+                message: e.to_string(),
+            })?;
+            site.rewrite_patterns.push(source, pattern);
+        }
+
+        for (field, template) in templates {
+            Template::parse(template).map_err(|e| ChimneyError::ConfigError {
+                field: format!("sites.{name}.{field}"),
+                message: e.to_string(),
+            })?;
+        }
+
+        for (index, rule) in site.header_rules.iter().enumerate() {
+            let field = format!("sites.{name}.header_rules[{index}]");
+            let names = rule
+                .set
+                .keys()
+                .chain(rule.append.keys())
+                .chain(rule.remove.iter());
+            if let Some(header) = names
+                .into_iter()
+                .find(|header| HeaderName::from_bytes(header.as_bytes()).is_err())
+            {
+                return Err(ChimneyError::ConfigError {
+                    field,
+                    message: format!("`{header}` is not a valid header name"),
+                });
+            }
+
+            if let Some(value) = rule
+                .set
+                .values()
+                .chain(rule.append.values())
+                .find(|value| HeaderValue::from_str(value).is_err())
+            {
+                return Err(ChimneyError::ConfigError {
+                    field,
+                    message: format!("`{value}` is not a valid header value"),
+                });
+            }
+        }
+
+        if let Some(method) = site
+            .allowed_methods
+            .iter()
+            .find(|method| !Self::is_valid_method(method))
+        {
+            return Err(ChimneyError::ConfigError {
+                field: format!("sites.{name}.allowed_methods"),
+                message: format!("`{method}` is not a valid HTTP method"),
+            });
+        }
+
+        site.compile_templates();
+        Ok(site)
+    }
+
+    /// Imports the rules from the `_redirects` and `_headers` files in the site's root directory,
+    /// if there are any
+    ///
+    /// The site's own configuration takes precedence: imported redirects and rewrites are only
+    /// added for sources that aren't configured yet, and imported header rules are applied before
+    /// the configured ones. Importing the same files again doesn't duplicate any rules, so this
+    /// can be called every time the configuration is (re)loaded.
+    ///
+    /// The files are read through the filesystem the site is served from.
+    pub fn import_site_files(
+        &mut self,
+        filesystem: &dyn Filesystem,
+        root: &Path,
+    ) -> Result<(), ChimneyError> {
+        if let Some(input) = self.read_site_file(filesystem, &root.join(netlify::REDIRECTS_FILE))? {
+            let imported = netlify::parse_redirects(&input)?;
+            debug!(
+                "Importing {} redirects and {} rewrites into site '{}'",
+                imported.redirects.len(),
+                imported.rewrites.len(),
+                self.name
+            );
+
+            for (source, rule) in imported.redirects {
+                self.redirects.entry(source).or_insert(rule);
+            }
+
+            for (source, rule) in imported.rewrites {
+                self.rewrites.entry(source).or_insert(rule);
+            }
+
+            self.compile_routes();
+
+            // The fallback file is relative to the site's directory rather than its root
+            if let Some(fallback) = imported.fallback
+                && self.fallback_file.is_none()
+            {
+                let fallback = Path::new(&self.root).join(fallback.trim_start_matches('/'));
+                self.fallback_file = Some(fallback.to_string_lossy().to_string());
+            }
+        }
+
+        if let Some(input) = self.read_site_file(filesystem, &root.join(netlify::HEADERS_FILE))? {
+            let imported = netlify::parse_headers(&input)?
+                .into_iter()
+                .filter(|rule| !self.header_rules.contains(rule))
+                .collect::<Vec<_>>();
+            debug!(
+                "Importing {} header rules into site '{}'",
+                imported.len(),
+                self.name
+            );
+
+            self.header_rules.splice(0..0, imported);
+        }
+
+        self.compile_templates();
+        Ok(())
+    }
+
+    /// Reads a file from the site's root directory, if it exists
+    fn read_site_file(
+        &self,
+        filesystem: &dyn Filesystem,
+        path: &Path,
+    ) -> Result<Option<String>, ChimneyError> {
+        let invalid = |message: String| ChimneyError::ConfigError {
+            field: format!("sites.{}", self.name),
+            message,
+        };
+
+        if !filesystem
+            .exists(path.to_path_buf())
+            .map_err(|e| invalid(e.to_string()))?
+        {
+            return Ok(None);
+        }
+
+        let content = filesystem
+            .read_file(path.to_path_buf())
+            .map_err(|e| invalid(e.to_string()))?;
+        String::from_utf8(content.into_bytes())
+            .map(Some)
+            .map_err(|_| invalid(format!("`{}` is not valid UTF-8", path.display())))
+    }
+
+    /// Loads the users of the site's `htpasswd` file, relative to the site's directory
+    ///
+    /// The file is read through the filesystem the site is served from, once, every time the
+    /// configuration is (re)loaded rather than for every request. It has to be inside the site's
+    /// directory, also once symlinks are resolved.
+    pub fn load_htpasswd_file(
+        &mut self,
+        filesystem: &dyn Filesystem,
+        directory: &Path,
+    ) -> Result<(), ChimneyError> {
+        let Some(file) = self.auth.htpasswd_file.clone() else {
+            return Ok(());
+        };
+
+        let invalid = |message: String| ChimneyError::ConfigError {
+            field: format!("sites.{}.auth.htpasswd_file", self.name),
+            message,
+        };
+
+        let path = directory.join(&file);
+        if !filesystem
+            .stat(path.clone())
+            .is_ok_and(|stat| stat.is_file())
+        {
+            return Err(invalid(format!("`{file}` doesn't exist")));
+        }
+
+        let confined = match (
+            filesystem.canonicalize(path.clone()),
+            filesystem.canonicalize(directory.to_path_buf()),
+        ) {
+            (Ok(path), Ok(directory)) => path.starts_with(directory),
+            _ => false,
+        };
+        if !Self::is_inside_directory(&file) || !confined {
+            return Err(invalid(format!(
+                "`{file}` has to be a relative path inside the site's directory"
+            )));
+        }
+
+        let content = filesystem
+            .read_file(path)
+            .map_err(|e| invalid(e.to_string()))?;
+        let input = String::from_utf8(content.into_bytes())
+            .map_err(|_| invalid(format!("`{file}` is not valid UTF-8")))?;
+        self.auth.htpasswd_users = auth::parse_htpasswd(&input);
+        debug!(
+            "Loaded {} users from the htpasswd file of site '{}'",
+            self.auth.htpasswd_users.len(),
+            self.name
+        );
+
+        Ok(())
+    }
+
+    /// Whether the path, relative to the site's root, must not be served
+    ///
+    /// Besides the paths the site's `access` rules deny, the site's `htpasswd` file is never
+    /// served, not even if the rules allow it.
+    pub fn is_denied(&self, path: &str) -> bool {
+        self.access.is_denied(path) || self.is_htpasswd_file(path)
+    }
+
+    /// Whether the path, relative to the site's root, is the site's `htpasswd` file
+    ///
+    /// Like denied paths, this ignores case, so it also holds on case-insensitive filesystems.
+    fn is_htpasswd_file(&self, path: &str) -> bool {
+        let Some(file) = &self.auth.htpasswd_file else {
+            return false;
+        };
+
+        let components = |path: &Path| {
+            path.components()
+                .filter(|component| !matches!(component, Component::CurDir))
+                .map(|component| component.as_os_str().to_string_lossy().to_lowercase())
+                .collect::<Vec<_>>()
+        };
+
+        let served = Path::new(&self.root).join(path.trim_start_matches('/'));
+        components(&served) == components(Path::new(file))
+    }
+
+    /// Whether a configured path stays inside the directory it is relative to
+    fn is_inside_directory(path: &str) -> bool {
+        let path = Path::new(path);
+        path.components()
+            .any(|component| matches!(component, Component::Normal(_)))
+            && path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    }
+
+    /// Returns the index file to serve when a directory is requested
+    #[deprecated(note = "sites can try several index files, use `index_files` instead")]
+    pub fn index_file(&self) -> String {
+        self.index_files()[0].to_string()
+    }
+
+    /// Returns the index files to try, in order, when a directory is requested
+    pub fn index_files(&self) -> Vec<&str> {
+        match (&self.default_index_files, &self.default_index_file) {
+            (Some(files), _) if !files.is_empty() => files.iter().map(String::as_str).collect(),
+            (_, Some(file)) => vec![file.as_str()],
+            _ => vec![DEFAULT_INDEX_FILE],
+        }
+    }
+
+    /// Returns the configured error page for a status code, if any
+    pub fn error_page(&self, code: u16) -> Option<&str> {
+        self.error_pages.get(&code.to_string()).map(String::as_str)
+    }
+
+    /// Returns every HTTP method the site responds to, in uppercase and without duplicates
+    pub fn allowed_methods(&self) -> Vec<String> {
+        let mut methods: Vec<String> = DEFAULT_ALLOWED_METHODS
+            .iter()
+            .map(|method| method.to_string())
+            .collect();
+
+        for method in &self.allowed_methods {
+            let method = method.trim().to_ascii_uppercase();
+            if Self::is_valid_method(&method) && !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+
+        methods
     }
 
-    /// Returns the index file to serve when a directory is requested
-    pub fn index_file(&self) -> String {
-        self.default_index_file
-            .clone()
-            .unwrap_or_else(|| "index.html".to_string())
+    /// Whether the site responds to the given HTTP method
+    pub fn is_method_allowed(&self, method: &str) -> bool {
+        // Methods are case-sensitive, but configured ones are compared as if they were uppercase
+        // since that is what every client sends for the standard methods anyway. This runs for
+        // every request, so unlike `allowed_methods` it doesn't collect anything.
+        DEFAULT_ALLOWED_METHODS.contains(&method)
+            || (!method.bytes().any(|b| b.is_ascii_lowercase())
+                && self
+                    .allowed_methods
+                    .iter()
+                    .any(|allowed| allowed.trim().eq_ignore_ascii_case(method)))
+    }
+
+    /// HTTP methods are tokens, so only a limited set of characters is allowed
+    fn is_valid_method(method: &str) -> bool {
+        let method = method.trim();
+        !method.is_empty()
+            && method
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
     }
 }
 
@@ -297,6 +1237,8 @@ impl Site {
             matcher, self.name, rule
         );
         self.redirects.insert(matcher, rule);
+        self.compile_routes();
+        self.compile_templates();
     }
 
     /// Adds a rewrite rule to the site
@@ -308,6 +1250,38 @@ impl Site {
             matcher, self.name, rule
         );
         self.rewrites.insert(matcher, rule);
+        self.compile_routes();
+    }
+
+    /// Compiles the sources of the redirect and rewrite rules that are patterns
+    ///
+    /// This happens whenever the rules are loaded or added through the site's methods, rules
+    /// inserted into `redirects` or `rewrites` directly need this to be called afterwards.
+    /// Invalid patterns are skipped.
+    pub fn compile_routes(&mut self) {
+        self.redirect_patterns = RoutePatterns::compile(self.redirects.keys());
+        self.rewrite_patterns = RoutePatterns::compile(self.rewrites.keys());
+    }
+
+    /// Parses the templates of the response headers, header rules and redirect targets
+    ///
+    /// Like [`Site::compile_routes`], this happens whenever they are loaded or added through the
+    /// site's methods. Templates that weren't parsed up front still work, they are just parsed
+    /// for every response.
+    pub fn compile_templates(&mut self) {
+        let headers = self.response_headers.values().cloned();
+        let rules = self
+            .header_rules
+            .iter()
+            .flat_map(|rule| rule.set.values().chain(rule.append.values()).cloned());
+        let redirects = self.redirects.values().map(RedirectRule::target);
+
+        self.templates = Templates::compile(headers.chain(rules).chain(redirects));
+    }
+
+    /// Returns the parsed templates of the response headers and redirect targets
+    pub fn templates(&self) -> &Templates {
+        &self.templates
     }
 
     /// Adds a response header to the site
@@ -317,6 +1291,7 @@ impl Site {
             header, value, self.name
         );
         self.response_headers.insert(header, value);
+        self.compile_templates();
     }
 
     /// Removes a response header from the site
@@ -396,8 +1371,19 @@ impl Site {
 }
 
 impl Site {
-    /// Finds a redirect rule for a given path
+    /// Finds a redirect rule for a given path, with the captures of pattern rules already filled
+    /// into the target
     pub fn find_redirect_rule(&self, path: &str) -> Option<RedirectRule> {
+        self.match_redirect_rule(path).map(|(rule, captures)| {
+            let target = captures.substitute(&rule.target());
+            rule.with_target(target)
+        })
+    }
+
+    /// Finds a redirect rule for a given path along with the values captured by its pattern
+    ///
+    /// Exact paths take precedence over patterns, which are tried in the order they are declared.
+    pub fn match_redirect_rule(&self, path: &str) -> Option<(RedirectRule, Captures)> {
         debug!("Finding redirect for path: {path}");
 
         if path.is_empty() {
@@ -422,18 +1408,21 @@ impl Site {
         }
 
         debug!("Looking for redirect key: {redirect_key}");
-        match self.redirects.get(&redirect_key) {
-            Some(rule) => {
-                debug!("Found redirect rule for path: {path}, rule: {rule:?}");
-                Some(rule.clone())
-            }
-            _ => {
-                debug!("No redirect found for path: {path}");
-                None
-            }
+        if let Some((rule, captures)) =
+            match_rule(&self.redirects, &self.redirect_patterns, &redirect_key)
+        {
+            debug!("Found redirect rule for path: {path}, rule: {rule:?}");
+            return Some((rule.clone(), captures));
         }
+
+        debug!("No redirect found for path: {path}");
+        None
     }
 
+    /// Finds a rewrite rule for a given path, with the captures of pattern rules already filled
+    /// into the target
+    ///
+    /// Exact paths take precedence over patterns, which are tried in the order they are declared.
     pub fn find_rewrite_rule(&self, path: &str) -> Option<RewriteRule> {
         debug!("Finding rewrite for path: {path}");
         if path.is_empty() {
@@ -457,10 +1446,11 @@ impl Site {
         }
 
         debug!("Looking for rewrite key: {rewrite_key}");
-        match self.rewrites.get(&rewrite_key) {
-            Some(rule) => {
+        match match_rule(&self.rewrites, &self.rewrite_patterns, &rewrite_key) {
+            Some((rule, captures)) => {
                 debug!("Found rewrite rule for path: {path}, rule: {rule:?}");
-                Some(rule.clone())
+                let target = captures.substitute(&rule.target());
+                Some(rule.clone().with_target(target))
             }
             _ => {
                 debug!("No rewrite found for path: {path}");
@@ -470,6 +1460,25 @@ impl Site {
     }
 }
 
+/// Finds the rule whose source matches the path, along with the values captured by its pattern
+///
+/// Exact paths take precedence over patterns, which are tried in the order they are declared.
+/// Like patterns, exact paths also match with a single trailing slash.
+fn match_rule<'a, R>(
+    rules: &'a IndexMap<String, R>,
+    patterns: &RoutePatterns,
+    path: &str,
+) -> Option<(&'a R, Captures)> {
+    let exact = |path: &str| rules.get(path).filter(|_| !RoutePattern::is_pattern(path));
+    let trimmed = path.strip_suffix('/').filter(|trimmed| !trimmed.is_empty());
+    if let Some(rule) = exact(path).or_else(|| trimmed.and_then(exact)) {
+        return Some((rule, Captures::default()));
+    }
+
+    let (source, captures) = patterns.find(path)?;
+    rules.get(source).map(|rule| (rule, captures))
+}
+
 #[derive(Default, Debug, Deserialize, Serialize, Clone)]
 pub struct Sites {
     /// The list of sites in the configuration
@@ -640,10 +1649,26 @@ pub struct SiteBuilder {
     domain_names: Vec<String>,
     fallback_file: Option<String>,
     default_index_file: Option<String>,
+    default_index_files: Option<Vec<String>>,
+    autoindex: Autoindex,
+    clean_urls: CleanUrls,
+    trailing_slash: TrailingSlash,
+    access: AccessRules,
+    auth: Auth,
+    symlinks: SymlinkPolicy,
+    error_pages: HashMap<String, String>,
+    mime_types: HashMap<String, String>,
+    content_sniffing: bool,
+    image_variants: ImageVariants,
+    i18n: I18n,
     https_config: Option<Https>,
+    allowed_methods: Vec<String>,
+    etag: EtagStrategy,
+    compression: Compression,
     response_headers: HashMap<String, String>,
-    redirects: HashMap<String, RedirectRule>,
-    rewrites: HashMap<String, RewriteRule>,
+    header_rules: Vec<HeaderRule>,
+    redirects: IndexMap<String, RedirectRule>,
+    rewrites: IndexMap<String, RewriteRule>,
 }
 
 impl SiteBuilder {
@@ -657,10 +1682,26 @@ impl SiteBuilder {
             domain_names: Vec::new(),
             fallback_file: None,
             default_index_file: None,
+            default_index_files: None,
+            autoindex: Autoindex::default(),
+            clean_urls: CleanUrls::default(),
+            trailing_slash: TrailingSlash::default(),
+            access: AccessRules::default(),
+            auth: Auth::default(),
+            symlinks: SymlinkPolicy::default(),
+            error_pages: HashMap::new(),
+            mime_types: HashMap::new(),
+            content_sniffing: false,
+            image_variants: ImageVariants::default(),
+            i18n: I18n::default(),
             https_config: None,
+            allowed_methods: Vec::new(),
+            etag: EtagStrategy::default(),
+            compression: Compression::default(),
             response_headers: HashMap::new(),
-            redirects: HashMap::new(),
-            rewrites: HashMap::new(),
+            header_rules: Vec::new(),
+            redirects: IndexMap::new(),
+            rewrites: IndexMap::new(),
         }
     }
 
@@ -753,6 +1794,30 @@ impl SiteBuilder {
     /// ```
     pub fn default_index_file(mut self, file: impl Into<String>) -> Self {
         self.default_index_file = Some(file.into());
+        self.default_index_files = None;
+        self
+    }
+
+    /// Sets the index files tried, in order, for directory requests.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::SiteBuilder;
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .default_index_files(["index.html", "index.htm", "default.html"])
+    ///     .build();
+    ///
+    /// assert_eq!(site.index_files(), ["index.html", "index.htm", "default.html"]);
+    /// ```
+    pub fn default_index_files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.default_index_files = Some(files.into_iter().map(Into::into).collect());
+        self.default_index_file = None;
         self
     }
 
@@ -811,6 +1876,287 @@ impl SiteBuilder {
         self
     }
 
+    /// Sets the directory listing configuration for the site.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{Autoindex, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .autoindex(Autoindex::default().with_path("/artifacts"))
+    ///     .build();
+    ///
+    /// assert!(site.autoindex.is_enabled_for("/artifacts/v1"));
+    /// assert!(!site.autoindex.is_enabled_for("/"));
+    /// ```
+    pub fn autoindex(mut self, autoindex: Autoindex) -> Self {
+        self.autoindex = autoindex;
+        self
+    }
+
+    /// Sets the clean URL configuration for the site.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{CleanUrls, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .clean_urls(CleanUrls::enabled().with_redirect(true))
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     site.clean_urls.canonical_path("/about.html", &site.index_files()),
+    ///     Some("/about".to_string())
+    /// );
+    /// ```
+    pub fn clean_urls(mut self, clean_urls: CleanUrls) -> Self {
+        self.clean_urls = clean_urls;
+        self
+    }
+
+    /// Sets the trailing slash policy for the site.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{SiteBuilder, TrailingSlash};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .trailing_slash(TrailingSlash::Never)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     site.trailing_slash.normalize("/docs/", true),
+    ///     Some("/docs".to_string())
+    /// );
+    /// ```
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = policy;
+        self
+    }
+
+    /// Sets the rules for paths that are never served.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{AccessRules, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .access(AccessRules::default().deny("/drafts/**").with_status(403))
+    ///     .build();
+    ///
+    /// assert!(site.access.is_denied("/drafts/post.html"));
+    /// assert!(site.access.is_denied("/.env"));
+    /// assert!(!site.access.is_denied("/.well-known/security.txt"));
+    /// ```
+    pub fn access(mut self, access: AccessRules) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Sets the users allowed to access the site, or some of its paths.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{Auth, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .auth(
+    ///         Auth::new("Internal")
+    ///             .path("/internal/**")
+    ///             .with_htpasswd_file(".htpasswd"),
+    ///     )
+    ///     .build();
+    ///
+    /// assert!(site.auth.is_protected("/internal/report.html"));
+    /// assert!(!site.auth.is_protected("/index.html"));
+    /// ```
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Sets which symlinks are followed when serving files.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{SiteBuilder, SymlinkPolicy};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .symlinks(SymlinkPolicy::Never)
+    ///     .build();
+    /// ```
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+
+    /// Sets the error page served for a status code, relative to the site's root.
+    ///
+    /// This method is chainable and can be called multiple times.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::SiteBuilder;
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .error_page(404, "404.html")
+    ///     .error_page(500, "errors/500.html")
+    ///     .build();
+    ///
+    /// assert_eq!(site.error_page(404), Some("404.html"));
+    /// assert_eq!(site.error_page(403), None);
+    /// ```
+    pub fn error_page(mut self, code: u16, page: impl Into<String>) -> Self {
+        self.error_pages.insert(code.to_string(), page.into());
+        self
+    }
+
+    /// Sets the MIME type served for files with the given extension.
+    ///
+    /// This method is chainable and can be called multiple times.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::SiteBuilder;
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .mime_type("ts", "video/mp2t")
+    ///     .mime_type("gmi", "text/gemini")
+    ///     .build();
+    ///
+    /// assert_eq!(site.mime_types["ts"], "video/mp2t");
+    /// ```
+    pub fn mime_type(mut self, extension: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        self.mime_types.insert(extension.into(), mime_type.into());
+        self
+    }
+
+    /// Sets whether the MIME type of files without a known extension is guessed from their content.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::SiteBuilder;
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .content_sniffing(true)
+    ///     .build();
+    ///
+    /// assert!(site.content_sniffing);
+    /// ```
+    pub fn content_sniffing(mut self, enabled: bool) -> Self {
+        self.content_sniffing = enabled;
+        self
+    }
+
+    /// Sets the image formats served in place of the requested images.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{ImageFormat, ImageVariants, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .image_variants(ImageVariants::new([ImageFormat::Avif, ImageFormat::Webp]))
+    ///     .build();
+    ///
+    /// assert_eq!(site.image_variants.formats, [ImageFormat::Avif, ImageFormat::Webp]);
+    /// ```
+    pub fn image_variants(mut self, variants: ImageVariants) -> Self {
+        self.image_variants = variants;
+        self
+    }
+
+    /// Sets the locales the site is available in.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{I18n, LocaleStrategy, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .i18n(
+    ///         I18n::new(["en", "fr", "de"])
+    ///             .with_cookie("locale")
+    ///             .with_strategy(LocaleStrategy::Rewrite),
+    ///     )
+    ///     .build();
+    ///
+    /// assert_eq!(site.i18n.default_locale(), Some("en"));
+    /// ```
+    pub fn i18n(mut self, i18n: I18n) -> Self {
+        self.i18n = i18n;
+        self
+    }
+
+    /// Allows an extra HTTP method for the site, on top of `GET`, `HEAD` and `OPTIONS`.
+    ///
+    /// This method is chainable and can be called multiple times.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::SiteBuilder;
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .allow_method("POST")
+    ///     .build();
+    ///
+    /// assert!(site.is_method_allowed("POST"));
+    /// assert!(!site.is_method_allowed("DELETE"));
+    /// ```
+    pub fn allow_method(mut self, method: impl Into<String>) -> Self {
+        let method = method.into().to_ascii_uppercase();
+        if !self.allowed_methods.contains(&method) {
+            self.allowed_methods.push(method);
+        }
+        self
+    }
+
+    /// Sets the strategy used to generate `ETag` headers for served files.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{EtagStrategy, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .etag(EtagStrategy::Strong)
+    ///     .build();
+    /// ```
+    pub fn etag(mut self, strategy: EtagStrategy) -> Self {
+        self.etag = strategy;
+        self
+    }
+
+    /// Sets the compression configuration for the site.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{Compression, Encoding, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .compression(
+    ///         Compression::default()
+    ///             .with_precompressed([Encoding::Brotli])
+    ///             .with_dynamic(true),
+    ///     )
+    ///     .build();
+    /// ```
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
     /// Adds a response header to the site.
     ///
     /// This method is chainable and can be called multiple times.
@@ -858,6 +2204,31 @@ impl SiteBuilder {
         self
     }
 
+    /// Adds a response header rule to the site, rules are applied in the order they are added.
+    ///
+    /// This method is chainable and can be called multiple times.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{HeaderRule, SiteBuilder};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .header_rule(
+    ///         HeaderRule::path("/assets/**")
+    ///             .set("Cache-Control", "public, max-age=31536000, immutable"),
+    ///     )
+    ///     .header_rule(HeaderRule::path("*.html").set("Cache-Control", "no-cache"))
+    ///     .build();
+    ///
+    /// assert_eq!(site.header_rules.len(), 2);
+    /// assert!(site.header_rules[0].matches("/assets/app.js", "text/javascript"));
+    /// ```
+    pub fn header_rule(mut self, rule: HeaderRule) -> Self {
+        self.header_rules.push(rule);
+        self
+    }
+
     /// Adds a simple redirect rule to the site.
     ///
     /// This method is chainable and can be called multiple times.
@@ -914,6 +2285,24 @@ impl SiteBuilder {
         self
     }
 
+    /// Adds a rewrite rule with full configuration to the site.
+    ///
+    /// This method is chainable and can be called multiple times.
+    ///
+    /// # Example
+    /// ```
+    /// use chimney::config::{SiteBuilder, RewriteRule};
+    ///
+    /// let site = SiteBuilder::new("my-site")
+    ///     .domain("example.com")
+    ///     .rewrite_rule("/app/*", RewriteRule::new("/app.html".to_string()).with_files_first(true))
+    ///     .build();
+    /// ```
+    pub fn rewrite_rule(mut self, from: impl Into<String>, rule: RewriteRule) -> Self {
+        self.rewrites.insert(from.into(), rule);
+        self
+    }
+
     /// Builds the `Site` from the configured options.
     ///
     /// # Example
@@ -930,16 +2319,39 @@ impl SiteBuilder {
     /// assert_eq!(site.root, "./public");
     /// ```
     pub fn build(self) -> Site {
-        Site {
+        let mut site = Site {
             name: self.name,
             root: self.root,
             domain_names: self.domain_names,
             fallback_file: self.fallback_file,
             default_index_file: self.default_index_file,
+            default_index_files: self.default_index_files,
+            autoindex: self.autoindex,
+            clean_urls: self.clean_urls,
+            trailing_slash: self.trailing_slash,
+            access: self.access,
+            auth: self.auth,
+            symlinks: self.symlinks,
+            error_pages: self.error_pages,
+            mime_types: self.mime_types,
+            content_sniffing: self.content_sniffing,
+            image_variants: self.image_variants,
+            i18n: self.i18n,
             https_config: self.https_config,
+            allowed_methods: self.allowed_methods,
+            etag: self.etag,
+            compression: self.compression,
             response_headers: self.response_headers,
+            header_rules: self.header_rules,
             redirects: self.redirects,
             rewrites: self.rewrites,
-        }
+            redirect_patterns: RoutePatterns::default(),
+            rewrite_patterns: RoutePatterns::default(),
+            templates: Templates::default(),
+        };
+
+        site.compile_routes();
+        site.compile_templates();
+        site
     }
 }
@@ -0,0 +1,242 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use log::debug;
+
+use crate::error::ChimneyError;
+
+/// The request a template is rendered for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TemplateContext<'a> {
+    /// The scheme the request was made with (`http` or `https`)
+    pub scheme: &'a str,
+
+    /// The host the request was made to, as detected by the host detection strategy
+    pub host: &'a str,
+
+    /// The path of the request, as sent by the client
+    pub path: &'a str,
+
+    /// The query string of the request without the leading `?`, empty if there is none
+    pub query: &'a str,
+
+    /// The method of the request
+    pub method: &'a str,
+}
+
+/// A value that can be filled into a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Variable {
+    /// `{env.NAME}`, the value of an environment variable (empty if it is not set)
+    Env(String),
+
+    /// `{request.scheme}`
+    RequestScheme,
+
+    /// `{request.host}`
+    RequestHost,
+
+    /// `{request.path}`
+    RequestPath,
+
+    /// `{request.query}`
+    RequestQuery,
+
+    /// `{request.method}`
+    RequestMethod,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Variable(Variable),
+}
+
+/// A string with `{env.*}` and `{request.*}` placeholders, as used in response headers and
+/// redirect targets
+///
+/// Only braces that look like one of these placeholders are treated as variables, anything else
+/// (like a JSON value in a `Report-To` header) is kept as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Parses a template, failing on unknown `request.*` variables or invalid variable names
+    pub fn parse(input: &str) -> Result<Self, ChimneyError> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = input;
+
+        while let Some(start) = rest.find('{') {
+            literal.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let placeholder = rest[1..]
+                .find('}')
+                .map(|end| &rest[1..end + 1])
+                .filter(|name| name.starts_with("env.") || name.starts_with("request."));
+
+            match placeholder {
+                Some(name) => {
+                    let variable = Self::parse_variable(name).ok_or_else(|| {
+                        ChimneyError::InvalidTemplate {
+                            template: input.to_string(),
+                            message: format!("unknown variable `{{{name}}}`"),
+                        }
+                    })?;
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Variable(variable));
+                    rest = &rest[name.len() + 2..];
+                }
+                None => {
+                    literal.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    fn parse_variable(name: &str) -> Option<Variable> {
+        if let Some(env) = name.strip_prefix("env.") {
+            let valid =
+                !env.is_empty() && env.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            return valid.then(|| Variable::Env(env.to_string()));
+        }
+
+        match name {
+            "request.scheme" => Some(Variable::RequestScheme),
+            "request.host" => Some(Variable::RequestHost),
+            "request.path" => Some(Variable::RequestPath),
+            "request.query" => Some(Variable::RequestQuery),
+            "request.method" => Some(Variable::RequestMethod),
+            _ => None,
+        }
+    }
+
+    /// Whether the template contains any variables
+    pub fn has_variables(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Variable(_)))
+    }
+
+    /// Fills in the variables for the given request
+    pub fn render(&self, context: &TemplateContext) -> String {
+        self.render_with(context, |literal| literal.to_string())
+    }
+
+    /// Fills in the variables for the given request, passing the literal parts of the template
+    /// through `literal` first
+    ///
+    /// This is used to substitute the captures of a redirect pattern without touching the values
+    /// of variables, which come from the request and can't be trusted.
+    pub fn render_with(
+        &self,
+        context: &TemplateContext,
+        literal: impl Fn(&str) -> String,
+    ) -> String {
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(value) => output.push_str(&literal(value)),
+                Segment::Variable(Variable::Env(name)) => {
+                    output.push_str(&std::env::var(name).unwrap_or_default())
+                }
+                Segment::Variable(Variable::RequestScheme) => output.push_str(context.scheme),
+                Segment::Variable(Variable::RequestHost) => output.push_str(context.host),
+                Segment::Variable(Variable::RequestPath) => output.push_str(context.path),
+                Segment::Variable(Variable::RequestQuery) => output.push_str(context.query),
+                Segment::Variable(Variable::RequestMethod) => output.push_str(context.method),
+            }
+        }
+
+        output
+    }
+}
+
+/// The parsed templates of a site's response headers and redirect targets, keyed by their source
+///
+/// Sites parse them once when they are loaded or their headers and redirects change, so
+/// responding to a request never has to parse a template. Sources without any braces are
+/// rendered as they are, so they aren't kept.
+#[derive(Debug, Default, Clone)]
+pub struct Templates {
+    templates: HashMap<String, Template>,
+}
+
+impl Templates {
+    /// Parses the sources, skipping any invalid ones
+    ///
+    /// Sites loaded from a configuration reject invalid templates up front, see
+    /// [`Site::from_table`](super::Site::from_table).
+    pub fn compile<S: AsRef<str>>(sources: impl IntoIterator<Item = S>) -> Self {
+        let mut templates = Self::default();
+        for source in sources {
+            let source = source.as_ref();
+            if !source.contains('{') || templates.templates.contains_key(source) {
+                continue;
+            }
+
+            match Template::parse(source) {
+                Ok(template) => {
+                    templates.templates.insert(source.to_string(), template);
+                }
+                Err(e) => debug!("Skipping invalid template: {e}"),
+            }
+        }
+
+        templates
+    }
+
+    /// Returns the parsed template for a source, parsing it now if it wasn't compiled
+    pub fn parse(&self, source: &str) -> Result<Cow<'_, Template>, ChimneyError> {
+        match self.templates.get(source) {
+            Some(template) => Ok(Cow::Borrowed(template)),
+            None => Template::parse(source).map(Cow::Owned),
+        }
+    }
+
+    /// Renders the template of a source for the given request, see [`render_template`]
+    pub fn render(&self, source: &str, context: &TemplateContext) -> String {
+        match self.templates.get(source) {
+            Some(template) => template.render(context),
+            None => render_template(source, context),
+        }
+    }
+
+    /// Returns the number of parsed templates
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    /// Whether none of the sources needed parsing
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+}
+
+/// Renders a template string for the given request
+///
+/// Templates are validated when the configuration is loaded, so an invalid one is returned as is
+/// instead of failing the request.
+pub fn render_template(input: &str, context: &TemplateContext) -> String {
+    if !input.contains('{') {
+        return input.to_string();
+    }
+
+    Template::parse(input)
+        .map(|template| template.render(context))
+        .unwrap_or_else(|_| input.to_string())
+}
@@ -0,0 +1,763 @@
+//! Converts the routing configuration of `vercel.json` and `serve.json` files into chimney sites
+//!
+//! Both formats describe redirects, rewrites and headers with `source` paths in the
+//! [path-to-regexp](https://github.com/pillarjs/path-to-regexp) syntax (`/blog/:slug`,
+//! `/docs/:path*`, `/(.*)`). Sources that map onto a plain chimney pattern are kept readable
+//! (`/docs/*`), anything else is turned into a regular expression.
+//!
+//! Rules that can't be converted are skipped with a warning instead of failing the whole
+//! conversion, so a project can be migrated first and the leftovers ported by hand.
+use std::{collections::HashMap, path::Path};
+
+use hyper::header::{HeaderName, HeaderValue};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{error::ChimneyError, with_leading_slash};
+
+use super::{
+    Autoindex, CleanUrls, HeaderRule, RedirectRule, RewriteRule, RoutePattern, Site, SiteBuilder,
+    TrailingSlash,
+};
+
+/// The name of Vercel's configuration file
+pub const VERCEL_FILE: &str = "vercel.json";
+
+/// The name of the configuration file of `serve`
+pub const SERVE_FILE: &str = "serve.json";
+
+/// The formats a routing configuration can be converted from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// `vercel.json`
+    Vercel,
+
+    /// `serve.json`, as used by `serve` and `serve-handler`
+    Serve,
+}
+
+impl ImportFormat {
+    /// Detects the format from the name of a file
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        match filename {
+            VERCEL_FILE => Some(ImportFormat::Vercel),
+            SERVE_FILE => Some(ImportFormat::Serve),
+            _ => None,
+        }
+    }
+
+    /// The name of the file this format is usually found in
+    pub fn filename(&self) -> &'static str {
+        match self {
+            ImportFormat::Vercel => VERCEL_FILE,
+            ImportFormat::Serve => SERVE_FILE,
+        }
+    }
+}
+
+/// The result of converting a routing configuration
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    /// The converted site
+    pub site: Site,
+
+    /// Everything that was skipped or could only be converted partially
+    pub warnings: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct VercelConfig {
+    #[serde(default)]
+    redirects: Vec<RouteEntry>,
+
+    #[serde(default)]
+    rewrites: Vec<RouteEntry>,
+
+    #[serde(default)]
+    headers: Vec<HeadersEntry>,
+
+    clean_urls: Option<bool>,
+
+    trailing_slash: Option<bool>,
+
+    /// Everything that isn't related to routing, like build settings
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct ServeConfig {
+    public: Option<String>,
+
+    #[serde(default)]
+    redirects: Vec<RouteEntry>,
+
+    #[serde(default)]
+    rewrites: Vec<RouteEntry>,
+
+    #[serde(default)]
+    headers: Vec<HeadersEntry>,
+
+    clean_urls: Option<Value>,
+
+    trailing_slash: Option<bool>,
+
+    directory_listing: Option<Value>,
+
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+/// A redirect or rewrite, with the fields of both formats
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct RouteEntry {
+    source: String,
+
+    destination: String,
+
+    /// Vercel, whether a redirect is permanent (`308`) or temporary (`307`)
+    permanent: Option<bool>,
+
+    /// Vercel, an explicit status code
+    status_code: Option<u16>,
+
+    /// `serve`, the status code of a redirect
+    #[serde(rename = "type")]
+    kind: Option<u16>,
+
+    /// Vercel, conditions on headers, cookies or the query string
+    #[serde(default)]
+    has: Vec<Value>,
+
+    #[serde(default)]
+    missing: Vec<Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct HeadersEntry {
+    source: String,
+
+    headers: Vec<Header>,
+
+    #[serde(default)]
+    has: Vec<Value>,
+
+    #[serde(default)]
+    missing: Vec<Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Header {
+    key: String,
+    value: String,
+}
+
+/// Converts a routing configuration in the given format into a site with the given name
+pub fn convert(
+    format: ImportFormat,
+    name: impl Into<String>,
+    input: &str,
+) -> Result<Conversion, ChimneyError> {
+    match format {
+        ImportFormat::Vercel => convert_vercel(name, input),
+        ImportFormat::Serve => convert_serve(name, input),
+    }
+}
+
+/// Converts the `redirects`, `rewrites` and `headers` of a `vercel.json` file
+pub fn convert_vercel(name: impl Into<String>, input: &str) -> Result<Conversion, ChimneyError> {
+    let config: VercelConfig = parse_json(VERCEL_FILE, input)?;
+    let mut converter = Converter::new(name.into());
+
+    for (index, entry) in config.redirects.iter().enumerate() {
+        let field = format!("redirects[{index}]");
+        let rule = match entry.status_code {
+            Some(status) => RedirectRule::new(String::new(), false, false).with_status(status),
+            // Vercel redirects are permanent unless told otherwise, and keep the method
+            None => RedirectRule::new(String::new(), !entry.permanent.unwrap_or(true), true),
+        };
+        converter.redirect(&field, entry, rule);
+    }
+
+    for (index, entry) in config.rewrites.iter().enumerate() {
+        converter.rewrite(&format!("rewrites[{index}]"), entry);
+    }
+
+    for (index, entry) in config.headers.iter().enumerate() {
+        converter.headers(
+            &format!("headers[{index}]"),
+            entry,
+            HeaderSource::PathToRegexp,
+        );
+    }
+
+    // Both platforms redirect `.html` paths to their clean form as well
+    if config.clean_urls == Some(true) {
+        converter.site.clean_urls = CleanUrls::enabled().with_redirect(true);
+    }
+
+    converter.trailing_slash(config.trailing_slash);
+
+    converter.ignore(config.other);
+    Ok(converter.finish())
+}
+
+/// Converts the routing configuration of a `serve.json` file
+pub fn convert_serve(name: impl Into<String>, input: &str) -> Result<Conversion, ChimneyError> {
+    let mut config: ServeConfig = parse_json(SERVE_FILE, input)?;
+    let mut converter = Converter::new(name.into());
+
+    // `serve` adds the leading slash itself, and `**` matches the same as `*` there
+    for entry in config
+        .redirects
+        .iter_mut()
+        .chain(config.rewrites.iter_mut())
+    {
+        entry.source = with_leading_slash!(entry.source.replace("**", "*"));
+    }
+
+    if let Some(public) = &config.public {
+        converter.site.root = public.clone();
+    }
+
+    for (index, entry) in config.redirects.iter().enumerate() {
+        let status = entry.kind.unwrap_or(301);
+        let rule = RedirectRule::new(String::new(), false, false).with_status(status);
+        converter.redirect(&format!("redirects[{index}]"), entry, rule);
+    }
+
+    for (index, entry) in config.rewrites.iter().enumerate() {
+        converter.rewrite(&format!("rewrites[{index}]"), entry);
+    }
+
+    for (index, entry) in config.headers.iter().enumerate() {
+        converter.headers(&format!("headers[{index}]"), entry, HeaderSource::Glob);
+    }
+
+    match &config.directory_listing {
+        None | Some(Value::Bool(false)) => {}
+        Some(Value::Bool(true)) => converter.site.autoindex = Autoindex::enabled(),
+        Some(Value::Array(globs)) => {
+            let mut autoindex = Autoindex::default();
+            for glob in globs {
+                // Only globs covering a whole directory map onto the path prefixes of autoindex
+                match glob.as_str().and_then(|glob| glob.strip_suffix("/**")) {
+                    Some(prefix) if !prefix.contains(['*', '?', '{', '[', '(']) => {
+                        autoindex = autoindex.with_path(prefix);
+                    }
+                    _ => converter.warn(format!(
+                        "directoryListing: `{glob}` can't be converted, only globs like `/path/**` are supported"
+                    )),
+                }
+            }
+            converter.site.autoindex = autoindex;
+        }
+        Some(value) => converter.warn(format!(
+            "directoryListing: `{value}` is not a boolean or a list of globs"
+        )),
+    }
+
+    match &config.clean_urls {
+        None | Some(Value::Bool(false)) => {}
+        Some(Value::Bool(true)) => {
+            converter.site.clean_urls = CleanUrls::enabled().with_redirect(true);
+        }
+        Some(value) => converter.warn(format!(
+            "cleanUrls: `{value}` can't be converted, only `true` or `false` are supported"
+        )),
+    }
+
+    converter.trailing_slash(config.trailing_slash);
+
+    converter.ignore(config.other);
+    Ok(converter.finish())
+}
+
+fn parse_json<T: for<'de> Deserialize<'de>>(file: &str, input: &str) -> Result<T, ChimneyError> {
+    serde_json::from_str(input).map_err(|e| ChimneyError::ParseError {
+        field: file.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// How the `source` of a header rule is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HeaderSource {
+    /// path-to-regexp, as in `vercel.json`
+    PathToRegexp,
+
+    /// A glob, as in `serve.json`
+    Glob,
+}
+
+struct Converter {
+    site: Site,
+    warnings: Vec<String>,
+}
+
+impl Converter {
+    fn new(name: String) -> Self {
+        Self {
+            site: SiteBuilder::new(name).build(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    /// Both platforms apply the policy to every path, chimney only adds the slash to directories
+    fn trailing_slash(&mut self, trailing_slash: Option<bool>) {
+        if trailing_slash == Some(true) {
+            self.warn(
+                "trailingSlash: only paths to directories are redirected to end with a slash, \
+                 other paths are served as requested"
+                    .to_string(),
+            );
+        }
+
+        self.site.trailing_slash = match trailing_slash {
+            Some(true) => TrailingSlash::Always,
+            Some(false) => TrailingSlash::Never,
+            None => TrailingSlash::Preserve,
+        };
+    }
+
+    fn ignore(&mut self, other: Map<String, Value>) {
+        for key in other.keys() {
+            // The schema reference only helps editors
+            if key != "$schema" {
+                self.warn(format!("{key}: not related to routing, ignored"));
+            }
+        }
+    }
+
+    /// Skips rules with conditions, which chimney can't match on
+    fn has_conditions(&mut self, field: &str, has: &[Value], missing: &[Value]) -> bool {
+        let conditional = !has.is_empty() || !missing.is_empty();
+        if conditional {
+            self.warn(format!(
+                "{field}: conditions (`has` and `missing`) are not supported, skipped"
+            ));
+        }
+
+        conditional
+    }
+
+    /// Converts the source and destination of a redirect or rewrite
+    fn route(&mut self, field: &str, entry: &RouteEntry) -> Option<(String, String)> {
+        if self.has_conditions(field, &entry.has, &entry.missing) {
+            return None;
+        }
+
+        let route = convert_source(&entry.source).and_then(|(source, renames)| {
+            RoutePattern::parse(&source).map_err(|e| e.to_string())?;
+            Ok((source, convert_destination(&entry.destination, &renames)))
+        });
+
+        match route {
+            Ok(route) => Some(route),
+            Err(message) => {
+                self.warn(format!("{field}: `{}` {message}, skipped", entry.source));
+                None
+            }
+        }
+    }
+
+    /// Warns about rules whose source is already handled, the first rule wins like it would on
+    /// the original platform
+    fn is_duplicate(&mut self, field: &str, entry: &RouteEntry, duplicate: bool) -> bool {
+        if duplicate {
+            self.warn(format!(
+                "{field}: `{}` is already handled by an earlier rule, skipped",
+                entry.source
+            ));
+        }
+
+        duplicate
+    }
+
+    fn redirect(&mut self, field: &str, entry: &RouteEntry, rule: RedirectRule) {
+        let Some((source, destination)) = self.route(field, entry) else {
+            return;
+        };
+
+        if self.is_duplicate(field, entry, self.site.redirects.contains_key(&source)) {
+            return;
+        }
+
+        let rule = rule.with_target(destination);
+        if let Err(message) = rule.validate() {
+            self.warn(format!("{field}: {message}, skipped"));
+            return;
+        }
+
+        self.site.redirects.insert(source, rule);
+    }
+
+    fn rewrite(&mut self, field: &str, entry: &RouteEntry) {
+        if entry.destination.starts_with("http://") || entry.destination.starts_with("https://") {
+            self.warn(format!(
+                "{field}: proxying to another origin (`{}`) is not supported, skipped",
+                entry.destination
+            ));
+            return;
+        }
+
+        let Some((source, destination)) = self.route(field, entry) else {
+            return;
+        };
+
+        if self.is_duplicate(field, entry, self.site.rewrites.contains_key(&source)) {
+            return;
+        }
+
+        // Rewrites only apply to paths that don't match a file, which is what the fallback file
+        // does for a catch-all rewrite to a single page
+        if source == "/*"
+            && !destination.contains([':', '*', '$'])
+            && self.site.fallback_file.is_none()
+        {
+            let fallback = Path::new(&self.site.root).join(destination.trim_start_matches('/'));
+            self.site.fallback_file = Some(fallback.to_string_lossy().to_string());
+            return;
+        }
+
+        let rule = RewriteRule::new(destination).with_files_first(true);
+        self.site.rewrites.insert(source, rule);
+    }
+
+    fn headers(&mut self, field: &str, entry: &HeadersEntry, kind: HeaderSource) {
+        if self.has_conditions(field, &entry.has, &entry.missing) {
+            return;
+        }
+
+        let glob = match kind {
+            HeaderSource::Glob
+                if entry
+                    .source
+                    .contains(['(', ')', '{', '}', '[', ']', '!', '+', '@']) =>
+            {
+                Err("uses glob syntax that is not supported".to_string())
+            }
+            HeaderSource::Glob => Ok(entry.source.clone()),
+            HeaderSource::PathToRegexp => source_to_glob(&entry.source),
+        };
+
+        let glob = match glob {
+            Ok(glob) => glob,
+            Err(message) => {
+                self.warn(format!("{field}: `{}` {message}, skipped", entry.source));
+                return;
+            }
+        };
+
+        let mut rule = HeaderRule::path(glob);
+        for header in &entry.headers {
+            if HeaderName::from_bytes(header.key.as_bytes()).is_err()
+                || HeaderValue::from_str(&header.value).is_err()
+            {
+                self.warn(format!(
+                    "{field}: `{}: {}` is not a valid header, skipped",
+                    header.key, header.value
+                ));
+                continue;
+            }
+
+            rule.set
+                .entry(header.key.clone())
+                .and_modify(|value| {
+                    value.push_str(", ");
+                    value.push_str(&header.value);
+                })
+                .or_insert_with(|| header.value.clone());
+        }
+
+        self.site.header_rules.push(rule);
+    }
+
+    fn finish(mut self) -> Conversion {
+        self.site.compile_routes();
+        self.site.compile_templates();
+        Conversion {
+            site: self.site,
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// A token of a path-to-regexp source
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Param {
+        name: Option<String>,
+        pattern: Option<String>,
+        modifier: Option<char>,
+    },
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let (name, pattern) = match c {
+            '\\' => {
+                literal.extend(chars.next());
+                continue;
+            }
+            '{' | '}' => return Err("uses groups (`{}`), which are not supported".to_string()),
+            ':' if chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') =>
+            {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+
+                let pattern = match chars.next_if_eq(&'(') {
+                    Some(_) => Some(group(&mut chars)?),
+                    None => None,
+                };
+                (Some(name), pattern)
+            }
+            '(' => (None, Some(group(&mut chars)?)),
+            // A bare `*` matches anything, as in `serve.json`
+            '*' => (None, Some(".*".to_string())),
+            c => {
+                literal.push(c);
+                continue;
+            }
+        };
+
+        let modifier = chars.next_if(|c| matches!(c, '?' | '*' | '+'));
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(std::mem::take(&mut literal)));
+        }
+        tokens.push(Token::Param {
+            name,
+            pattern,
+            modifier,
+        });
+    }
+
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a regular expression up to the matching closing parenthesis
+fn group(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    let mut pattern = String::new();
+    let mut depth = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                pattern.push(c);
+                pattern.extend(chars.next());
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(pattern);
+                }
+            }
+            _ => {}
+        }
+        pattern.push(c);
+    }
+
+    Err("has an unbalanced `(`".to_string())
+}
+
+/// Whether a pattern matches anything, like the `(.*)` in `/blog/(.*)`
+fn is_catch_all(pattern: Option<&str>, modifier: Option<char>) -> bool {
+    matches!(
+        (pattern, modifier),
+        (None | Some("[^/]+" | "[^/]*"), Some('*')) | (Some(".*"), None)
+    )
+}
+
+/// Converts a path-to-regexp source into the source of a chimney rule, along with the parameters
+/// that have to be renamed in the destination
+fn convert_source(source: &str) -> Result<(String, HashMap<String, String>), String> {
+    if !source.starts_with('/') {
+        return Err("is not a path starting with `/`".to_string());
+    }
+
+    let tokens = tokenize(source)?;
+    match plain_source(&tokens) {
+        Some(plain) => Ok(plain),
+        None => Ok((regex_source(&tokens), HashMap::new())),
+    }
+}
+
+/// Converts the source to chimney's own pattern syntax, if every parameter is a whole segment
+fn plain_source(tokens: &[Token]) -> Option<(String, HashMap<String, String>)> {
+    let mut source = String::new();
+    let mut renames = HashMap::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        let last = index == tokens.len() - 1;
+        let segment_start = source.ends_with('/');
+        let segment_end =
+            last || matches!(&tokens[index + 1], Token::Literal(next) if next.starts_with('/'));
+
+        match token {
+            Token::Literal(literal) => {
+                if literal.contains([':', '*']) {
+                    return None;
+                }
+                source.push_str(literal);
+            }
+            Token::Param {
+                name: Some(name),
+                pattern: None,
+                modifier: None,
+            } if segment_start && segment_end => {
+                source.push(':');
+                source.push_str(name);
+            }
+            Token::Param {
+                name,
+                pattern,
+                modifier,
+            } if segment_start && last && is_catch_all(pattern.as_deref(), *modifier) => {
+                if let Some(name) = name {
+                    renames.insert(name.clone(), "splat".to_string());
+                }
+                source.push('*');
+            }
+            _ => return None,
+        }
+    }
+
+    Some((source, renames))
+}
+
+/// Converts the source to a regular expression, with named groups for the parameters
+fn regex_source(tokens: &[Token]) -> String {
+    let mut source = String::from("^");
+
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Literal(literal) => {
+                // The slash in front of an optional parameter is optional too
+                let optional_next = matches!(
+                    tokens.get(index + 1),
+                    Some(Token::Param {
+                        modifier: Some('?' | '*'),
+                        ..
+                    })
+                );
+                let literal = if optional_next {
+                    literal.strip_suffix('/').unwrap_or(literal)
+                } else {
+                    literal
+                };
+                source.push_str(&regex::escape(literal));
+            }
+            Token::Param {
+                name,
+                pattern,
+                modifier,
+            } => {
+                let prefix = match index.checked_sub(1).map(|previous| &tokens[previous]) {
+                    Some(Token::Literal(previous))
+                        if previous.ends_with('/') && matches!(modifier, Some('?' | '*')) =>
+                    {
+                        "/"
+                    }
+                    _ => "",
+                };
+
+                let pattern = pattern.as_deref().unwrap_or("[^/]+");
+                let inner = match modifier {
+                    Some('*' | '+') => format!("{pattern}(?:/{pattern})*"),
+                    _ => pattern.to_string(),
+                };
+                let group = match name {
+                    Some(name) => format!("(?<{name}>{inner})"),
+                    None => format!("({inner})"),
+                };
+
+                match modifier {
+                    Some('?' | '*') => source.push_str(&format!("(?:{prefix}{group})?")),
+                    _ => source.push_str(&group),
+                }
+            }
+        }
+    }
+
+    source.push('$');
+    source
+}
+
+/// Converts the parameters of a destination (`:path*`, `:slug`) to chimney's syntax
+fn convert_destination(destination: &str, renames: &HashMap<String, String>) -> String {
+    let mut output = String::with_capacity(destination.len());
+    let mut rest = destination;
+
+    while let Some(index) = rest.find(':') {
+        output.push_str(&rest[..=index]);
+        rest = &rest[index + 1..];
+
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let name = &rest[..end];
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+
+        output.push_str(renames.get(name).map_or(name, String::as_str));
+        rest = rest[end..].trim_start_matches(['*', '+', '?']);
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Converts a path-to-regexp source into a glob for a header rule
+fn source_to_glob(source: &str) -> Result<String, String> {
+    if !source.starts_with('/') {
+        return Err("is not a path starting with `/`".to_string());
+    }
+
+    let mut glob = String::new();
+    for token in tokenize(source)? {
+        match token {
+            Token::Literal(literal) if literal.contains(['*', '?', '[']) => {
+                return Err("contains characters with a special meaning in globs".to_string());
+            }
+            Token::Literal(literal) => glob.push_str(&literal),
+            Token::Param {
+                pattern, modifier, ..
+            } if is_catch_all(pattern.as_deref(), modifier)
+                || modifier == Some('+') && pattern.is_none() =>
+            {
+                glob.push_str("**");
+            }
+            Token::Param {
+                pattern: None,
+                modifier: None,
+                ..
+            } => glob.push('*'),
+            _ => return Err("can't be converted to a glob".to_string()),
+        }
+    }
+
+    Ok(glob)
+}
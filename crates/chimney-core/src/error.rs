@@ -18,6 +18,12 @@ pub enum ChimneyError {
     #[error("{0}")]
     IOError(#[from] std::io::Error),
 
+    #[error("Invalid template `{template}`: {message}")]
+    InvalidTemplate { template: String, message: String },
+
+    #[error("Invalid route pattern `{pattern}`: {message}")]
+    InvalidRoutePattern { pattern: String, message: String },
+
     #[error("Failed to parse Domain type: {0}")]
     DomainParseError(String),
 
@@ -63,12 +69,31 @@ pub enum ServerError {
         message: String,
     },
 
+    #[error("Failed to compress `{path}` with {encoding}: {message}")]
+    CompressionFailed {
+        path: String,
+        encoding: String,
+        message: String,
+    },
+
     #[error("No configured site found for domain `{host}`")]
     SiteNotFound { host: String },
 
+    #[error("Access to `{path}` is denied")]
+    AccessDenied { path: String, status: u16 },
+
+    #[error("Invalid request path `{path}`: {message}")]
+    InvalidRequestPath { path: String, message: String },
+
+    #[error("The root of site `{site}` is outside of the sites directory: `{root}`")]
+    SiteRootOutsideSitesDirectory { site: String, root: String },
+
     #[error("Failed to update configuration: {0}")]
     ConfigUpdateFailed(#[from] SendError<Arc<Config>>),
 
+    #[error("Failed to load configuration: {0}")]
+    ConfigLoadFailed(#[from] ChimneyError),
+
     // TLS-related errors
     #[error("TLS handshake failed: {0}")]
     TlsHandshakeFailed(String),
@@ -1,6 +1,13 @@
+use std::io::SeekFrom;
 use std::path::PathBuf;
 
-use super::{AbstractFile, Content, Filesystem, FilesystemError};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use super::{
+    AbstractFile, Content, ContentStream, Filesystem, FilesystemError, OpenStream,
+    STREAM_CHUNK_SIZE,
+};
 
 pub struct LocalFS {
     path: PathBuf,
@@ -65,6 +72,33 @@ impl Filesystem for LocalFS {
         Ok(Content::new(bytes))
     }
 
+    fn read_stream(&self, path: PathBuf, offset: u64, length: Option<u64>) -> OpenStream {
+        Box::pin(async move {
+            let read_error = |e: std::io::Error| FilesystemError::ReadFileError {
+                path: path.clone(),
+                message: e.to_string(),
+            };
+
+            // `tokio::fs` runs the blocking calls on the blocking thread pool
+            let mut file = tokio::fs::File::open(&path).await.map_err(read_error)?;
+            if offset > 0 {
+                file.seek(SeekFrom::Start(offset))
+                    .await
+                    .map_err(read_error)?;
+            }
+
+            let stream: ContentStream = match length {
+                Some(length) => Box::pin(ReaderStream::with_capacity(
+                    file.take(length),
+                    STREAM_CHUNK_SIZE,
+                )),
+                None => Box::pin(ReaderStream::with_capacity(file, STREAM_CHUNK_SIZE)),
+            };
+
+            Ok(stream)
+        })
+    }
+
     fn stat(&self, path: PathBuf) -> Result<AbstractFile, FilesystemError> {
         AbstractFile::from_disk_path(path)
     }
@@ -73,4 +107,14 @@ impl Filesystem for LocalFS {
         let exists = path.exists();
         Ok(exists)
     }
+
+    fn canonicalize(&self, path: PathBuf) -> Result<PathBuf, FilesystemError> {
+        path.canonicalize().map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => FilesystemError::NotFound(path.clone()),
+            _ => FilesystemError::MetadataError {
+                path: path.clone(),
+                message: e.to_string(),
+            },
+        })
+    }
 }
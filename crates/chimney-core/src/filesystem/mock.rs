@@ -1,3 +1,5 @@
+use std::path::{Component, Path, PathBuf};
+
 use super::{AbstractFile, Filesystem};
 
 // The various files and directories that the mock filesystem will use and their contents.
@@ -30,10 +32,54 @@ static MOCK_FILES: &[(&str, &str)] = &[
     ("data/note.txt", "This is a note for Chimney."),
 ];
 
+// The symlinks in the mock filesystem and the paths they point to.
+static MOCK_SYMLINKS: &[(&str, &str)] = &[
+    // Points to a file in the same directory
+    ("public/main.css", "public/style.css"),
+    // Points outside of `public`, to a sibling directory
+    ("public/data", "data"),
+    // A precompressed sidecar pointing outside of `public`
+    ("public/script.js.br", "data/note.txt"),
+];
+
 /// A mock filesystem implementation for testing purposes.
+///
+/// Like a local filesystem, paths are resolved by dropping `.` segments and following symlinks.
+/// A path is a directory if any of the mock files is inside of it.
 #[derive(Debug, Clone, Default)]
 pub struct MockFilesystem;
 
+impl MockFilesystem {
+    /// Resolves a path to the mock file or directory it points to
+    fn resolve(path: &Path) -> PathBuf {
+        let mut resolved: PathBuf = path
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect();
+
+        // Symlinks can point to other symlinks, but never in a loop
+        for _ in 0..=MOCK_SYMLINKS.len() {
+            let target = MOCK_SYMLINKS.iter().find_map(|(link, target)| {
+                let rest = resolved.strip_prefix(link).ok()?;
+                Some(Path::new(target).join(rest).components().collect())
+            });
+
+            match target {
+                Some(target) => resolved = target,
+                None => break,
+            }
+        }
+
+        resolved
+    }
+
+    fn is_directory(path: &Path) -> bool {
+        MOCK_FILES
+            .iter()
+            .any(|(file_name, _)| Path::new(file_name).starts_with(path))
+    }
+}
+
 impl Filesystem for MockFilesystem {
     fn read_dir(
         &self,
@@ -79,7 +125,8 @@ impl Filesystem for MockFilesystem {
         &self,
         path: std::path::PathBuf,
     ) -> Result<super::Content, super::FilesystemError> {
-        let path_str = path.to_string_lossy();
+        let resolved = Self::resolve(&path);
+        let path_str = resolved.to_string_lossy();
         for (file_name, content) in MOCK_FILES {
             if path_str.ends_with(file_name) || path_str == *file_name {
                 return Ok(super::Content::new(content.to_string().into()));
@@ -93,13 +140,20 @@ impl Filesystem for MockFilesystem {
     }
 
     fn stat(&self, path: std::path::PathBuf) -> Result<AbstractFile, super::FilesystemError> {
-        let path_str = path.to_string_lossy();
-        for (file_name, _) in MOCK_FILES {
+        let resolved = Self::resolve(&path);
+        let path_str = resolved.to_string_lossy();
+        for (file_name, content) in MOCK_FILES {
             if path_str == *file_name {
-                return Ok(AbstractFile::new(path, super::FileType::File));
+                return Ok(
+                    AbstractFile::new(path, super::FileType::File).with_size(content.len() as u64)
+                );
             }
         }
 
+        if Self::is_directory(&resolved) {
+            return Ok(AbstractFile::new(path, super::FileType::Directory));
+        }
+
         Err(super::FilesystemError::MetadataError {
             path,
             message: "File not found in mock filesystem".to_string(),
@@ -107,7 +161,8 @@ impl Filesystem for MockFilesystem {
     }
 
     fn exists(&self, path: std::path::PathBuf) -> Result<bool, super::FilesystemError> {
-        let path_str = path.to_string_lossy();
+        let resolved = Self::resolve(&path);
+        let path_str = resolved.to_string_lossy();
         for (file_name, _) in MOCK_FILES {
             if path_str == *file_name || path_str.starts_with(file_name) {
                 return Ok(true);
@@ -115,4 +170,8 @@ impl Filesystem for MockFilesystem {
         }
         Ok(false)
     }
+
+    fn canonicalize(&self, path: PathBuf) -> Result<PathBuf, super::FilesystemError> {
+        Ok(Self::resolve(&path))
+    }
 }
@@ -1,4 +1,8 @@
 use std::path::PathBuf;
+use std::pin::Pin;
+
+use futures_util::Stream;
+use hyper::body::Bytes;
 use thiserror::Error;
 
 use crate::error::ChimneyError;
@@ -30,6 +34,15 @@ pub enum FilesystemError {
     GenericError(String),
 }
 
+/// The size of the chunks a file is read in when streamed.
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A stream of chunks of a file's content, as returned by [`Filesystem::read_stream`].
+pub type ContentStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+/// A file being opened for streaming, resolving to its [`ContentStream`] once it is ready.
+pub type OpenStream = Pin<Box<dyn Future<Output = Result<ContentStream, FilesystemError>> + Send>>;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
     File,
@@ -47,6 +60,9 @@ pub struct AbstractFile {
     /// The type of the file (e.g., file, directory, symlink).
     pub file_type: FileType,
 
+    /// The size of the file in bytes, if known.
+    pub size: Option<u64>,
+
     /// The time the file was created.
     pub created_at: Option<std::time::SystemTime>,
 
@@ -88,6 +104,11 @@ impl Content {
     pub fn size(&self) -> u64 {
         self.size
     }
+
+    /// Consumes the content and returns the raw byte vector.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.content
+    }
 }
 
 impl AbstractFile {
@@ -96,6 +117,7 @@ impl AbstractFile {
         Self {
             path,
             file_type,
+            size: None,
             created_at: None,
             modified_at: None,
             accessed_at: None,
@@ -108,6 +130,12 @@ impl AbstractFile {
         &self.path
     }
 
+    /// Sets the size of the file in bytes.
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
     /// Returns `true` if the file is a directory.
     pub fn is_directory(&self) -> bool {
         matches!(self.file_type, FileType::Directory)
@@ -146,6 +174,7 @@ impl AbstractFile {
             )));
         };
 
+        let size = metadata.is_file().then_some(metadata.len());
         let created_at = metadata.created().ok();
         let modified_at = metadata.modified().ok();
         let accessed_at = metadata.accessed().ok();
@@ -154,6 +183,7 @@ impl AbstractFile {
         Ok(Self {
             path,
             file_type,
+            size,
             created_at,
             modified_at,
             accessed_at,
@@ -172,9 +202,43 @@ pub trait Filesystem: Send + Sync {
     /// Read a file's content from the filesystem.
     fn read_file(&self, path: PathBuf) -> Result<Content, FilesystemError>;
 
+    /// Read a file's content as a stream of chunks, starting at `offset` and reading at most
+    /// `length` bytes (or until the end of the file if `length` is `None`).
+    ///
+    /// The default implementation reads the whole file with [`Filesystem::read_file`] and yields
+    /// the requested slice as a single chunk, implementations that are able to read files
+    /// incrementally should override this to keep memory usage flat regardless of the file size.
+    ///
+    /// Opening the file is asynchronous, so implementations backed by blocking I/O must not open
+    /// it before the returned future is polled.
+    fn read_stream(&self, path: PathBuf, offset: u64, length: Option<u64>) -> OpenStream {
+        let content = match self.read_file(path) {
+            Ok(content) => Bytes::from(content.into_bytes()),
+            Err(e) => return Box::pin(futures_util::future::ready(Err(e))),
+        };
+
+        let start = (offset as usize).min(content.len());
+        let end = match length {
+            Some(length) => start.saturating_add(length as usize).min(content.len()),
+            None => content.len(),
+        };
+
+        let chunk = content.slice(start..end);
+        let stream: ContentStream = Box::pin(futures_util::stream::once(async move { Ok(chunk) }));
+        Box::pin(futures_util::future::ready(Ok(stream)))
+    }
+
     /// Check if a file or directory exists.
     fn exists(&self, path: PathBuf) -> Result<bool, FilesystemError>;
 
     /// Get a file's metadata.
     fn stat(&self, path: PathBuf) -> Result<AbstractFile, FilesystemError>;
+
+    /// Resolve a path to its canonical, absolute form, following every symlink along the way.
+    ///
+    /// The default implementation returns the path as is, which is correct for filesystems
+    /// without symlinks (like object stores).
+    fn canonicalize(&self, path: PathBuf) -> Result<PathBuf, FilesystemError> {
+        Ok(path)
+    }
 }
@@ -0,0 +1,186 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
+
+/// The prefixes of the bcrypt hash versions that can be verified
+const BCRYPT_PREFIXES: [&str; 4] = ["$2a$", "$2b$", "$2x$", "$2y$"];
+
+/// The hash passwords of unknown users are verified against, with bcrypt's default cost
+const DUMMY_HASH: &str = "$2b$12$t9AueJm.7CG/1BIR3CysiewbMwCAuKIG6DiMnJfSzqH2W2rv83.dS";
+
+/// The maximum number of verified credentials kept in a [`CredentialCache`]
+pub const CREDENTIAL_CACHE_CAPACITY: usize = 1_000;
+
+/// Parses the name and password out of an `Authorization: Basic ...` header
+pub fn parse_basic_credentials(value: &str) -> Option<(String, String)> {
+    let (scheme, credentials) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+
+    let decoded = STANDARD.decode(credentials.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (name, password) = decoded.split_once(':')?;
+    Some((name.to_string(), password.to_string()))
+}
+
+/// Whether the password hash is in a format that can be verified (bcrypt or Argon2)
+pub fn is_supported_hash(hash: &str) -> bool {
+    is_bcrypt(hash)
+        || PasswordHash::new(hash).is_ok_and(|hash| hash.algorithm.as_str().starts_with("argon2"))
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    BCRYPT_PREFIXES
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
+
+/// Verifies a password against its bcrypt or Argon2 hash
+///
+/// Hashes in any other format never match.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    if is_bcrypt(hash) {
+        return bcrypt::verify(password, hash).unwrap_or(false);
+    }
+
+    match PasswordHash::new(hash) {
+        Ok(hash) if hash.algorithm.as_str().starts_with("argon2") => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        _ => false,
+    }
+}
+
+/// Verifies the password of a user against their hash, if the user exists
+///
+/// Unknown users never match, but their password is still verified against a dummy hash, so
+/// how long it takes doesn't tell which users exist.
+pub fn verify_user_password(password: &str, hash: Option<&str>) -> bool {
+    match hash {
+        Some(hash) => verify_password(password, hash),
+        None => {
+            verify_password(password, DUMMY_HASH);
+            false
+        }
+    }
+}
+
+/// Parses the users of an `htpasswd` file, one `name:hash` pair per line
+///
+/// Empty lines and comments (`#`) are skipped. Users listed more than once keep the last hash
+/// they are listed with.
+pub fn parse_htpasswd(input: &str) -> IndexMap<String, String> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (name, hash) = line.split_once(':')?;
+            Some((name.to_string(), hash.to_string()))
+        })
+        .collect()
+}
+
+/// Returns the value of the `WWW-Authenticate` header asking for credentials in the realm
+pub fn challenge(realm: &str) -> String {
+    let realm = realm.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("Basic realm=\"{realm}\", charset=\"UTF-8\"")
+}
+
+/// A digest of a user, their password and the hash it was verified against
+type CredentialDigest = [u8; 32];
+
+#[derive(Debug, Default)]
+struct DigestStore {
+    digests: HashSet<CredentialDigest>,
+
+    /// Insertion order of the digests, the oldest ones are evicted first
+    order: VecDeque<CredentialDigest>,
+}
+
+/// A cache of credentials that were verified, so clients sending the same credentials with
+/// every request don't have their password hashed every time
+///
+/// Only a SHA-256 digest of the user, the password and the hash it matched is kept, so changing
+/// a user's password hash never lets the old password through.
+#[derive(Debug)]
+pub struct CredentialCache {
+    store: Mutex<DigestStore>,
+    capacity: usize,
+}
+
+impl CredentialCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            store: Mutex::new(DigestStore::default()),
+            capacity,
+        }
+    }
+
+    /// Whether the password of the user was already verified against the hash
+    pub fn contains(&self, user: &str, password: &str, hash: &str) -> bool {
+        let digest = credential_digest(user, password, hash);
+        self.store
+            .lock()
+            .is_ok_and(|store| store.digests.contains(&digest))
+    }
+
+    /// Remembers that the password of the user matches the hash
+    pub fn insert(&self, user: &str, password: &str, hash: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let Ok(mut store) = self.store.lock() else {
+            return;
+        };
+
+        let digest = credential_digest(user, password, hash);
+        if store.digests.insert(digest) {
+            store.order.push_back(digest);
+        }
+
+        while store.digests.len() > self.capacity {
+            let Some(oldest) = store.order.pop_front() else {
+                break;
+            };
+            store.digests.remove(&oldest);
+        }
+    }
+
+    /// Returns the number of cached credentials
+    pub fn len(&self) -> usize {
+        self.store
+            .lock()
+            .map(|store| store.digests.len())
+            .unwrap_or_default()
+    }
+
+    /// Whether no credentials are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for CredentialCache {
+    fn default() -> Self {
+        Self::new(CREDENTIAL_CACHE_CAPACITY)
+    }
+}
+
+fn credential_digest(user: &str, password: &str, hash: &str) -> CredentialDigest {
+    let mut hasher = Sha256::new();
+
+    // Every part is prefixed with its length, so they can't run into each other
+    for part in [user, password, hash] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+
+    hasher.finalize().into()
+}
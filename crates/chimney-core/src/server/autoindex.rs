@@ -0,0 +1,349 @@
+use std::cmp::Ordering;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use serde::Serialize;
+
+use crate::filesystem::AbstractFile;
+
+/// Characters that have to be escaped in a path segment of a link
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The column a listing is sorted by
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Modified,
+}
+
+impl SortKey {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Modified => "modified",
+        }
+    }
+}
+
+/// The direction a listing is sorted in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "asc",
+            SortOrder::Descending => "desc",
+        }
+    }
+}
+
+/// How a listing should be sorted, as requested by the `sort` and `order` query parameters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Sorting {
+    pub key: SortKey,
+    pub order: SortOrder,
+}
+
+impl Sorting {
+    /// Reads the sorting from a query string (e.g. `sort=size&order=desc`)
+    ///
+    /// Unknown parameters and values are ignored and fall back to sorting by name, ascending.
+    pub fn from_query(query: Option<&str>) -> Self {
+        let mut sorting = Self::default();
+
+        for (name, value) in query.unwrap_or_default().split('&').filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some((name, value.to_ascii_lowercase()))
+        }) {
+            match (name, value.as_str()) {
+                ("sort", "name") => sorting.key = SortKey::Name,
+                ("sort", "size") => sorting.key = SortKey::Size,
+                ("sort", "modified") => sorting.key = SortKey::Modified,
+                ("order", "asc") => sorting.order = SortOrder::Ascending,
+                ("order", "desc") => sorting.order = SortOrder::Descending,
+                _ => {}
+            }
+        }
+
+        sorting
+    }
+}
+
+/// A single file or directory in a listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    /// The name of the file or directory
+    pub name: String,
+
+    /// Whether the entry is a directory
+    #[serde(rename = "directory")]
+    pub is_directory: bool,
+
+    /// The size of the file in bytes, unknown for directories
+    pub size: Option<u64>,
+
+    /// The last modified time of the entry
+    #[serde(serialize_with = "serialize_time")]
+    pub modified: Option<SystemTime>,
+}
+
+/// The content of a directory, as presented to the client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Listing {
+    /// The request path of the directory, always with a trailing slash
+    pub path: String,
+
+    /// The entries of the directory
+    pub entries: Vec<Entry>,
+}
+
+impl Listing {
+    /// Builds a listing for the directory at the given request path
+    ///
+    /// Hidden files (names starting with a dot) are left out unless `show_hidden` is set.
+    pub fn new(path: &str, files: Vec<AbstractFile>, show_hidden: bool) -> Self {
+        let mut path = crate::with_leading_slash!(path);
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+
+        let entries = files
+            .into_iter()
+            .filter_map(|file| {
+                let name = file.path.file_name()?.to_string_lossy().to_string();
+                if !show_hidden && name.starts_with('.') {
+                    return None;
+                }
+
+                Some(Entry {
+                    name,
+                    is_directory: file.is_directory(),
+                    size: file.size,
+                    modified: file.modified_at,
+                })
+            })
+            .collect();
+
+        Self { path, entries }
+    }
+
+    /// Sorts the entries, directories are always listed before files
+    pub fn sort(&mut self, sorting: Sorting) {
+        self.entries.sort_by(|a, b| {
+            let ordering = match sorting.key {
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Modified => a.modified.cmp(&b.modified),
+            }
+            .then_with(|| a.name.cmp(&b.name));
+
+            let ordering = match sorting.order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            };
+
+            match (a.is_directory, b.is_directory) {
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ => ordering,
+            }
+        });
+    }
+
+    /// Renders the listing as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Renders the listing as an HTML page
+    pub fn to_html(&self, sorting: Sorting) -> String {
+        let title = format!(
+            "Index of {}",
+            escape_html(&percent_decode_str(&self.path).decode_utf8_lossy())
+        );
+
+        let mut rows = String::new();
+        if self.path != "/" {
+            rows.push_str(
+                "<tr><td><a href=\"../\">../</a></td><td class=\"size\">-</td><td>-</td></tr>\n",
+            );
+        }
+
+        for entry in &self.entries {
+            let suffix = if entry.is_directory { "/" } else { "" };
+            let href = format!(
+                "{}{}{suffix}",
+                self.path,
+                utf8_percent_encode(&entry.name, PATH_SEGMENT)
+            );
+            let size = match entry.size {
+                Some(size) if !entry.is_directory => format_size(size),
+                _ => "-".to_string(),
+            };
+            let modified = entry
+                .modified
+                .map(|time| {
+                    DateTime::<Utc>::from(time)
+                        .format("%Y-%m-%d %H:%M UTC")
+                        .to_string()
+                })
+                .unwrap_or_else(|| "-".to_string());
+
+            rows.push_str(&format!(
+                "<tr><td><a href=\"{}\">{}{suffix}</a></td><td class=\"size\">{size}</td><td>{modified}</td></tr>\n",
+                escape_html(&href),
+                escape_html(&entry.name),
+            ));
+        }
+
+        let header = |key: SortKey, label: &str| {
+            // Clicking the column the listing is already sorted by flips the order
+            let order = if sorting.key == key && sorting.order == SortOrder::Ascending {
+                SortOrder::Descending
+            } else {
+                SortOrder::Ascending
+            };
+            format!(
+                "<th><a href=\"?sort={}&amp;order={}\">{label}</a></th>",
+                key.as_str(),
+                order.as_str()
+            )
+        };
+
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font-family: system-ui, -apple-system, sans-serif; margin: 2rem auto; max-width: 960px; padding: 0 1rem; color: #1f2328; }}
+h1 {{ font-size: 1.25rem; font-weight: 600; word-break: break-all; }}
+table {{ width: 100%; border-collapse: collapse; font-size: 0.9rem; }}
+th, td {{ text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #d1d9e0; }}
+th a {{ color: inherit; }}
+td.size {{ text-align: right; white-space: nowrap; font-variant-numeric: tabular-nums; }}
+a {{ color: #0969da; text-decoration: none; }}
+a:hover {{ text-decoration: underline; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+<thead><tr>{}{}{}</tr></thead>
+<tbody>
+{rows}</tbody>
+</table>
+</body>
+</html>
+"#,
+            header(SortKey::Name, "Name"),
+            header(SortKey::Size, "Size"),
+            header(SortKey::Modified, "Last modified"),
+        )
+    }
+}
+
+/// Whether the client prefers a JSON listing over an HTML one, based on its `Accept` header
+///
+/// Wildcards are ignored, so browsers sending `*/*` still get HTML.
+pub fn prefers_json(accept: Option<&str>) -> bool {
+    let Some(accept) = accept else {
+        return false;
+    };
+
+    let quality = |media_type: &str| {
+        accept
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let kind = parts.next()?.trim();
+                if !kind.eq_ignore_ascii_case(media_type) {
+                    return None;
+                }
+
+                let quality = parts
+                    .find_map(|param| {
+                        let (name, value) = param.split_once('=')?;
+                        name.trim()
+                            .eq_ignore_ascii_case("q")
+                            .then(|| value.trim().parse::<f32>().unwrap_or(0.0))
+                    })
+                    .unwrap_or(1.0);
+                Some(quality)
+            })
+            .fold(None, |best: Option<f32>, quality| {
+                Some(best.map_or(quality, |best| best.max(quality)))
+            })
+    };
+
+    match (quality("application/json"), quality("text/html")) {
+        (Some(json), Some(html)) => json > 0.0 && json >= html,
+        (Some(json), None) => json > 0.0,
+        _ => false,
+    }
+}
+
+/// Formats a size in bytes for humans (e.g. `1.5 KiB`)
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if size < 1024 {
+        return format!("{size} B");
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn serialize_time<S: serde::Serializer>(
+    time: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serializer.serialize_str(&DateTime::<Utc>::from(*time).to_rfc3339()),
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,38 @@
+use std::convert::Infallible;
+
+use futures_util::{StreamExt, TryStreamExt};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Empty, Full, StreamBody};
+use hyper::body::{Bytes, Frame};
+
+use crate::filesystem::ContentStream;
+
+/// The body type of every response sent by the server.
+///
+/// Small, static bodies (error messages, redirects) are sent in one piece, while files are
+/// streamed from the filesystem in chunks so memory usage stays flat regardless of their size.
+pub type Body = UnsyncBoxBody<Bytes, std::io::Error>;
+
+/// Creates a body that is sent in a single chunk
+pub fn full(data: impl Into<Bytes>) -> Body {
+    Full::new(data.into())
+        .map_err(|never: Infallible| match never {})
+        .boxed_unsync()
+}
+
+/// Creates a body without any content
+pub fn empty() -> Body {
+    Empty::new()
+        .map_err(|never: Infallible| match never {})
+        .boxed_unsync()
+}
+
+/// Creates a body that is streamed from the provided chunks
+pub fn stream(stream: ContentStream) -> Body {
+    StreamBody::new(stream.map_ok(Frame::data)).boxed_unsync()
+}
+
+/// Creates a body that streams each of the provided chunks in order
+pub fn concat(streams: Vec<ContentStream>) -> Body {
+    stream(Box::pin(futures_util::stream::iter(streams).flatten()))
+}
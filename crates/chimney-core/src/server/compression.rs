@@ -0,0 +1,347 @@
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hyper::body::Bytes;
+use log::debug;
+use sha2::{Digest, Sha256};
+
+use crate::config::{Compression, CompressionCache, Encoding};
+
+/// The maximum total size of the compressed variants kept in memory, shared by all sites
+pub const MEMORY_CACHE_CAPACITY: usize = 64 * 1024 * 1024;
+
+/// The maximum total size of the compressed variants kept in each disk cache directory
+pub const DISK_CACHE_CAPACITY: u64 = 1024 * 1024 * 1024;
+
+/// The encodings variants can be cached with, used to recognize the files of the disk cache
+const CACHED_ENCODINGS: [Encoding; 3] = [Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+
+/// The gzip level used for on-the-fly compression
+const GZIP_LEVEL: u32 = 6;
+
+/// The brotli quality used for on-the-fly compression, higher levels are too slow to do per request
+const BROTLI_QUALITY: i32 = 5;
+
+/// The brotli window size (log2) used for on-the-fly compression
+const BROTLI_WINDOW: i32 = 22;
+
+/// The zstd level used for on-the-fly compression
+const ZSTD_LEVEL: i32 = 3;
+
+/// Whether compressing content of the given MIME type is worth it
+///
+/// Most binary formats (images, video, archives, fonts) are already compressed, so compressing
+/// them again only wastes CPU time.
+pub fn is_compressible(mime_type: &str) -> bool {
+    let mime_type = mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if mime_type.starts_with("text/") || mime_type.ends_with("+json") || mime_type.ends_with("+xml")
+    {
+        return true;
+    }
+
+    matches!(
+        mime_type.as_str(),
+        "application/json"
+            | "application/javascript"
+            | "application/x-javascript"
+            | "application/xml"
+            | "application/wasm"
+            | "application/rtf"
+            | "application/x-sh"
+            | "application/x-csh"
+            | "application/vnd.ms-fontobject"
+            | "image/svg+xml"
+            | "image/bmp"
+            | "image/vnd.microsoft.icon"
+            | "font/ttf"
+            | "font/otf"
+    )
+}
+
+/// Whether a file of the given MIME type and size should be compressed on the fly
+pub fn should_compress(config: &Compression, mime_type: &str, size: u64) -> bool {
+    config.dynamic
+        && !config.encodings.is_empty()
+        && (config.min_size..=config.max_size).contains(&size)
+        && is_compressible(mime_type)
+}
+
+/// Compresses the content with the given encoding
+pub fn compress(content: &[u8], encoding: Encoding) -> Result<Vec<u8>, std::io::Error> {
+    match encoding {
+        Encoding::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(
+                Vec::with_capacity(content.len() / 2),
+                flate2::Compression::new(GZIP_LEVEL),
+            );
+            encoder.write_all(content)?;
+            encoder.finish()
+        }
+        Encoding::Brotli => {
+            let params = brotli::enc::BrotliEncoderParams {
+                quality: BROTLI_QUALITY,
+                lgwin: BROTLI_WINDOW,
+                ..Default::default()
+            };
+
+            let mut output = Vec::with_capacity(content.len() / 2);
+            brotli::BrotliCompress(&mut &content[..], &mut output, &params)?;
+            Ok(output)
+        }
+        Encoding::Zstd => zstd::stream::encode_all(content, ZSTD_LEVEL),
+    }
+}
+
+/// Identifies a compressed variant of a specific version of a file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// The path of the original file
+    pub path: PathBuf,
+
+    /// The last modified time of the original file
+    pub modified: SystemTime,
+
+    /// The size of the original file in bytes
+    pub size: u64,
+
+    /// The encoding of the variant
+    pub encoding: Encoding,
+}
+
+impl CacheKey {
+    pub fn new(path: &Path, modified: SystemTime, size: u64, encoding: Encoding) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified,
+            size,
+            encoding,
+        }
+    }
+
+    /// A SHA-256 digest of the key, used to name the files of the disk cache
+    ///
+    /// Unlike the standard library's hashers, it is the same across builds and restarts, so
+    /// entries written by a previous run are found again.
+    fn digest(&self) -> String {
+        let modified = self.modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let digest = Sha256::new()
+            .chain_update(self.path.as_os_str().as_encoded_bytes())
+            .chain_update([0])
+            .chain_update(modified.as_nanos().to_be_bytes())
+            .chain_update(self.size.to_be_bytes())
+            .chain_update(self.encoding.as_str())
+            .finalize();
+
+        digest[..16].iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// The path of the variant in a disk cache rooted at `directory`
+    ///
+    /// Since the last modified time is part of the digest, a changed file simply gets a new entry,
+    /// stale entries are left behind until they are evicted, and can be removed at any time.
+    pub fn disk_path(&self, directory: &Path) -> PathBuf {
+        directory.join(format!("{}.{}", self.digest(), self.encoding.extension()))
+    }
+}
+
+/// Whether a file in a disk cache directory is a cached variant, named by [`CacheKey::disk_path`]
+fn is_disk_entry(name: &OsStr) -> bool {
+    let Some((digest, extension)) = name.to_str().and_then(|name| name.split_once('.')) else {
+        return false;
+    };
+
+    digest.len() == 32
+        && digest.bytes().all(|b| b.is_ascii_hexdigit())
+        && CACHED_ENCODINGS
+            .iter()
+            .any(|encoding| encoding.extension() == extension)
+}
+
+#[derive(Debug, Default)]
+struct MemoryStore {
+    entries: HashMap<CacheKey, Bytes>,
+
+    /// Insertion order of the entries, the oldest ones are evicted first
+    order: VecDeque<CacheKey>,
+
+    /// The total size of all entries in bytes
+    size: usize,
+}
+
+/// A cache of compressed variants of files
+///
+/// The in-memory store is shared by every site using the `memory` cache, while sites using the
+/// `disk` cache each write to their own directory. Both are bounded in size, evicting the oldest
+/// variants first.
+#[derive(Debug)]
+pub struct CompressedCache {
+    memory: Mutex<MemoryStore>,
+    capacity: usize,
+    disk_capacity: u64,
+}
+
+impl CompressedCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            memory: Mutex::new(MemoryStore::default()),
+            capacity,
+            disk_capacity: DISK_CACHE_CAPACITY,
+        }
+    }
+
+    /// Sets the maximum total size of the variants kept in each disk cache directory
+    pub fn with_disk_capacity(mut self, disk_capacity: u64) -> Self {
+        self.disk_capacity = disk_capacity;
+        self
+    }
+
+    /// Looks up a compressed variant in the cache configured for a site
+    pub async fn get(&self, cache: &CompressionCache, key: &CacheKey) -> Option<Bytes> {
+        match cache {
+            CompressionCache::None => None,
+            CompressionCache::Memory => {
+                let store = self.memory.lock().ok()?;
+                store.entries.get(key).cloned()
+            }
+            CompressionCache::Disk(directory) => {
+                let path = key.disk_path(Path::new(directory));
+                tokio::fs::read(&path).await.ok().map(Bytes::from)
+            }
+        }
+    }
+
+    /// Stores a compressed variant in the cache configured for a site
+    ///
+    /// Failing to store a variant is not fatal, it will simply be compressed again next time.
+    pub async fn insert(&self, cache: &CompressionCache, key: CacheKey, content: Bytes) {
+        match cache {
+            CompressionCache::None => {}
+            CompressionCache::Memory => {
+                if content.len() > self.capacity {
+                    return;
+                }
+
+                let Ok(mut store) = self.memory.lock() else {
+                    return;
+                };
+
+                if let Some(previous) = store.entries.insert(key.clone(), content.clone()) {
+                    store.size -= previous.len();
+                } else {
+                    store.order.push_back(key);
+                }
+                store.size += content.len();
+
+                while store.size > self.capacity {
+                    let Some(oldest) = store.order.pop_front() else {
+                        break;
+                    };
+                    if let Some(evicted) = store.entries.remove(&oldest) {
+                        store.size -= evicted.len();
+                    }
+                }
+            }
+            CompressionCache::Disk(directory) => {
+                let capacity = self.disk_capacity;
+                if content.len() as u64 > capacity {
+                    return;
+                }
+
+                let directory = PathBuf::from(directory);
+                let written = tokio::task::spawn_blocking(move || {
+                    Self::write_to_disk(&directory, &key, &content)?;
+                    Self::evict_from_disk(&directory, capacity)
+                })
+                .await;
+
+                match written {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => debug!("Failed to write compressed variant to disk cache: {e}"),
+                    Err(e) => debug!("Failed to write compressed variant to disk cache: {e}"),
+                }
+            }
+        }
+    }
+
+    /// Returns the number of variants held in memory
+    pub fn memory_entries(&self) -> usize {
+        self.memory
+            .lock()
+            .map(|store| store.entries.len())
+            .unwrap_or_default()
+    }
+
+    /// Writes the variant to a temporary file first, so concurrent readers never see a partially
+    /// written entry
+    fn write_to_disk(directory: &Path, key: &CacheKey, content: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(directory)?;
+
+        let mut file = tempfile::NamedTempFile::new_in(directory)?;
+        file.write_all(content)?;
+        file.persist(key.disk_path(directory))
+            .map_err(|e| e.error)?;
+
+        Ok(())
+    }
+
+    /// Removes the oldest variants from a disk cache directory until their total size fits within
+    /// `capacity`
+    ///
+    /// Only files named like variants count, so temporary files that are still being written and
+    /// anything else in the directory are left alone.
+    fn evict_from_disk(directory: &Path, capacity: u64) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            if !is_disk_entry(&entry.file_name()) {
+                continue;
+            }
+
+            // Another request may have evicted the entry in the meantime
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+
+            total += metadata.len();
+            entries.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+
+        if total <= capacity {
+            return Ok(());
+        }
+
+        entries.sort_by_key(|(modified, ..)| *modified);
+        for (_, size, path) in entries {
+            if total <= capacity {
+                break;
+            }
+
+            debug!("Evicting compressed variant from disk cache: {path:?}");
+            if std::fs::remove_file(&path).is_ok() {
+                total -= size;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for CompressedCache {
+    fn default() -> Self {
+        Self::new(MEMORY_CACHE_CAPACITY)
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_util::TryStreamExt;
+use hyper::header::{self, HeaderValue};
+use hyper::{HeaderMap, Method};
+use log::debug;
+use sha2::{Digest, Sha256};
+
+use crate::filesystem::{AbstractFile, ContentStream};
+
+/// An entity tag as used in the `ETag`, `If-Match` and `If-None-Match` headers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+    /// Whether the tag is a weak validator
+    weak: bool,
+
+    /// The opaque tag value, without the surrounding quotes
+    tag: String,
+}
+
+impl EntityTag {
+    /// Creates a new strong entity tag
+    pub fn strong(tag: impl Into<String>) -> Self {
+        Self {
+            weak: false,
+            tag: tag.into(),
+        }
+    }
+
+    /// Creates a new weak entity tag
+    pub fn weak(tag: impl Into<String>) -> Self {
+        Self {
+            weak: true,
+            tag: tag.into(),
+        }
+    }
+
+    /// Creates a weak entity tag from the file's size and last modified time
+    ///
+    /// Returns `None` if either of them is unknown.
+    pub fn from_metadata(file: &AbstractFile) -> Option<Self> {
+        let size = file.size?;
+        let modified = file.modified_at?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+        Some(Self::weak(format!("{modified:x}-{size:x}")))
+    }
+
+    /// Creates a strong entity tag from the SHA-256 digest of the file's content
+    ///
+    /// The digest only depends on the content, so the tag stays the same across restarts and
+    /// toolchain upgrades.
+    pub fn from_content(content: &[u8]) -> Self {
+        Self::from_digest(Sha256::new_with_prefix(content), content.len())
+    }
+
+    /// Creates a strong entity tag by hashing a stream of the file's content chunk by chunk
+    ///
+    /// This produces the same tag as [`EntityTag::from_content`] without holding the whole
+    /// content in memory.
+    pub async fn from_stream(mut stream: ContentStream) -> Result<Self, std::io::Error> {
+        let mut hasher = Sha256::new();
+        let mut length = 0usize;
+
+        while let Some(chunk) = stream.try_next().await? {
+            hasher.update(&chunk);
+            length += chunk.len();
+        }
+
+        Ok(Self::from_digest(hasher, length))
+    }
+
+    /// Half of the digest is plenty to tell versions of a file apart, and keeps the tag short
+    fn from_digest(hasher: Sha256, length: usize) -> Self {
+        let digest = hasher.finalize();
+        let hex: String = digest[..16].iter().map(|b| format!("{b:02x}")).collect();
+        Self::strong(format!("{hex}-{length:x}"))
+    }
+
+    /// Derives a tag for another representation of the same content, e.g. a compressed variant
+    pub fn with_suffix(&self, suffix: &str) -> Self {
+        Self {
+            weak: self.weak,
+            tag: format!("{}-{suffix}", self.tag),
+        }
+    }
+
+    /// Whether the tag is a weak validator
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// The opaque tag value, without the surrounding quotes
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Strong comparison: both tags must be strong and have the same value
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Weak comparison: the tags must have the same value, regardless of their weakness
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+
+    /// Converts the tag to a header value
+    pub fn to_header_value(&self) -> HeaderValue {
+        // The tag is either generated by us or parsed from a valid header value, so this can only
+        // fail if someone constructs a tag with invalid characters by hand.
+        HeaderValue::from_str(&self.to_string()).unwrap_or(HeaderValue::from_static("\"\""))
+    }
+
+    /// Parses a comma-separated list of entity tags as found in `If-Match` and `If-None-Match`
+    ///
+    /// Returns `None` for the wildcard (`*`) value.
+    pub fn parse_list(value: &str) -> Option<Vec<EntityTag>> {
+        if value.trim() == "*" {
+            return None;
+        }
+
+        let mut tags = Vec::new();
+        let mut rest = value;
+
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+            if rest.is_empty() {
+                break;
+            }
+
+            let weak = rest.starts_with("W/");
+            if weak {
+                rest = &rest[2..];
+            }
+
+            // A malformed tag means we can't make sense of the rest of the list, so we just stop
+            // here and work with what we have.
+            let Some(quoted) = rest.strip_prefix('"') else {
+                debug!("Malformed entity tag list: {value}");
+                break;
+            };
+            let Some(end) = quoted.find('"') else {
+                debug!("Unterminated entity tag in list: {value}");
+                break;
+            };
+
+            tags.push(EntityTag {
+                weak,
+                tag: quoted[..end].to_string(),
+            });
+            rest = &quoted[end + 1..];
+        }
+
+        Some(tags)
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/\"{}\"", self.tag)
+        } else {
+            write!(f, "\"{}\"", self.tag)
+        }
+    }
+}
+
+/// The maximum number of files whose strong entity tags are kept in an [`EtagCache`]
+pub const ETAG_CACHE_CAPACITY: usize = 10_000;
+
+/// The strong entity tag of a specific version of a file
+#[derive(Debug, Clone)]
+struct CachedTag {
+    modified: SystemTime,
+    size: u64,
+    tag: EntityTag,
+}
+
+#[derive(Debug, Default)]
+struct TagStore {
+    entries: HashMap<PathBuf, CachedTag>,
+
+    /// Insertion order of the paths, the oldest ones are evicted first
+    order: VecDeque<PathBuf>,
+}
+
+/// A cache of the strong entity tags of files, so their content is only hashed again once their
+/// size or last modified time changes
+#[derive(Debug)]
+pub struct EtagCache {
+    store: Mutex<TagStore>,
+    capacity: usize,
+}
+
+impl EtagCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            store: Mutex::new(TagStore::default()),
+            capacity,
+        }
+    }
+
+    /// Looks up the tag of a file, if it was computed for the same version of the file
+    pub fn get(&self, path: &Path, modified: SystemTime, size: u64) -> Option<EntityTag> {
+        let store = self.store.lock().ok()?;
+        store
+            .entries
+            .get(path)
+            .filter(|cached| cached.modified == modified && cached.size == size)
+            .map(|cached| cached.tag.clone())
+    }
+
+    /// Stores the tag of a version of a file, replacing the tag of any previous version
+    pub fn insert(&self, path: &Path, modified: SystemTime, size: u64, tag: EntityTag) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let Ok(mut store) = self.store.lock() else {
+            return;
+        };
+
+        let cached = CachedTag {
+            modified,
+            size,
+            tag,
+        };
+        if store.entries.insert(path.to_path_buf(), cached).is_none() {
+            store.order.push_back(path.to_path_buf());
+        }
+
+        while store.entries.len() > self.capacity {
+            let Some(oldest) = store.order.pop_front() else {
+                break;
+            };
+            store.entries.remove(&oldest);
+        }
+    }
+
+    /// Returns the number of files whose tags are cached
+    pub fn len(&self) -> usize {
+        self.store
+            .lock()
+            .map(|store| store.entries.len())
+            .unwrap_or_default()
+    }
+
+    /// Whether no tags are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for EtagCache {
+    fn default() -> Self {
+        Self::new(ETAG_CACHE_CAPACITY)
+    }
+}
+
+/// The outcome of evaluating the conditional headers of a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition {
+    /// All preconditions passed, the request should be served as usual
+    Passed,
+
+    /// The client's cached representation is still fresh, respond with `304 Not Modified`
+    NotModified,
+
+    /// A precondition failed, respond with `412 Precondition Failed`
+    Failed,
+}
+
+/// Formats a timestamp as an HTTP date (e.g. `Sun, 06 Nov 1994 08:49:37 GMT`)
+pub fn format_http_date(time: SystemTime) -> String {
+    httpdate::fmt_http_date(time)
+}
+
+/// Evaluates the conditional request headers against the current state of a resource
+///
+/// The headers are evaluated in the order defined by RFC 9110 section 13.2.2:
+/// `If-Match`, `If-Unmodified-Since`, `If-None-Match` and finally `If-Modified-Since`.
+pub fn evaluate(
+    method: &Method,
+    headers: &HeaderMap<HeaderValue>,
+    etag: Option<&EntityTag>,
+    last_modified: Option<SystemTime>,
+) -> Precondition {
+    let is_get_or_head = method == Method::GET || method == Method::HEAD;
+
+    if let Some(value) = header_str(headers, &header::IF_MATCH) {
+        if !if_match(value, etag) {
+            debug!("If-Match precondition failed: {value}");
+            return Precondition::Failed;
+        }
+    } else if let Some(since) = header_date(headers, &header::IF_UNMODIFIED_SINCE)
+        && let Some(modified) = last_modified
+        && unix_seconds(modified) > unix_seconds(since)
+    {
+        debug!("If-Unmodified-Since precondition failed");
+        return Precondition::Failed;
+    }
+
+    if let Some(value) = header_str(headers, &header::IF_NONE_MATCH) {
+        if !if_none_match(value, etag) {
+            debug!("If-None-Match precondition failed: {value}");
+            return if is_get_or_head {
+                Precondition::NotModified
+            } else {
+                Precondition::Failed
+            };
+        }
+    } else if is_get_or_head
+        && let Some(since) = header_date(headers, &header::IF_MODIFIED_SINCE)
+        && let Some(modified) = last_modified
+        && unix_seconds(modified) <= unix_seconds(since)
+    {
+        debug!("Resource has not been modified since the provided date");
+        return Precondition::NotModified;
+    }
+
+    Precondition::Passed
+}
+
+/// Returns `true` if the `If-Match` condition holds (strong comparison)
+fn if_match(value: &str, etag: Option<&EntityTag>) -> bool {
+    match EntityTag::parse_list(value) {
+        // The resource exists, so the wildcard always matches
+        None => true,
+        Some(tags) => etag.is_some_and(|etag| tags.iter().any(|tag| tag.strong_eq(etag))),
+    }
+}
+
+/// Returns `true` if the `If-None-Match` condition holds (weak comparison)
+fn if_none_match(value: &str, etag: Option<&EntityTag>) -> bool {
+    match EntityTag::parse_list(value) {
+        // The resource exists, so the wildcard never holds
+        None => false,
+        Some(tags) => !etag.is_some_and(|etag| tags.iter().any(|tag| tag.weak_eq(etag))),
+    }
+}
+
+fn header_str<'a>(
+    headers: &'a HeaderMap<HeaderValue>,
+    name: &header::HeaderName,
+) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Reads an HTTP date header, invalid dates are ignored as required by the spec
+fn header_date(headers: &HeaderMap<HeaderValue>, name: &header::HeaderName) -> Option<SystemTime> {
+    header_str(headers, name).and_then(|value| httpdate::parse_http_date(value).ok())
+}
+
+/// HTTP dates only have a one second resolution, so all comparisons are done in whole seconds
+pub(crate) fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
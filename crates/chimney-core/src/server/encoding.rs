@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::Encoding;
+use crate::server::quality::parse_quality;
+
+/// A content coding listed in an `Accept-Encoding` header along with its quality value
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceptedEncoding {
+    /// The content coding token, lowercased (e.g. `gzip`, `br`, `identity` or `*`)
+    pub coding: String,
+
+    /// The quality value between 0 and 1, where 0 means "not acceptable"
+    pub quality: f32,
+}
+
+/// Parses the value of an `Accept-Encoding` header
+///
+/// Invalid quality values are treated as 0 (see [`parse_quality`]).
+pub fn parse_accept_encoding(value: &str) -> Vec<AcceptedEncoding> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (coding, quality) = parse_quality(entry);
+            if coding.is_empty() {
+                return None;
+            }
+
+            let coding = coding.to_ascii_lowercase();
+            Some(AcceptedEncoding { coding, quality })
+        })
+        .collect()
+}
+
+/// Picks the best encoding out of the `available` ones for the given `Accept-Encoding` header
+///
+/// The encoding with the highest quality value wins, ties are broken using the order of
+/// `available` (the server's preference). Returns `None` if the response should not be encoded,
+/// which is always the case when the client did not send an `Accept-Encoding` header.
+pub fn negotiate(accept_encoding: Option<&str>, available: &[Encoding]) -> Option<Encoding> {
+    let accepted = parse_accept_encoding(accept_encoding?);
+    let wildcard = accepted
+        .iter()
+        .find(|entry| entry.coding == "*")
+        .map(|entry| entry.quality);
+
+    let mut best: Option<(Encoding, f32)> = None;
+    for encoding in available {
+        let quality = accepted
+            .iter()
+            .find(|entry| Encoding::from_token(&entry.coding) == Some(*encoding))
+            .map(|entry| entry.quality)
+            .or(wildcard)
+            .unwrap_or(0.0);
+
+        if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((*encoding, quality));
+        }
+    }
+
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Returns the path of the precompressed sibling of a file (e.g. `app.js.br` for `app.js`)
+pub fn sidecar_path(file: &Path, encoding: Encoding) -> PathBuf {
+    let mut path = file.as_os_str().to_os_string();
+    path.push(".");
+    path.push(encoding.extension());
+    PathBuf::from(path)
+}
@@ -0,0 +1,50 @@
+use hyper::StatusCode;
+
+/// Renders the built-in error page for a status code
+///
+/// This is served whenever a site has no error page configured for the status code, or the
+/// configured one cannot be read.
+pub fn default_page(code: StatusCode) -> String {
+    let status = code.as_u16();
+    let reason = code.canonical_reason().unwrap_or("Error");
+    let message = match code {
+        StatusCode::BAD_REQUEST => "The server could not understand the request.",
+        StatusCode::UNAUTHORIZED => "You need to be authenticated to access this page.",
+        StatusCode::FORBIDDEN => "You do not have permission to access this page.",
+        StatusCode::NOT_FOUND => "The page you are looking for does not exist or has been moved.",
+        StatusCode::METHOD_NOT_ALLOWED => "This method is not allowed for the requested page.",
+        StatusCode::SERVICE_UNAVAILABLE => {
+            "The site is temporarily unavailable, please try again later."
+        }
+        code if code.is_server_error() => "Something went wrong while handling the request.",
+        _ => "The request could not be completed.",
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{status} {reason}</title>
+<style>
+body {{ font-family: system-ui, -apple-system, sans-serif; margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center; color: #1f2328; background: #f6f8fa; }}
+main {{ text-align: center; padding: 2rem; }}
+h1 {{ font-size: 4rem; font-weight: 700; margin: 0; }}
+h2 {{ font-size: 1.25rem; font-weight: 600; margin: 0.5rem 0 1rem; }}
+p {{ color: #59636e; margin: 0; }}
+footer {{ margin-top: 2rem; font-size: 0.8rem; color: #818b98; }}
+</style>
+</head>
+<body>
+<main>
+<h1>{status}</h1>
+<h2>{reason}</h2>
+<p>{message}</p>
+<footer>chimney</footer>
+</main>
+</body>
+</html>
+"#
+    )
+}
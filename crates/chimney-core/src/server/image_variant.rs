@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::ImageFormat;
+use crate::server::quality::parse_quality;
+
+/// Returns the quality value the `Accept` header gives to a media type, if it lists it
+///
+/// Only exact matches count, since browsers send `*/*` and `image/*` regardless of the formats
+/// they can actually decode.
+fn quality(accept: &str, media_type: &str) -> Option<f32> {
+    accept
+        .split(',')
+        .map(parse_quality)
+        .filter(|(kind, _)| kind.eq_ignore_ascii_case(media_type))
+        .map(|(_, quality)| quality)
+        .reduce(f32::max)
+}
+
+/// Picks the best image format out of the `available` ones for the given `Accept` header
+///
+/// The format with the highest quality value wins, ties are broken using the order of
+/// `available` (the site's preference). Returns `None` if the client doesn't explicitly accept
+/// any of them, in which case the original image is served.
+pub fn negotiate(accept: Option<&str>, available: &[ImageFormat]) -> Option<ImageFormat> {
+    let accept = accept?;
+
+    let mut best: Option<(ImageFormat, f32)> = None;
+    for format in available {
+        let quality = quality(accept, format.mime_type()).unwrap_or(0.0);
+        if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((*format, quality));
+        }
+    }
+
+    best.map(|(format, _)| format)
+}
+
+/// Returns the path of an image's variant in the given format (e.g. `photo.avif` for
+/// `photo.jpg`)
+pub fn variant_path(file: &Path, format: ImageFormat) -> PathBuf {
+    file.with_extension(format.extension())
+}
@@ -0,0 +1,66 @@
+use crate::server::quality::parse_quality;
+
+/// A language range listed in an `Accept-Language` header along with its quality value
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceptedLanguage {
+    /// The language range, lowercased (e.g. `fr-ch`, `en` or `*`)
+    pub range: String,
+
+    /// The quality value between 0 and 1, where 0 means "not acceptable"
+    pub quality: f32,
+}
+
+/// Parses the value of an `Accept-Language` header
+///
+/// Invalid quality values are treated as 0, the same as in `Accept-Encoding` (see
+/// [`parse_quality`]).
+pub fn parse_accept_language(value: &str) -> Vec<AcceptedLanguage> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (range, quality) = parse_quality(entry);
+            if range.is_empty() {
+                return None;
+            }
+
+            let range = range.to_ascii_lowercase();
+            Some(AcceptedLanguage { range, quality })
+        })
+        .collect()
+}
+
+/// Picks the best locale out of the `available` ones for the given `Accept-Language` header
+///
+/// Ranges are tried from the highest quality value down, in the order they are listed on ties.
+/// A range matches a locale that is the same (`fr` and `fr`), more specific (`fr` and `fr-ca`)
+/// or less specific (`fr-ch` and `fr`), exact matches being preferred. The wildcard `*` is left
+/// to the caller's default, so `None` is returned if no range matches.
+pub fn negotiate<'a>(accept_language: Option<&str>, available: &'a [String]) -> Option<&'a str> {
+    let mut accepted = parse_accept_language(accept_language?);
+    accepted.retain(|entry| entry.quality > 0.0 && entry.range != "*");
+    accepted.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+
+    accepted.iter().find_map(|entry| {
+        let range = entry.range.as_str();
+        let is_prefix = |prefix: &str, tag: &str| {
+            tag.len() > prefix.len()
+                && tag[..prefix.len()].eq_ignore_ascii_case(prefix)
+                && tag.as_bytes()[prefix.len()] == b'-'
+        };
+
+        available
+            .iter()
+            .find(|locale| locale.eq_ignore_ascii_case(range))
+            .or_else(|| available.iter().find(|locale| is_prefix(range, locale)))
+            .or_else(|| available.iter().find(|locale| is_prefix(locale, range)))
+            .map(String::as_str)
+    })
+}
+
+/// Returns the value of a cookie from a `Cookie` header
+pub fn cookie_value<'a>(cookie_header: &'a str, name: &str) -> Option<&'a str> {
+    cookie_header.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key.trim() == name).then(|| value.trim().trim_matches('"'))
+    })
+}
@@ -1,13 +1,265 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::ChimneyError;
 
 pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
 
+/// The charset appended to text MIME types unless configured otherwise
+pub const DEFAULT_CHARSET: &str = "utf-8";
+
+/// How many bytes at the start of a file are looked at to sniff its MIME type
+pub const SNIFF_LENGTH: usize = 512;
+
+/// Maps file extensions to MIME types, on top of the built-in ones (see [`from_extension`])
+///
+/// Extra types can be loaded from files in the `/etc/mime.types` format, where each line is a
+/// MIME type followed by its extensions. Text types get a `charset` parameter, unless they
+/// already have one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeRegistry {
+    /// The loaded types, by lowercase extension
+    types: HashMap<String, String>,
+
+    /// The charset appended to text types, if any
+    charset: Option<String>,
+}
+
+impl Default for MimeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MimeRegistry {
+    /// Creates a registry with only the built-in types and the default charset
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::new(),
+            charset: Some(DEFAULT_CHARSET.to_string()),
+        }
+    }
+
+    /// Sets the charset appended to text types, `None` leaves them without one
+    pub fn with_charset(mut self, charset: Option<impl Into<String>>) -> Self {
+        self.charset = charset.map(Into::into);
+        self
+    }
+
+    /// Returns the charset appended to text types, if any
+    pub fn charset(&self) -> Option<&str> {
+        self.charset.as_deref()
+    }
+
+    /// Maps an extension (with or without the leading dot) to a MIME type, replacing the
+    /// built-in or previously loaded type
+    pub fn insert(&mut self, extension: &str, mime_type: impl Into<String>) {
+        self.types
+            .insert(normalize_extension(extension), mime_type.into());
+    }
+
+    /// Adds the types from the content of an `/etc/mime.types`-style file
+    ///
+    /// Empty lines, comments (`#`) and types without extensions are skipped. Extensions that are
+    /// listed more than once keep the last type they are listed with.
+    pub fn parse(&mut self, input: &str) -> Result<(), String> {
+        for (index, line) in input.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(mime_type) = fields.next() else {
+                continue;
+            };
+
+            if !is_valid_mime_type(mime_type) {
+                return Err(format!(
+                    "line {}: `{mime_type}` is not a valid MIME type",
+                    index + 1
+                ));
+            }
+
+            for extension in fields {
+                self.insert(extension, mime_type);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the types from an `/etc/mime.types`-style file
+    pub fn load_file(&mut self, path: &Path) -> Result<(), ChimneyError> {
+        let input = std::fs::read_to_string(path).map_err(|e| ChimneyError::ConfigError {
+            field: "mime_types_files".to_string(),
+            message: format!("Failed to read `{}`: {e}", path.display()),
+        })?;
+
+        self.parse(&input)
+            .map_err(|message| ChimneyError::ConfigError {
+                field: "mime_types_files".to_string(),
+                message: format!("Invalid MIME types file `{}`, {message}", path.display()),
+            })
+    }
+
+    /// Returns the MIME type for an extension, without any parameters
+    pub fn lookup(&self, extension: &str) -> &str {
+        match self.types.get(&normalize_extension(extension)) {
+            Some(mime_type) => mime_type,
+            None => from_extension(extension),
+        }
+    }
+
+    /// Returns the MIME type for a file, if its extension is known to the registry or `overrides`
+    ///
+    /// `overrides` maps extensions to MIME types and takes precedence over the registry, which
+    /// is how a site's `mime_types` are applied.
+    pub fn find<'a>(
+        &'a self,
+        path: &Path,
+        overrides: &'a HashMap<String, String>,
+    ) -> Option<&'a str> {
+        let extension = path.extension().and_then(|extension| extension.to_str())?;
+
+        overrides
+            .iter()
+            .find(|(key, _)| normalize_extension(key) == normalize_extension(extension))
+            .map(|(_, mime_type)| mime_type.as_str())
+            .or_else(|| Some(self.lookup(extension)))
+            .filter(|mime_type| *mime_type != DEFAULT_MIME_TYPE)
+    }
+
+    /// Returns the `Content-Type` for a file, with the charset appended if it is a text type
+    ///
+    /// See [`MimeRegistry::find`] for how `overrides` are applied. An override that sets its own
+    /// `charset` (like `"text/plain; charset=iso-8859-1"`) is used as is.
+    pub fn content_type(&self, path: &Path, overrides: &HashMap<String, String>) -> String {
+        let mime_type = self.find(path, overrides).unwrap_or(DEFAULT_MIME_TYPE);
+        self.with_charset_parameter(mime_type)
+    }
+
+    /// Appends the charset to a text MIME type that doesn't have any parameters yet
+    pub fn with_charset_parameter(&self, mime_type: &str) -> String {
+        match &self.charset {
+            Some(charset) if is_text(mime_type) && !mime_type.contains(';') => {
+                format!("{mime_type}; charset={charset}")
+            }
+            _ => mime_type.to_string(),
+        }
+    }
+}
+
+/// Guesses the MIME type of content from its first bytes (see [`SNIFF_LENGTH`])
+///
+/// Only a few unambiguous formats are recognized: HTML, PNG, JPEG, GIF, WebP, PDF, JSON and
+/// WebAssembly. Anything else returns `None`, so it keeps being served as
+/// [`DEFAULT_MIME_TYPE`].
+pub fn sniff(content: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: [(&[u8], &str); 6] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\0asm", "application/wasm"),
+    ];
+
+    let content = &content[..content.len().min(SNIFF_LENGTH)];
+
+    if content.len() >= 12 && content.starts_with(b"RIFF") && &content[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+
+    // Text may start with a UTF-8 byte order mark and whitespace
+    let text = content.strip_prefix(b"\xef\xbb\xbf").unwrap_or(content);
+    let text = text.trim_ascii_start();
+    if is_html(text) {
+        return Some("text/html");
+    }
+    if is_json(text) {
+        return Some("application/json");
+    }
+
+    SIGNATURES
+        .into_iter()
+        .find(|(signature, _)| content.starts_with(signature))
+        .map(|(_, mime_type)| mime_type)
+}
+
+/// Whether the content starts with one of the tags HTML documents commonly start with
+fn is_html(content: &[u8]) -> bool {
+    const TAGS: [&[u8]; 6] = [
+        b"<!doctype html",
+        b"<html",
+        b"<head",
+        b"<body",
+        b"<!--",
+        b"<meta",
+    ];
+
+    TAGS.into_iter().any(|tag| {
+        content.len() > tag.len()
+            && content[..tag.len()].eq_ignore_ascii_case(tag)
+            && (tag == b"<!--" || matches!(content[tag.len()], b' ' | b'>' | b'\t' | b'\n' | b'\r'))
+    })
+}
+
+/// Whether the content starts like a JSON object or array
+fn is_json(content: &[u8]) -> bool {
+    let (first, rest) = match content.split_first() {
+        Some((first, rest)) => (*first, rest.trim_ascii_start()),
+        None => return false,
+    };
+
+    // Plain text can easily start with a bracket, so the next character has to fit too
+    matches!(
+        (first, rest.first()),
+        (b'{', Some(b'"' | b'}')) | (b'[', Some(b'{' | b'[' | b'"' | b']'))
+    )
+}
+
+/// Whether the MIME type is text that a charset applies to
+///
+/// This covers every `text/*` type, JavaScript and XML documents. JSON is always UTF-8, so it
+/// doesn't take a charset.
+pub fn is_text(mime_type: &str) -> bool {
+    let essence = mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    essence.starts_with("text/")
+        || essence == "application/javascript"
+        || essence == "application/xml"
+        || (essence.starts_with("application/") && essence.ends_with("+xml"))
+}
+
+/// Whether the value looks like a MIME type (`type/subtype`, optionally with parameters)
+pub fn is_valid_mime_type(mime_type: &str) -> bool {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    match essence.split_once('/') {
+        Some((kind, subtype)) => {
+            let is_token = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+            };
+            is_token(kind) && is_token(subtype)
+        }
+        None => false,
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_ascii_lowercase()
+}
+
 /// Returns the MIME type for a given file path.
 pub fn from_path(path: PathBuf) -> &'static str {
-    if let Some(extension) = path.extension() {
-        if let Some(ext_str) = extension.to_str() {
-            return from_extension(ext_str);
-        }
+    if let Some(extension) = path.extension()
+        && let Some(ext_str) = extension.to_str()
+    {
+        return from_extension(ext_str);
     }
     DEFAULT_MIME_TYPE
 }
@@ -27,6 +279,7 @@ pub fn from_extension(extension: &str) -> &'static str {
         "abw" => "application/x-abiword",
         "apng" => "image/apng",
         "arc" => "application/x-freearc",
+        "atom" => "application/atom+xml",
         "avif" => "image/avif",
         "avi" => "video/x-msvideo",
         "azw" => "application/vnd.amazon.ebook",
@@ -41,8 +294,14 @@ pub fn from_extension(extension: &str) -> &'static str {
         "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
         "eot" => "application/vnd.ms-fontobject",
         "epub" => "application/epub+zip",
+        "flac" => "audio/flac",
+        "geojson" => "application/geo+json",
         "gz" => "application/gzip",
         "gif" => "image/gif",
+        "glb" => "model/gltf-binary",
+        "gltf" => "model/gltf+json",
+        "heic" => "image/heic",
+        "heif" => "image/heif",
         "htc" => "text/x-component",
         "htm" | "html" | "stm" => "text/html",
         "htt" => "text/webviewhtml",
@@ -50,21 +309,25 @@ pub fn from_extension(extension: &str) -> &'static str {
         "ics" => "text/calendar",
         "jar" => "application/java-archive",
         "jpeg" | "jpg" => "image/jpeg",
-        "js" | "mjs" => "text/javascript",
+        "js" | "mjs" | "cjs" => "text/javascript",
         "json" => "application/json",
         "jsonld" => "application/ld+json",
         "jxl" => "image/jxl",
+        "m4a" => "audio/mp4",
+        "map" => "application/json",
+        "md" | "markdown" => "text/markdown",
         "mid" | "midi" => "audio/midi",
         "mht" | "mhtml" | "nws" => "message/rfc822",
         "mp3" => "audio/mpeg",
-        "mp4" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "mp4" | "m4v" => "video/mp4",
         "mpeg" | "mpg" | "mpa" | "mpe" | "mp2" | "mpv2" => "video/mpeg",
         "mpkg" => "application/vnd.apple.installer+xml",
         "mov" | "qt" => "video/quicktime",
         "odp" => "application/vnd.oasis.opendocument.presentation",
         "ods" => "application/vnd.oasis.opendocument.spreadsheet",
         "odt" => "application/vnd.oasis.opendocument.text",
-        "oga" => "audio/ogg",
+        "oga" | "ogg" => "audio/ogg",
         "ogv" => "video/ogg",
         "ogx" => "application/ogg",
         "opus" => "audio/opus",
@@ -77,20 +340,25 @@ pub fn from_extension(extension: &str) -> &'static str {
         "rgb" => "image/x-rgb",
         "rar" => "application/vnd.rar",
         "rtf" => "application/rtf",
+        "rss" => "application/rss+xml",
         "rtx" => "text/richtext",
         "sh" => "application/x-sh",
         "svg" => "image/svg+xml",
         "tar" => "application/x-tar",
         "tif" | "tiff" => "image/tiff",
-        "ts" => "video/mp2t",
+        "toml" => "application/toml",
+        "ts" | "mts" | "cts" | "tsx" => "text/typescript",
         "ttf" => "font/ttf",
         "txt" | "c" | "h" | "bas" => "text/plain",
+        "usdz" => "model/vnd.usdz+zip",
         "vcf" => "text/vcard",
         "vsd" => "application/vnd.visio",
+        "vtt" => "text/vtt",
         "wasm" => "application/wasm",
         "wav" => "audio/wav",
         "weba" => "audio/webm",
         "webm" => "video/webm",
+        "webmanifest" => "application/manifest+json",
         "webp" => "image/webp",
         "woff" => "font/woff",
         "woff2" => "font/woff2",
@@ -99,6 +367,7 @@ pub fn from_extension(extension: &str) -> &'static str {
         "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
         "xml" => "text/xml",
         "xul" => "application/vnd.mozilla.xul+xml",
+        "yaml" | "yml" => "application/yaml",
         "zip" => "application/zip",
         "3gp" => "video/3gpp",
         "3g2" => "video/3gpp2",
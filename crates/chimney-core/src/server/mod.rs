@@ -1,5 +1,17 @@
+pub mod auth;
+pub mod autoindex;
+pub mod body;
+pub mod compression;
+pub mod conditional;
+pub mod encoding;
+pub mod error_page;
+pub mod image_variant;
+pub mod language;
 pub mod mimetype;
+pub mod quality;
+pub mod range;
 pub mod redirect;
+pub mod request_path;
 pub mod service;
 
 use std::{net::SocketAddr, sync::Arc};
@@ -51,23 +63,23 @@ impl Server {
     ///
     /// let filesystem = Arc::new(LocalFilesystem::new());
     /// let config = Arc::new(Config::default());
-    /// let server = Server::new(filesystem, config);
+    /// let server = Server::new(filesystem, config)?;
     /// ```
     pub fn new(
         filesystem: Arc<dyn crate::filesystem::Filesystem>,
         config_handle: ConfigHandle,
-    ) -> Self {
+    ) -> Result<Self, ServerError> {
         debug!("Creating a new Chimney server instance");
-        let service = service::Service::new(filesystem.clone(), config_handle.clone());
+        let service = service::Service::new(filesystem.clone(), config_handle.clone())?;
 
-        Server {
+        Ok(Server {
             config_handle,
             signal: Arc::new(Notify::new()),
             graceful_shutdown: true,
             service,
             tls_manager: None,
             tls_acceptor: None,
-        }
+        })
     }
 
     /// Create a new server instance with TLS support enabled
@@ -102,8 +114,8 @@ impl Server {
         config_handle: ConfigHandle,
     ) -> Result<Self, ServerError> {
         debug!("Creating a new Chimney server instance with TLS");
+        let service = service::Service::new(filesystem.clone(), config_handle.clone())?;
         let config = config_handle.get();
-        let service = service::Service::new(filesystem.clone(), config_handle.clone());
 
         // Initialize TLS if any site has HTTPS enabled
         let (tls_manager, tls_acceptor) = if crate::tls::TlsManager::is_tls_enabled(&config) {
@@ -0,0 +1,20 @@
+/// Splits an entry of an `Accept`-style header, like `gzip;q=0.8`, into its value and quality
+///
+/// The quality defaults to 1 and is clamped between 0 and 1. Invalid quality values are treated
+/// as 0, so a malformed entry can never cause something the client did not ask for to be picked.
+pub fn parse_quality(entry: &str) -> (&str, f32) {
+    let mut parts = entry.split(';');
+    let value = parts.next().unwrap_or_default().trim();
+
+    let quality = parts
+        .find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("q")
+                .then(|| value.trim().parse::<f32>().unwrap_or(0.0))
+        })
+        .unwrap_or(1.0)
+        .clamp(0.0, 1.0);
+
+    (value, quality)
+}
@@ -0,0 +1,219 @@
+use std::hash::{BuildHasher, RandomState};
+use std::time::SystemTime;
+
+use log::debug;
+
+use crate::server::conditional::{EntityTag, unix_seconds};
+
+/// The maximum number of ranges we are willing to serve in a single response.
+///
+/// Requests with more ranges than this (after coalescing) are served in full instead, which the
+/// spec explicitly allows and protects us from clients asking for thousands of tiny ranges.
+pub const MAX_RANGES: usize = 32;
+
+/// An inclusive range of bytes within a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    /// The offset of the first byte in the range
+    pub start: u64,
+
+    /// The offset of the last byte in the range (inclusive)
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn new(start: u64, end: u64) -> Self {
+        Self { start, end }
+    }
+
+    /// The number of bytes covered by the range
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// The value of the `Content-Range` header for this range
+    pub fn content_range(&self, size: u64) -> String {
+        format!("bytes {}-{}/{size}", self.start, self.end)
+    }
+}
+
+/// The result of evaluating a `Range` header against a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ranges {
+    /// At least one of the requested ranges overlaps the file, respond with `206 Partial Content`
+    Satisfiable(Vec<ByteRange>),
+
+    /// None of the requested ranges overlap the file, respond with `416 Range Not Satisfiable`
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header value for a file of the given size
+///
+/// Returns `None` if the header should be ignored and the full file served instead, which is the
+/// case for unknown range units, malformed values and requests for too many ranges.
+pub fn parse(value: &str, size: u64) -> Option<Ranges> {
+    let (unit, specs) = value.trim().split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        debug!("Ignoring range request with unsupported unit: {unit}");
+        return None;
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs.split(',') {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            continue;
+        }
+
+        let (first, last) = spec.split_once('-')?;
+        let (first, last) = (first.trim(), last.trim());
+
+        let range = if first.is_empty() {
+            // A suffix range, e.g. `-500` for the last 500 bytes
+            let suffix: u64 = last.parse().ok()?;
+            if suffix == 0 || size == 0 {
+                continue;
+            }
+
+            ByteRange::new(size.saturating_sub(suffix), size - 1)
+        } else {
+            let start: u64 = first.parse().ok()?;
+            let end = match last {
+                "" => u64::MAX,
+                last => last.parse().ok()?,
+            };
+
+            if end < start {
+                // This is a syntactically invalid range, so the whole header is invalid
+                return None;
+            }
+
+            if start >= size {
+                continue;
+            }
+
+            ByteRange::new(start, end.min(size - 1))
+        };
+
+        ranges.push(range);
+    }
+
+    if ranges.is_empty() {
+        return Some(Ranges::Unsatisfiable);
+    }
+
+    let ranges = coalesce(ranges);
+    if ranges.len() > MAX_RANGES {
+        debug!(
+            "Ignoring range request with {} ranges (max: {MAX_RANGES})",
+            ranges.len()
+        );
+        return None;
+    }
+
+    Some(Ranges::Satisfiable(ranges))
+}
+
+/// Merges overlapping and adjacent ranges, so no byte is ever sent twice
+fn coalesce(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    if ranges.len() < 2 {
+        return ranges;
+    }
+
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+/// Evaluates the `If-Range` header against the file's current validators
+///
+/// Returns `true` if the `Range` header should be honored. An entity tag must match using the
+/// strong comparison function, and a date must exactly match the file's last modified time.
+pub fn if_range_matches(
+    value: Option<&str>,
+    etag: Option<&EntityTag>,
+    last_modified: Option<SystemTime>,
+) -> bool {
+    let Some(value) = value.map(str::trim) else {
+        return true;
+    };
+
+    if value.starts_with('"') || value.starts_with("W/") {
+        return match EntityTag::parse_list(value).as_deref() {
+            Some([tag]) => etag.is_some_and(|etag| etag.strong_eq(tag)),
+            _ => false,
+        };
+    }
+
+    match (httpdate::parse_http_date(value), last_modified) {
+        (Ok(date), Some(modified)) => unix_seconds(date) == unix_seconds(modified),
+        _ => false,
+    }
+}
+
+/// Builds the framing of a `multipart/byteranges` response body
+#[derive(Debug, Clone)]
+pub struct MultipartByteranges {
+    boundary: String,
+    content_type: String,
+    size: u64,
+}
+
+impl MultipartByteranges {
+    /// Creates a new multipart body for a file with the given content type and size
+    pub fn new(content_type: impl Into<String>, size: u64) -> Self {
+        // The boundary only needs to be unlikely to appear in the file's content
+        let boundary = format!("chimney-{:016x}", RandomState::new().hash_one(size));
+
+        Self {
+            boundary,
+            content_type: content_type.into(),
+            size,
+        }
+    }
+
+    /// The boundary separating the parts of the body
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// The value of the `Content-Type` header for the whole response
+    pub fn content_type(&self) -> String {
+        format!("multipart/byteranges; boundary={}", self.boundary)
+    }
+
+    /// The delimiter and headers that precede the content of a part
+    pub fn part_header(&self, range: &ByteRange) -> String {
+        format!(
+            "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            self.boundary,
+            self.content_type,
+            range.content_range(self.size)
+        )
+    }
+
+    /// The delimiter that closes the body after the last part
+    pub fn closing(&self) -> String {
+        format!("\r\n--{}--\r\n", self.boundary)
+    }
+
+    /// The total length of the body for the given ranges
+    pub fn content_length(&self, ranges: &[ByteRange]) -> u64 {
+        let parts: u64 = ranges
+            .iter()
+            .map(|range| self.part_header(range).len() as u64 + range.length())
+            .sum();
+
+        parts + self.closing().len() as u64
+    }
+}
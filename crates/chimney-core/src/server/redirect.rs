@@ -2,17 +2,15 @@
 
 use std::{future::Future, pin::Pin, sync::Arc};
 
-use http_body_util::Full;
 use hyper::{
-    body::{Bytes, Incoming},
-    header,
+    Request, Response, StatusCode, body::Incoming, header, http::uri::Scheme,
     service::Service as HyperService,
-    Request, Response, StatusCode,
 };
 use log::debug;
 
 use crate::config::ConfigHandle;
 
+use super::body::{self, Body};
 use super::service::Service;
 
 /// Redirect service that wraps the main service and handles HTTP→HTTPS redirects
@@ -34,7 +32,7 @@ impl RedirectService {
     }
 
     /// Build a redirect response using the resolved host
-    fn build_redirect_response(req: &Request<Incoming>, host: &str) -> Response<Full<Bytes>> {
+    fn build_redirect_response(req: &Request<Incoming>, host: &str) -> Response<Body> {
         let uri = req.uri();
         let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
 
@@ -45,22 +43,29 @@ impl RedirectService {
         Response::builder()
             .status(StatusCode::MOVED_PERMANENTLY)
             .header(header::LOCATION, location)
-            .body(Full::new(Bytes::from("Redirecting to HTTPS")))
+            .body(body::full("Redirecting to HTTPS"))
             .unwrap()
     }
 }
 
 impl HyperService<Request<Incoming>> for RedirectService {
-    type Response = Response<Full<Bytes>>;
+    type Response = Response<Body>;
     type Error = crate::error::ServerError;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
-    fn call(&self, req: Request<Incoming>) -> Self::Future {
+    fn call(&self, mut req: Request<Incoming>) -> Self::Future {
         let inner = self.inner.clone();
         let config_handle = self.config_handle.clone();
         let is_https = self.is_https;
 
         Box::pin(async move {
+            // The service has no other way to know which kind of connection the request came in on
+            req.extensions_mut().insert(if is_https {
+                Scheme::HTTPS
+            } else {
+                Scheme::HTTP
+            });
+
             // Only redirect if this is an HTTP request (not HTTPS)
             if is_https {
                 return inner.call(req).await;
@@ -90,7 +95,8 @@ impl HyperService<Request<Incoming>> for RedirectService {
             }
 
             // Check site-specific auto_redirect (defaults to true)
-            let should_redirect = if let Some(site) = config.sites.find_by_hostname(&resolved.host) {
+            let should_redirect = if let Some(site) = config.sites.find_by_hostname(&resolved.host)
+            {
                 site.https_config
                     .as_ref()
                     .map(|https| https.auto_redirect)
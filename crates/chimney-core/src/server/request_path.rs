@@ -0,0 +1,68 @@
+use std::path::{Component, Path};
+
+use percent_encoding::percent_decode_str;
+
+use crate::error::ServerError;
+
+/// Decodes and normalizes a request path into a path relative to the site's root
+///
+/// The path is percent-decoded first, so encoded separators and dots (`%2F`, `%2e%2e`) are
+/// treated exactly like their plain forms. Empty and `.` segments are dropped and `..` removes
+/// the segment before it, the result has neither a leading nor a trailing slash (`""` for the
+/// root itself).
+///
+/// Paths that would leave the root, aren't valid UTF-8 once decoded or contain a NUL byte or a
+/// backslash are rejected with [`ServerError::InvalidRequestPath`].
+pub fn normalize(path: &str) -> Result<String, ServerError> {
+    let invalid = |message: &str| ServerError::InvalidRequestPath {
+        path: path.to_string(),
+        message: message.to_string(),
+    };
+
+    let decoded = percent_decode_str(path)
+        .decode_utf8()
+        .map_err(|_| invalid("not valid UTF-8"))?;
+
+    if decoded.contains(['\0', '\\']) {
+        return Err(invalid("contains a NUL byte or a backslash"));
+    }
+
+    let mut segments = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(invalid("escapes the site's root"));
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let normalized = segments.join("/");
+
+    // Anything the platform treats specially when joining paths (like a drive prefix on
+    // Windows) could still replace the root
+    if !Path::new(&normalized)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(invalid("escapes the site's root"));
+    }
+
+    Ok(normalized)
+}
+
+/// Normalizes a request path into the form redirect and rewrite rules are matched against
+///
+/// This is the path [`normalize`] returns with a leading slash, and with a trailing one if the
+/// request had it, so `/blog/../posts/a%62c/` is matched as `/posts/abc/`.
+pub fn normalize_route(path: &str) -> Result<String, ServerError> {
+    let normalized = normalize(path)?;
+    if normalized.is_empty() || !path.ends_with('/') {
+        return Ok(format!("/{normalized}"));
+    }
+
+    Ok(format!("/{normalized}/"))
+}
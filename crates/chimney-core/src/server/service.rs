@@ -1,21 +1,38 @@
-use http_body_util::Full;
+use futures_util::{StreamExt, stream};
 use hyper::body::Bytes;
 use hyper::header::{self, HeaderName, HeaderValue};
+use hyper::http::request::Parts;
+use hyper::http::uri::Scheme;
 use hyper::service::Service as HyperService;
-use hyper::{HeaderMap, StatusCode};
-use hyper::{Request, Response, body::Incoming as IncomingBody};
-use log::{debug, info, trace};
-use std::path::PathBuf;
+use hyper::{HeaderMap, Method, StatusCode};
+use hyper::{Request, Response};
+use log::{debug, error, info, trace};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use crate::config::{ConfigHandle, RedirectRule, Site};
+use crate::config::{
+    Captures, Config, ConfigHandle, Encoding, EtagStrategy, ImageFormat, LocaleStrategy,
+    QueryHandling, RedirectRule, Site, SymlinkPolicy, TemplateContext, TrailingSlash,
+};
 use crate::error::ServerError;
-use crate::filesystem::FilesystemError;
+use crate::filesystem::{ContentStream, FilesystemError};
+use crate::server::auth;
+use crate::server::autoindex::{self, Listing, Sorting};
+use crate::server::body::{self, Body};
+use crate::server::compression::{self, CacheKey, CompressedCache};
+use crate::server::conditional::{self, EntityTag, EtagCache, Precondition};
+use crate::server::encoding;
+use crate::server::error_page;
+use crate::server::image_variant;
+use crate::server::language;
 use crate::server::mimetype;
+use crate::server::range::{self, MultipartByteranges, Ranges};
+use crate::server::request_path;
 use crate::with_leading_slash;
 
+#[derive(Clone)]
 pub struct DetectedHost {
     /// The detected host, which can be a domain or an IP address
     pub host: String,
@@ -37,12 +54,47 @@ pub struct Service {
 
     /// The configuration for the server
     config: ConfigHandle,
+
+    /// The cache of files compressed on the fly
+    compressed_cache: Arc<CompressedCache>,
+
+    /// The cache of strong entity tags, so files aren't hashed again until they change
+    etag_cache: Arc<EtagCache>,
+
+    /// The cache of verified credentials, so passwords aren't hashed for every request
+    credential_cache: Arc<auth::CredentialCache>,
+}
+
+/// Where the content of a response is read from
+enum Source {
+    /// A file, streamed from the filesystem
+    File(PathBuf),
+
+    /// Content that is already in memory, such as a file compressed on the fly
+    Memory(Bytes),
 }
 
 impl Service {
-    pub fn new(filesystem: Arc<dyn crate::filesystem::Filesystem>, config: ConfigHandle) -> Self {
+    /// Creates a service serving the sites of the configuration from the filesystem
+    ///
+    /// The files the sites keep their own configuration in (like `_redirects`, `_headers` and
+    /// `htpasswd` files) are loaded from the filesystem first, see [`Config::import_site_files`].
+    pub fn new(
+        filesystem: Arc<dyn crate::filesystem::Filesystem>,
+        config: ConfigHandle,
+    ) -> Result<Self, ServerError> {
         debug!("Creating a new Resolver instance");
-        Service { filesystem, config }
+        let mut loaded = Config::clone(&config.get());
+        loaded.import_site_files(filesystem.as_ref())?;
+        config.set(loaded)?;
+
+        Ok(Service {
+            filesystem,
+            config,
+            compressed_cache: Arc::new(CompressedCache::default()),
+            etag_cache: Arc::new(EtagCache::default()),
+            credential_cache: Arc::new(auth::CredentialCache::default()),
+        })
     }
 
     /// Resolves the host from the request headers using the cached resolved host header.
@@ -170,20 +222,143 @@ impl Service {
         Ok(resolved)
     }
 
+    /// Resolves the site's root directory relative to the sites directory
+    ///
+    /// This allows chimney-core to work as a library without requiring CLI preprocessing
+    fn site_root(&self, site: &Site) -> PathBuf {
+        self.config.get().site_root(site)
+    }
+
+    /// Resolves a directory path for the provided route, if the route points to a directory
+    ///
+    /// Routes are normalized the same way as in [`Service::resolve_file_from_route`], and
+    /// directories the site denies access to or that are outside of its root are never resolved.
+    pub fn resolve_directory_from_route(&self, route: &str, site: &Site) -> Option<PathBuf> {
+        let route = request_path::normalize(route).ok()?;
+        self.find_directory(&route, site)
+    }
+
+    /// Resolves the directory to list for the provided route, if the site's autoindex is enabled
+    /// for it
+    ///
+    /// The autoindex paths are matched against the normalized route, the same one the directory
+    /// is resolved from, so `/artifacts/../private/` can't list `/private/`.
+    fn resolve_listing_from_route(&self, route: &str, site: &Site) -> Option<PathBuf> {
+        let route = request_path::normalize(route).ok()?;
+        if !site.autoindex.is_enabled_for(&route) {
+            return None;
+        }
+
+        self.find_directory(&route, site)
+    }
+
+    /// Finds the directory a normalized route points to, if the site allows serving it
+    fn find_directory(&self, route: &str, site: &Site) -> Option<PathBuf> {
+        if site.is_denied(route) {
+            return None;
+        }
+
+        let path = self.site_root(site).join(route);
+
+        match self.filesystem.stat(path.clone()) {
+            Ok(stat) if stat.is_directory() => self
+                .is_confined_to_root(site, &path)
+                .is_ok_and(|confined| confined)
+                .then_some(path),
+            _ => None,
+        }
+    }
+
     /// Resolves a file path using the filesystem abstraction and the provided route
+    ///
+    /// The route is a (percent-encoded) URL path, which is decoded and normalized first, so it
+    /// can never point outside of the site's root. Routes that try to leave it fail with
+    /// [`ServerError::InvalidRequestPath`], and files only reachable through symlinks the site's
+    /// `symlinks` policy doesn't follow are not resolved at all.
+    ///
+    /// Paths the site denies access to (dotfiles, chimney's configuration files and the site's
+    /// `access` rules) fail with [`ServerError::AccessDenied`]. This is checked for the route
+    /// before the filesystem is touched, and again for the file it resolves to, like a
+    /// directory's index file.
     pub async fn resolve_file_from_route(
         &self,
         route: &str,
         site: &Site,
     ) -> Result<Option<PathBuf>, crate::error::ServerError> {
-        let route = route.trim_matches('/').to_string();
+        let route = request_path::normalize(route)?;
+        Self::ensure_access(site, &route)?;
+
+        let Some(file) = self.find_file_for_route(route, site).await? else {
+            return Ok(None);
+        };
+
+        if let Ok(relative) = file.strip_prefix(self.site_root(site)) {
+            Self::ensure_access(site, &relative.to_string_lossy())?;
+        }
+
+        if !self.is_confined_to_root(site, &file)? {
+            debug!("Not following symlinks to {file:?} for site: {}", site.name);
+            return Ok(None);
+        }
+
+        Ok(Some(file))
+    }
 
-        // Resolve the site's root directory relative to sites_directory
-        // This allows chimney-core to work as a library without requiring CLI preprocessing
+    /// Whether a file inside the site's root may be served under the site's symlink policy
+    ///
+    /// The root itself may be a symlink, but the place it points to has to be inside the sites
+    /// directory, or this fails with [`ServerError::SiteRootOutsideSitesDirectory`].
+    fn is_confined_to_root(&self, site: &Site, file: &Path) -> Result<bool, ServerError> {
         let config = self.config.get();
-        let path = PathBuf::from(&config.sites_directory)
-            .join(&site.name)
-            .join(&site.root);
+        let root = config.site_root(site);
+
+        let real_root = self.filesystem.canonicalize(root.clone())?;
+        let sites_directory = self
+            .filesystem
+            .canonicalize(PathBuf::from(&config.sites_directory))?;
+        if !real_root.starts_with(&sites_directory) {
+            return Err(ServerError::SiteRootOutsideSitesDirectory {
+                site: site.name.clone(),
+                root: real_root.to_string_lossy().to_string(),
+            });
+        }
+
+        if site.symlinks == SymlinkPolicy::Follow {
+            return Ok(true);
+        }
+
+        let real_file = self.filesystem.canonicalize(file.to_path_buf())?;
+        Ok(match site.symlinks {
+            SymlinkPolicy::Follow => true,
+            SymlinkPolicy::WithinRoot => real_file.starts_with(&real_root),
+            // Without any symlink in between, the real path is the same as the requested one
+            SymlinkPolicy::Never => file
+                .strip_prefix(&root)
+                .is_ok_and(|relative| real_file == real_root.join(relative)),
+        })
+    }
+
+    /// Fails with [`ServerError::AccessDenied`] if the path, relative to the site's root, must
+    /// not be served
+    fn ensure_access(site: &Site, path: &str) -> Result<(), ServerError> {
+        if !site.is_denied(path) {
+            return Ok(());
+        }
+
+        debug!("Access to {path} is denied for site: {}", site.name);
+        Err(ServerError::AccessDenied {
+            path: with_leading_slash!(path),
+            status: site.access.status,
+        })
+    }
+
+    /// Finds the file a route points to, without checking whether it may be served
+    async fn find_file_for_route(
+        &self,
+        route: String,
+        site: &Site,
+    ) -> Result<Option<PathBuf>, ServerError> {
+        let path = self.site_root(site);
 
         debug!(
             "Base path for site {}: {}",
@@ -200,7 +375,7 @@ impl Service {
         let stat = match self.filesystem.stat(path.join(&route)) {
             Ok(stat) => stat,
             Err(FilesystemError::NotFound(_)) => {
-                return Ok(None);
+                return Ok(self.resolve_clean_url(&route, site));
             }
             Err(e) => {
                 debug!("Failed to stat path: {route}, error: {e}");
@@ -217,16 +392,22 @@ impl Service {
             let path = path.join(&route);
 
             debug!("Path is a directory or empty, resolving to index file");
-            // We will resolve to the index file of the site, if it exists.
-            let dir_index_file = path.join(site.index_file());
-            debug!(
-                "Resolving to index file in directory: {}",
-                dir_index_file.to_string_lossy()
-            );
+            // We will resolve to the first index file of the site that exists, in the configured
+            // order.
+            let dir_index_file = site.index_files().into_iter().find_map(|file| {
+                let dir_index_file = path.join(file);
+                debug!(
+                    "Resolving to index file in directory: {}",
+                    dir_index_file.to_string_lossy()
+                );
+
+                matches!(self.filesystem.exists(dir_index_file.clone()), Ok(true))
+                    .then_some(dir_index_file)
+            });
 
-            match self.filesystem.exists(dir_index_file.clone()) {
-                Ok(true) => dir_index_file.to_string_lossy().to_string(),
-                _ => return Ok(None),
+            match dir_index_file {
+                Some(dir_index_file) => dir_index_file.to_string_lossy().to_string(),
+                None => return Ok(self.resolve_clean_url(&route, site)),
             }
         } else {
             path.join(route).to_string_lossy().to_string()
@@ -247,11 +428,81 @@ impl Service {
         Ok(Some(path.into()))
     }
 
-    /// The main function that handles incoming requests.
-    async fn handle_request(
+    /// Resolves a route without a matching file (or directory index) to an `.html` file, if the
+    /// site has clean URLs enabled
+    fn resolve_clean_url(&self, route: &str, site: &Site) -> Option<PathBuf> {
+        if !site.clean_urls.enabled || route.is_empty() || route.ends_with(".html") {
+            return None;
+        }
+
+        let path = self.site_root(site).join(format!("{route}.html"));
+        debug!("Trying clean URL for route: {route}, path: {path:?}");
+
+        match self.filesystem.stat(path.clone()) {
+            Ok(stat) if stat.is_file() => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Redirects a request for an `.html` file to its clean form, when the site asks for it and
+    /// the clean form serves the same file
+    async fn redirect_to_clean_url(
+        &self,
+        site: &Site,
+        path: &str,
+        request: &Parts,
+    ) -> Result<Option<Response<Body>>, ServerError> {
+        let Some(clean) = site.clean_urls.canonical_path(path, &site.index_files()) else {
+            return Ok(None);
+        };
+
+        let file = self.resolve_file_from_route(path, site).await?;
+        if file.is_none() || file != self.resolve_file_from_route(&clean, site).await? {
+            return Ok(None);
+        }
+
+        // Redirecting straight to the form the trailing slash policy expects saves a hop
+        let is_directory = self.resolve_directory_from_route(&clean, site).is_some();
+        let clean = site
+            .trailing_slash
+            .normalize(&clean, is_directory)
+            .unwrap_or(clean);
+
+        let target = QueryHandling::Preserve.apply(&clean, request.uri.query());
+        debug!("Redirecting {path} to its clean URL: {target}");
+        Ok(Some(self.respond(Status::Redirect {
+            code: StatusCode::MOVED_PERMANENTLY,
+            target,
+        })))
+    }
+
+    /// Redirects a request whose path doesn't follow the site's trailing slash policy
+    ///
+    /// Only [`TrailingSlash::Always`] needs to know whether the path is a directory, so the
+    /// filesystem is left alone for the other policies.
+    fn redirect_to_trailing_slash(
         &self,
-        req: Request<IncomingBody>,
-    ) -> Result<Response<Full<Bytes>>, ServerError> {
+        site: &Site,
+        path: &str,
+        request: &Parts,
+    ) -> Option<Response<Body>> {
+        let is_directory = site.trailing_slash == TrailingSlash::Always
+            && !path.ends_with('/')
+            && self.resolve_directory_from_route(path, site).is_some();
+
+        let target = site.trailing_slash.normalize(path, is_directory)?;
+        let target = QueryHandling::Preserve.apply(&target, request.uri.query());
+        debug!("Redirecting {path} to follow the trailing slash policy: {target}");
+        Some(self.respond(Status::Redirect {
+            code: StatusCode::MOVED_PERMANENTLY,
+            target,
+        }))
+    }
+
+    /// The main function that handles incoming requests.
+    ///
+    /// The request body is never read, so this works with any body type.
+    async fn handle_request<B>(&self, req: Request<B>) -> Result<Response<Body>, ServerError> {
         #[cfg(debug_assertions)]
         let start = std::time::Instant::now();
 
@@ -265,13 +516,14 @@ impl Service {
             req.method(),
             req.uri(),
             req.headers()
-                .get("User-Agent")
+                .get(header::USER_AGENT)
                 .unwrap_or(&HeaderValue::from_static("Unknown"))
                 .to_str()
                 .unwrap_or("Unknown")
         );
 
-        let headers = req.headers();
+        let (mut req, _) = req.into_parts();
+        let headers = &req.headers;
         trace!("Request headers: {headers:?}");
 
         let resolved = self.resolve_host(headers).await?;
@@ -294,51 +546,181 @@ impl Service {
             debug!("Not caching target header, auto-detect mode is disabled");
         }
 
+        // The detected host is kept around for templates in headers and redirect targets
+        req.extensions.insert(resolved.clone());
+
         let site = config
             .sites
             .find_by_hostname(&resolved.host)
             .ok_or_else(|| ServerError::SiteNotFound {
                 host: resolved.host.clone(),
             })?;
-        let path = with_leading_slash!(req.uri().path());
+
+        // Once we know which site the request is for, every error is answered with the site's
+        // error page for the matching status code.
+        let mut response = match self.handle_site_request(site, &req).await {
+            Ok(response) => response,
+            Err(error) => {
+                debug!("Failed to handle request for site {}: {error}", site.name);
+                self.respond_with_error(site, Self::error_status(&error), HeaderMap::new(), &req)
+            }
+        };
+        Self::apply_locale_headers(site, &req, &mut response);
+
+        #[cfg(debug_assertions)]
+        {
+            let elapsed = start.elapsed();
+            debug!(
+                "Handled request for {} in {:?} with response: {:?}",
+                req.uri.path(),
+                elapsed,
+                response.status(),
+            );
+        }
+
+        Ok(response)
+    }
+
+    /// Handles a request for a specific site
+    async fn handle_site_request(
+        &self,
+        site: &Site,
+        req: &Parts,
+    ) -> Result<Response<Body>, ServerError> {
+        let config = self.config.get();
+        let path = with_leading_slash!(req.uri.path());
+
+        // The method is checked before anything else, neither `OPTIONS` nor disallowed methods
+        // ever need to touch the filesystem.
+        if req.method == Method::OPTIONS {
+            debug!("Responding to OPTIONS request for path: {path}");
+            return Ok(self.respond(Status::NoContent {
+                headers: Self::allow_header(site),
+            }));
+        }
+
+        if !site.is_method_allowed(req.method.as_str()) {
+            debug!(
+                "Method {} is not allowed for site: {}",
+                req.method, site.name
+            );
+            return Ok(self.respond_with_error(
+                site,
+                StatusCode::METHOD_NOT_ALLOWED,
+                Self::allow_header(site),
+                req,
+            ));
+        }
+
+        // Protected paths need valid credentials before anything else, so not even their
+        // redirects are revealed. The path is normalized first, so `/%69nternal/` or
+        // `/public/../internal/` can't be used to get around the patterns.
+        let mut authenticated = !site.auth.is_enabled();
+        if !authenticated
+            && let Some(response) = self
+                .authenticate(
+                    site,
+                    req,
+                    &request_path::normalize(&path)?,
+                    &mut authenticated,
+                )
+                .await?
+        {
+            return Ok(response);
+        }
 
         // Redirects take precedence over rewrites, we need to check for that first before
-        // any attempt to normalize the path (with index.html for example) or rewrite it
-        if let Some(rule) = site.find_redirect_rule(path.as_str()) {
-            debug!("Found redirect rule for path: {}", req.uri().path());
-            return self.handle_redirect(rule);
+        // any attempt to normalize the path (with index.html for example) or rewrite it. Rules
+        // are matched on the decoded path, like the auth and access patterns.
+        if let Some((rule, captures)) =
+            site.match_redirect_rule(&request_path::normalize_route(&path)?)
+            && !(rule.is_files_first() && self.route_has_file(&path, site).await)
+            && let Some(response) = self.handle_redirect(site, rule, &captures, req)?
+        {
+            debug!("Found redirect rule for path: {}", req.uri.path());
+            return Ok(response);
         }
 
+        // The trailing slash policy applies to the path the client asked for, before it gets
+        // rewritten to anything else
+        if let Some(response) = self.redirect_to_trailing_slash(site, &path, req) {
+            return Ok(response);
+        }
+
+        // Requests for the root of a localized site are sent to the subtree of the locale the
+        // client prefers
+        let path = match Self::negotiate_locale(site, &path, req) {
+            Some(locale) if site.i18n.strategy == LocaleStrategy::Redirect => {
+                let target = QueryHandling::Preserve.apply(&format!("/{locale}/"), req.uri.query());
+                debug!("Redirecting {path} to the negotiated locale: {target}");
+                return Ok(self.respond(Status::Redirect {
+                    code: StatusCode::FOUND,
+                    target,
+                }));
+            }
+            Some(locale) => format!("/{locale}/"),
+            None => path,
+        };
+
         // We need to check for possible rewrite rules, since if there are any, we need to use the
         // configured rewrite path going forward.
-        let path = site
-            .find_rewrite_rule(path.as_str())
-            .map_or(path.to_string(), |rule| rule.target().to_string());
+        let rule = match site.find_rewrite_rule(&request_path::normalize_route(&path)?) {
+            Some(rule) if rule.is_files_first() && self.route_has_file(&path, site).await => None,
+            rule => rule,
+        };
+        let path = match rule {
+            Some(rule) => rule.target().to_string(),
+            None => {
+                // Only paths the client asked for are redirected to their clean form, never the
+                // target of a rewrite
+                if let Some(response) = self.redirect_to_clean_url(site, &path, req).await? {
+                    return Ok(response);
+                }
+
+                path
+            }
+        };
 
         debug!("Resolved path after rewrites: {path}");
 
+        // Rewrites (including the one to the negotiated locale) can point into a protected path
+        if !authenticated
+            && let Some(response) = self
+                .authenticate(
+                    site,
+                    req,
+                    &request_path::normalize(&path)?,
+                    &mut authenticated,
+                )
+                .await?
+        {
+            return Ok(response);
+        }
+
         let file = self.resolve_file_from_route(&path, site).await?;
 
         match file {
             Some(file) => {
                 debug!("Resolved file: {file:?}");
-                let response = self.respond_with_file(file, site);
 
-                #[cfg(debug_assertions)]
+                // So can the file a route resolves to, like a directory's index file
+                if let Some(response) = self
+                    .authenticate_file(site, req, &file, &mut authenticated)
+                    .await?
                 {
-                    let elapsed = start.elapsed();
-                    debug!(
-                        "Handled request for {} in {:?} with response: {:?}",
-                        req.uri().path(),
-                        elapsed,
-                        response.as_ref().map(|r| r.status()),
-                    );
+                    return Ok(response);
                 }
 
-                response
+                self.respond_with_file(file, site, req).await
             }
             None => {
-                info!("File not found for route: {}", req.uri().path());
+                // Directories without an index file can be served as a listing instead
+                if let Some(directory) = self.resolve_listing_from_route(&path, site) {
+                    debug!("Serving directory listing for: {directory:?}");
+                    return self.respond_with_listing(directory, site, req);
+                }
+
+                info!("File not found for route: {}", req.uri.path());
 
                 // If there is a fallback file configured, we will try to serve that instead.
                 if let Some(fallback) = &site.fallback_file {
@@ -353,17 +735,408 @@ impl Service {
                     );
 
                     if let Ok(true) = self.filesystem.exists(fallback_path.clone()) {
-                        return self.respond_with_file(fallback_path, site);
+                        if let Some(response) = self
+                            .authenticate_file(site, req, &fallback_path, &mut authenticated)
+                            .await?
+                        {
+                            return Ok(response);
+                        }
+
+                        return self.respond_with_file(fallback_path, site, req).await;
+                    }
+                }
+
+                Ok(self.respond_with_error(site, StatusCode::NOT_FOUND, HeaderMap::new(), req))
+            }
+        }
+    }
+
+    /// Whether a route resolves to a file, for the rules that only apply to paths that don't
+    async fn route_has_file(&self, path: &str, site: &Site) -> bool {
+        matches!(self.resolve_file_from_route(path, site).await, Ok(Some(_)))
+    }
+
+    /// Checks the credentials of a request if the route, relative to the site's root, is
+    /// protected by the site's `auth`
+    ///
+    /// Returns the `401 Unauthorized` response to send if they are missing or wrong, or `None`
+    /// if the request can go ahead. Hashing passwords is slow on purpose, so it is done on a
+    /// blocking thread, and at most once per request: `authenticated` is set once they are
+    /// verified (or up front, for sites without any users), so later routes are let through.
+    async fn authenticate(
+        &self,
+        site: &Site,
+        request: &Parts,
+        path: &str,
+        authenticated: &mut bool,
+    ) -> Result<Option<Response<Body>>, ServerError> {
+        if *authenticated || !site.auth.is_protected(path) {
+            return Ok(None);
+        }
+
+        self.require_credentials(site, request, path, authenticated)
+            .await
+    }
+
+    /// Checks the credentials of a request for a file that is about to be served, like
+    /// [`Service::authenticate`]
+    ///
+    /// A file outside of the site's root (like a fallback file elsewhere in the site's directory)
+    /// can't be matched against the protected paths, so it always needs credentials.
+    async fn authenticate_file(
+        &self,
+        site: &Site,
+        request: &Parts,
+        file: &Path,
+        authenticated: &mut bool,
+    ) -> Result<Option<Response<Body>>, ServerError> {
+        match file.strip_prefix(self.site_root(site)) {
+            Ok(relative) => {
+                self.authenticate(site, request, &relative.to_string_lossy(), authenticated)
+                    .await
+            }
+            Err(_) => {
+                self.require_credentials(site, request, &file.to_string_lossy(), authenticated)
+                    .await
+            }
+        }
+    }
+
+    /// Verifies the credentials of a request for a protected path, see [`Service::authenticate`]
+    ///
+    /// Credentials that were verified before are looked up in the service's cache instead of
+    /// hashing the password again.
+    async fn require_credentials(
+        &self,
+        site: &Site,
+        request: &Parts,
+        path: &str,
+        authenticated: &mut bool,
+    ) -> Result<Option<Response<Body>>, ServerError> {
+        if *authenticated {
+            return Ok(None);
+        }
+
+        let credentials = request
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(auth::parse_basic_credentials);
+
+        if let Some((user, password)) = credentials {
+            let hash = site.auth.password_hash(&user).map(str::to_string);
+            let cached = hash
+                .as_deref()
+                .is_some_and(|hash| self.credential_cache.contains(&user, &password, hash));
+
+            let verified = if cached {
+                true
+            } else {
+                let verify = tokio::task::spawn_blocking({
+                    let (password, hash) = (password.clone(), hash.clone());
+                    move || auth::verify_user_password(&password, hash.as_deref())
+                });
+
+                match verify.await {
+                    Ok(verified) => verified,
+                    Err(e) => {
+                        error!("Failed to verify the credentials of {user}: {e}");
+                        return Ok(Some(self.respond_with_error(
+                            site,
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            HeaderMap::new(),
+                            request,
+                        )));
                     }
                 }
+            };
+
+            if verified {
+                if let Some(hash) = &hash {
+                    self.credential_cache.insert(&user, &password, hash);
+                }
+
+                debug!("Authenticated {user} for /{path}");
+                *authenticated = true;
+                return Ok(None);
+            }
+        }
+
+        debug!("Missing or invalid credentials for /{path}");
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&auth::challenge(&site.auth.realm)) {
+            headers.insert(header::WWW_AUTHENTICATE, value);
+        }
+
+        Ok(Some(self.respond_with_error(
+            site,
+            StatusCode::UNAUTHORIZED,
+            headers,
+            request,
+        )))
+    }
+
+    /// Picks the locale a request for the root of a localized site is served in
+    ///
+    /// A cookie naming one of the site's locales takes precedence over the `Accept-Language`
+    /// header, and the default locale is used if the client doesn't accept any of them. Returns
+    /// `None` for any other path, or if the site isn't localized.
+    fn negotiate_locale<'a>(site: &'a Site, path: &str, request: &Parts) -> Option<&'a str> {
+        let i18n = &site.i18n;
+        if !i18n.is_enabled() || !path.trim_matches('/').is_empty() {
+            return None;
+        }
+
+        let from_cookie = i18n.cookie.as_deref().and_then(|name| {
+            request
+                .headers
+                .get_all(header::COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .find_map(|value| language::cookie_value(value, name))
+        });
+        let accept_language = request
+            .headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok());
+
+        from_cookie
+            .and_then(|locale| i18n.locales.iter().find(|l| l.eq_ignore_ascii_case(locale)))
+            .map(String::as_str)
+            .or_else(|| language::negotiate(accept_language, &i18n.locales))
+            .or_else(|| i18n.default_locale())
+    }
+
+    /// Adds `Vary` to responses for the root of a localized site, since they depend on the
+    /// client's language, and `Content-Language` to every response served in a locale
+    fn apply_locale_headers(site: &Site, request: &Parts, response: &mut Response<Body>) {
+        let path = request.uri.path();
+        let is_redirect = response.status().is_redirection();
+        let headers = response.headers_mut();
+
+        let locale = match Self::negotiate_locale(site, path, request) {
+            Some(locale) => {
+                headers.append(header::VARY, HeaderValue::from_static("Accept-Language"));
+                if site.i18n.cookie.is_some() {
+                    headers.append(header::VARY, HeaderValue::from_static("Cookie"));
+                }
+                locale
+            }
+            None => match site.i18n.locale_of(path) {
+                Some(locale) => locale,
+                None => return,
+            },
+        };
+
+        // A redirect isn't served in any language, the page it points to is
+        if !is_redirect
+            && !headers.contains_key(header::CONTENT_LANGUAGE)
+            && let Ok(value) = HeaderValue::from_str(locale)
+        {
+            headers.insert(header::CONTENT_LANGUAGE, value);
+        }
+    }
+
+    /// Maps an error that occurred while handling a request for a site to a status code
+    fn error_status(error: &ServerError) -> StatusCode {
+        match error {
+            ServerError::FilesystemError(FilesystemError::NotFound(_)) => StatusCode::NOT_FOUND,
+            ServerError::InvalidHeaderValue { .. } => StatusCode::BAD_REQUEST,
+            ServerError::InvalidRequestPath { .. } => StatusCode::BAD_REQUEST,
+            ServerError::AccessDenied { status, .. } => {
+                StatusCode::from_u16(*status).unwrap_or(StatusCode::NOT_FOUND)
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Responds with the site's error page for the status code, or the built-in default page if
+    /// the site has none configured (or it cannot be read).
+    ///
+    /// The error page is always served with the original status code, so clients and crawlers
+    /// never mistake it for a successful response.
+    pub fn respond_with_error(
+        &self,
+        site: &Site,
+        code: StatusCode,
+        mut headers: HeaderMap<HeaderValue>,
+        request: &Parts,
+    ) -> Response<Body> {
+        let config = self.config.get();
+        let page = site.error_page(code.as_u16()).and_then(|page| {
+            let path = self.site_root(site).join(page.trim_start_matches('/'));
+            match self.filesystem.read_file(path.clone()) {
+                Ok(content) => Some((
+                    content.into_bytes(),
+                    config.mime_types().content_type(&path, &site.mime_types),
+                )),
+                Err(e) => {
+                    debug!("Failed to read error page for {code}: {e}");
+                    None
+                }
+            }
+        });
+
+        let (content, content_type) = match page {
+            Some((content, mime_type)) => (content, mime_type),
+            None => (
+                error_page::default_page(code).into_bytes(),
+                "text/html; charset=utf-8".to_string(),
+            ),
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&content_type) {
+            headers.insert(header::CONTENT_TYPE, value);
+        }
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(content.len()));
+
+        Self::apply_response_headers(site, request, &content_type, &mut headers);
+
+        let body = if request.method == Method::HEAD {
+            body::empty()
+        } else {
+            body::full(content)
+        };
+
+        self.respond(Status::ErrorPage {
+            code,
+            body,
+            headers,
+        })
+    }
+
+    /// Returns the `Content-Type` a file is served with
+    ///
+    /// Files whose extension is missing or unknown are sniffed if the site enables it, and served
+    /// as `application/octet-stream` if that doesn't recognize them either.
+    async fn resolve_content_type(&self, file: &Path, site: &Site) -> String {
+        let config = self.config.get();
+        let mime_types = config.mime_types();
+        if let Some(mime_type) = mime_types.find(file, &site.mime_types) {
+            return mime_types.with_charset_parameter(mime_type);
+        }
 
-                Ok(self.respond(Status::NotFound))
+        if site.content_sniffing
+            && let Ok(mut stream) = self
+                .open_stream(file, 0, Some(mimetype::SNIFF_LENGTH as u64))
+                .await
+        {
+            let mut content = Vec::with_capacity(mimetype::SNIFF_LENGTH);
+            while let Some(Ok(chunk)) = stream.next().await {
+                content.extend_from_slice(&chunk);
+            }
+
+            if let Some(mime_type) = mimetype::sniff(&content) {
+                debug!("Sniffed {mime_type} for {file:?}");
+                return mime_types.with_charset_parameter(mime_type);
             }
         }
+
+        mimetype::DEFAULT_MIME_TYPE.to_string()
+    }
+
+    /// Applies the site's `response_headers` and then its header rules that match the request path
+    /// and the MIME type of the response, in order
+    ///
+    /// Every response gets `X-Content-Type-Options: nosniff` first, so browsers stick to the
+    /// `Content-Type` they are given. Sites can still replace or remove it.
+    fn apply_response_headers(site: &Site, request: &Parts, mime: &str, headers: &mut HeaderMap) {
+        headers.insert(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        );
+
+        let context = Self::template_context(request);
+        let path = context.path;
+        let parse = |name: &str, value: Option<&str>| {
+            let name = HeaderName::from_str(name).ok()?;
+            let value = value
+                .map(|value| HeaderValue::from_str(&site.templates().render(value, &context)))
+                .transpose()
+                .ok()?;
+            Some((name, value))
+        };
+
+        for (key, value) in &site.response_headers {
+            if let Some((name, Some(value))) = parse(key, Some(value)) {
+                headers.insert(name, value);
+            }
+        }
+
+        for rule in site
+            .header_rules
+            .iter()
+            .filter(|rule| rule.matches(path, mime))
+        {
+            trace!("Applying header rule {rule:?} to {path}");
+
+            for key in &rule.remove {
+                if let Some((name, _)) = parse(key, None) {
+                    headers.remove(name);
+                }
+            }
+
+            for (key, value) in &rule.set {
+                if let Some((name, Some(value))) = parse(key, Some(value)) {
+                    headers.insert(name, value);
+                }
+            }
+
+            for (key, value) in &rule.append {
+                if let Some((name, Some(value))) = parse(key, Some(value)) {
+                    headers.append(name, value);
+                }
+            }
+        }
+    }
+
+    /// Collects the request values available to templates
+    fn template_context(request: &Parts) -> TemplateContext<'_> {
+        let host = request
+            .extensions
+            .get::<DetectedHost>()
+            .map(|detected| detected.host.as_str())
+            .or_else(|| {
+                request
+                    .headers
+                    .get(header::HOST)
+                    .and_then(|value| value.to_str().ok())
+            })
+            .unwrap_or_default();
+
+        // A proxy terminating TLS in front of us knows better than the connection itself
+        let scheme = request
+            .headers
+            .get("x-forwarded-proto")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(',').next().unwrap_or_default().trim())
+            .filter(|scheme| {
+                scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+            })
+            .or_else(|| request.extensions.get::<Scheme>().map(Scheme::as_str))
+            .or_else(|| request.uri.scheme_str())
+            .unwrap_or("http");
+
+        TemplateContext {
+            scheme,
+            host,
+            path: request.uri.path(),
+            query: request.uri.query().unwrap_or_default(),
+            method: request.method.as_str(),
+        }
+    }
+
+    /// Builds the `Allow` header listing the methods a site responds to
+    fn allow_header(site: &Site) -> HeaderMap<HeaderValue> {
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&site.allowed_methods().join(", ")) {
+            headers.insert(header::ALLOW, value);
+        }
+        headers
     }
 
     /// Handles errors that occur during request processing.
-    fn handle_error(&self, error: ServerError) -> Response<Full<Bytes>> {
+    fn handle_error(&self, error: ServerError) -> Response<Body> {
         debug!("Handling error: {error}");
         let status = match error {
             ServerError::SiteNotFound { host } => {
@@ -398,18 +1171,52 @@ impl Service {
 pub enum Status {
     Ok {
         /// The body of the response
-        body: Vec<u8>,
+        body: Body,
+
+        /// The headers to include in the response
+        headers: HeaderMap<HeaderValue>,
+    },
+    PartialContent {
+        /// The body of the response, containing only the requested ranges
+        body: Body,
 
         /// The headers to include in the response
         headers: HeaderMap<HeaderValue>,
     },
+    NoContent {
+        /// The headers to include in the response
+        headers: HeaderMap<HeaderValue>,
+    },
+    NotModified {
+        /// The headers to include in the response
+        headers: HeaderMap<HeaderValue>,
+    },
     NotFound,
+
     InternalServerError,
     BadRequest,
+    PreconditionFailed,
+    RangeNotSatisfiable {
+        /// The full size of the requested file in bytes
+        size: u64,
+    },
     Redirect {
+        /// The redirect status code to return
+        code: StatusCode,
+
         /// The target URL or path to redirect to
         target: String,
     },
+    ErrorPage {
+        /// The HTTP status code to return
+        code: StatusCode,
+
+        /// The content of the error page
+        body: Body,
+
+        /// The headers to include in the response
+        headers: HeaderMap<HeaderValue>,
+    },
     GenericError {
         /// The error message to include in the response
         message: String,
@@ -425,38 +1232,85 @@ pub enum Status {
 const NOT_FOUND: &str = "Not Found";
 const INTERNAL_SERVER_ERROR: &str = "Internal Server Error";
 const BAD_REQUEST: &str = "Bad Request";
+const PRECONDITION_FAILED: &str = "Precondition Failed";
+const RANGE_NOT_SATISFIABLE: &str = "Range Not Satisfiable";
 
 impl Service {
-    fn respond(&self, status: Status) -> Response<Full<Bytes>> {
+    fn respond(&self, status: Status) -> Response<Body> {
         match status {
             Status::Ok { body, headers } => {
                 let mut response = Response::builder()
                     .status(StatusCode::OK)
-                    .body(Full::new(Bytes::from(body)))
+                    .body(body)
+                    .unwrap();
+
+                for (key, value) in headers.iter() {
+                    response.headers_mut().append(key.clone(), value.clone());
+                }
+
+                response
+            }
+            Status::PartialContent { body, headers } => {
+                let mut response = Response::builder()
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .body(body)
+                    .unwrap();
+
+                for (key, value) in headers.iter() {
+                    response.headers_mut().append(key.clone(), value.clone());
+                }
+
+                response
+            }
+            Status::NoContent { headers } => {
+                let mut response = Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(body::empty())
+                    .unwrap();
+
+                for (key, value) in headers.iter() {
+                    response.headers_mut().append(key.clone(), value.clone());
+                }
+
+                response
+            }
+            Status::NotModified { headers } => {
+                let mut response = Response::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .body(body::empty())
                     .unwrap();
 
                 for (key, value) in headers.iter() {
-                    response.headers_mut().insert(key.clone(), value.clone());
+                    response.headers_mut().append(key.clone(), value.clone());
                 }
 
                 response
             }
             Status::NotFound => Response::builder()
                 .status(StatusCode::NOT_FOUND)
-                .body(Full::new(Bytes::from(NOT_FOUND)))
+                .body(body::full(NOT_FOUND))
                 .unwrap(),
             Status::InternalServerError => Response::builder()
                 .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Full::new(Bytes::from(INTERNAL_SERVER_ERROR)))
+                .body(body::full(INTERNAL_SERVER_ERROR))
                 .unwrap(),
             Status::BadRequest => Response::builder()
                 .status(StatusCode::BAD_REQUEST)
-                .body(Full::new(Bytes::from(BAD_REQUEST)))
+                .body(body::full(BAD_REQUEST))
+                .unwrap(),
+            Status::PreconditionFailed => Response::builder()
+                .status(StatusCode::PRECONDITION_FAILED)
+                .body(body::full(PRECONDITION_FAILED))
+                .unwrap(),
+            Status::RangeNotSatisfiable { size } => Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{size}"))
+                .body(body::full(RANGE_NOT_SATISFIABLE))
                 .unwrap(),
-            Status::Redirect { target } => {
+            Status::Redirect { code, target } => {
                 let mut response = Response::builder()
-                    .status(StatusCode::FOUND) // Default to 302 Found
-                    .body(Full::new(Bytes::from(format!("Redirecting to {target}"))))
+                    .status(code)
+                    .body(body::full(format!("Redirecting to {target}")))
                     .unwrap();
 
                 response
@@ -465,6 +1319,19 @@ impl Service {
 
                 response
             }
+            Status::ErrorPage {
+                code,
+                body,
+                headers,
+            } => {
+                let mut response = Response::builder().status(code).body(body).unwrap();
+
+                for (key, value) in headers.iter() {
+                    response.headers_mut().append(key.clone(), value.clone());
+                }
+
+                response
+            }
             Status::GenericError {
                 message,
                 code,
@@ -472,11 +1339,11 @@ impl Service {
             } => {
                 let mut response = Response::builder()
                     .status(code)
-                    .body(Full::new(Bytes::from(message)))
+                    .body(body::full(message))
                     .unwrap();
 
                 for (key, value) in headers.iter() {
-                    response.headers_mut().insert(key.clone(), value.clone());
+                    response.headers_mut().append(key.clone(), value.clone());
                 }
 
                 response
@@ -485,73 +1352,609 @@ impl Service {
     }
 
     /// Responds with a file from the filesystem, setting the appropriate headers.
-    pub fn respond_with_file(
+    ///
+    /// The conditional headers of the request (`If-Match`, `If-None-Match`, `If-Modified-Since`
+    /// and `If-Unmodified-Since`) are evaluated against the file's validators, which may result in
+    /// a `304 Not Modified` or `412 Precondition Failed` response instead of the file's content.
+    ///
+    /// `HEAD` requests get the same headers as `GET` requests, without the body.
+    ///
+    /// `GET` requests with a `Range` header are served with `206 Partial Content` (or
+    /// `416 Range Not Satisfiable`), unless an `If-Range` validator no longer matches the file.
+    ///
+    /// If the client accepts it, a precompressed sibling of the file (e.g. `app.js.br`) is served
+    /// in its place with the matching `Content-Encoding`. Otherwise, compressible files may be
+    /// compressed on the fly if the site enables it.
+    ///
+    /// The file's content is streamed from the filesystem in chunks, it is never fully buffered
+    /// unless it has to be compressed on the fly.
+    pub async fn respond_with_file(
         &self,
         file: PathBuf,
         site: &Site,
-    ) -> Result<Response<Full<Bytes>>, ServerError> {
-        let mime_type = mimetype::from_path(file.clone());
-        let content = self
+        request: &Parts,
+    ) -> Result<Response<Body>, ServerError> {
+        // Images can be swapped for a variant in a format the client accepts, which is then served
+        // like any other file
+        let variants = self.find_image_variants(&file, site);
+        let accept = request
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok());
+        let available = variants.iter().map(|(format, _)| *format);
+        let format = image_variant::negotiate(accept, &available.collect::<Vec<_>>());
+
+        let file = match format.and_then(|format| variants.iter().find(|(f, _)| *f == format)) {
+            Some((format, variant)) => {
+                debug!("Serving {format} variant: {variant:?}");
+                variant.clone()
+            }
+            None => file,
+        };
+
+        // The MIME type comes from the image variant if there is one, but never from the
+        // precompressed sibling
+        let mime_type = self.resolve_content_type(&file, site).await;
+        let mime_type = mime_type.as_str();
+
+        let sidecars = self.find_precompressed_sidecars(&file, site);
+        let accept_encoding = request
+            .headers
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok());
+        let available = sidecars.iter().map(|(encoding, _)| *encoding);
+        let encoding = encoding::negotiate(accept_encoding, &available.collect::<Vec<_>>());
+
+        let file = match encoding.and_then(|encoding| sidecars.iter().find(|(e, _)| *e == encoding))
+        {
+            Some((encoding, sidecar)) => {
+                debug!("Serving precompressed ({encoding}) file: {sidecar:?}");
+                sidecar.clone()
+            }
+            None => file,
+        };
+
+        // Not every filesystem is able to provide metadata for every file, in which case we just
+        // serve the file without validators.
+        let metadata = self.filesystem.stat(file.clone()).ok();
+        let last_modified = metadata.as_ref().and_then(|stat| stat.modified_at);
+
+        // We need the size up front to set the `Content-Length` and evaluate ranges. Reading the
+        // whole file into memory to find it out would defeat streaming it, so it is an error.
+        let size = metadata
+            .as_ref()
+            .and_then(|stat| stat.size)
+            .ok_or_else(|| {
+                ServerError::FilesystemError(FilesystemError::MetadataError {
+                    path: file.clone(),
+                    message: "the size of the file is unknown".to_string(),
+                })
+            })?;
+
+        // Files with a precompressed sibling are never compressed on the fly, even if the client
+        // does not accept any of the sibling's encodings. Neither are byte ranges, compressing the
+        // whole file just to send a slice of it isn't worth it.
+        let compressible =
+            sidecars.is_empty() && compression::should_compress(&site.compression, mime_type, size);
+        let is_range_request =
+            request.method == Method::GET && request.headers.contains_key(header::RANGE);
+        let dynamic_encoding = if compressible && !is_range_request {
+            encoding::negotiate(accept_encoding, &site.compression.encodings)
+        } else {
+            None
+        };
+
+        let etag = match site.etag {
+            EtagStrategy::Weak => metadata.as_ref().and_then(EntityTag::from_metadata),
+            EtagStrategy::Strong => Some(self.strong_etag(&file, last_modified, size).await?),
+            EtagStrategy::Disabled => None,
+        };
+
+        // The compressed variant is a different representation, so it needs its own tag
+        let etag = match dynamic_encoding {
+            Some(encoding) => etag.map(|etag| etag.with_suffix(encoding.as_str())),
+            None => etag,
+        };
+
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(mime_type) {
+            headers.insert(header::CONTENT_TYPE, value);
+        }
+
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+        // Caches need to know the response depends on `Accept-Encoding` as soon as there is more
+        // than one representation of the file, even if we are serving the uncompressed one.
+        if !sidecars.is_empty() || compressible {
+            headers.insert(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+        }
+
+        // The same goes for `Accept` as soon as the image has a variant
+        if !variants.is_empty() {
+            headers.append(header::VARY, HeaderValue::from_static("Accept"));
+        }
+
+        if let Some(encoding) = encoding.or(dynamic_encoding) {
+            headers.insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.as_str()),
+            );
+        }
+
+        if let Some(etag) = &etag {
+            headers.insert(header::ETAG, etag.to_header_value());
+        }
+
+        if let Some(modified) = last_modified
+            && let Ok(value) = HeaderValue::from_str(&conditional::format_http_date(modified))
+        {
+            headers.insert(header::LAST_MODIFIED, value);
+        }
+
+        Self::apply_response_headers(site, request, mime_type, &mut headers);
+
+        match conditional::evaluate(
+            &request.method,
+            &request.headers,
+            etag.as_ref(),
+            last_modified,
+        ) {
+            Precondition::Passed => {}
+            Precondition::NotModified => {
+                debug!("Responding with 304 Not Modified for file: {file:?}");
+                return Ok(self.respond(Status::NotModified { headers }));
+            }
+            Precondition::Failed => {
+                debug!("Precondition failed for file: {file:?}");
+                return Ok(self.respond(Status::PreconditionFailed));
+            }
+        }
+
+        let (source, size) = match dynamic_encoding {
+            // A `HEAD` response only needs the length of the compressed variant, which is left out
+            // rather than compressing the file just to find it out
+            Some(encoding) if request.method == Method::HEAD => {
+                if let Some(content) = self
+                    .cached_variant(&file, site, encoding, last_modified, size)
+                    .await
+                {
+                    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(content.len()));
+                }
+
+                let body = body::empty();
+                return Ok(self.respond(Status::Ok { body, headers }));
+            }
+            Some(encoding) => {
+                let content = self
+                    .compress_file(&file, site, encoding, last_modified, size)
+                    .await?;
+                let size = content.len() as u64;
+                (Source::Memory(content), size)
+            }
+            None => (Source::File(file), size),
+        };
+
+        if let Some(ranges) = Self::requested_ranges(request, size, etag.as_ref(), last_modified) {
+            return self
+                .respond_with_ranges(&source, size, ranges, mime_type, headers)
+                .await;
+        }
+
+        // The body is streamed, so hyper has no way of knowing its length unless we tell it
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(size));
+
+        // A `HEAD` response carries the same headers as a `GET`, including the `Content-Length`,
+        // but there is no point in reading the file.
+        let body = if request.method == Method::HEAD {
+            body::empty()
+        } else {
+            body::stream(self.open_source(&source, 0, None).await?)
+        };
+
+        Ok(self.respond(Status::Ok { body, headers }))
+    }
+
+    /// Returns the strong entity tag of a file, hashing its content only if the cache has no tag
+    /// for this version of the file
+    ///
+    /// Without a last modified time, there is no telling whether a cached tag is stale, so the
+    /// file is hashed every time.
+    async fn strong_etag(
+        &self,
+        file: &Path,
+        last_modified: Option<std::time::SystemTime>,
+        size: u64,
+    ) -> Result<EntityTag, ServerError> {
+        if let Some(modified) = last_modified
+            && let Some(etag) = self.etag_cache.get(file, modified, size)
+        {
+            return Ok(etag);
+        }
+
+        debug!("Hashing file for its entity tag: {file:?}");
+        let stream = self.open_stream(file, 0, None).await?;
+        let etag = EntityTag::from_stream(stream).await.map_err(|e| {
+            ServerError::FilesystemError(FilesystemError::ReadFileError {
+                path: file.to_path_buf(),
+                message: e.to_string(),
+            })
+        })?;
+
+        if let Some(modified) = last_modified {
+            self.etag_cache.insert(file, modified, size, etag.clone());
+        }
+
+        Ok(etag)
+    }
+
+    /// Compresses a file on the fly, or fetches the compressed variant from the site's cache.
+    ///
+    /// Variants are only cached if the file's last modified time is known, since that is the only
+    /// way to tell a cached variant is stale.
+    async fn compress_file(
+        &self,
+        file: &Path,
+        site: &Site,
+        encoding: Encoding,
+        last_modified: Option<std::time::SystemTime>,
+        size: u64,
+    ) -> Result<Bytes, ServerError> {
+        if let Some(content) = self
+            .cached_variant(file, site, encoding, last_modified, size)
+            .await
+        {
+            debug!("Serving cached compressed ({encoding}) variant of file: {file:?}");
+            return Ok(content);
+        }
+
+        debug!("Compressing file on the fly with {encoding}: {file:?}");
+        let compression_error = |message: String| ServerError::CompressionFailed {
+            path: file.to_string_lossy().to_string(),
+            encoding: encoding.to_string(),
+            message,
+        };
+
+        // Reading the file blocks and compression is CPU-bound, so we keep both off the async
+        // worker threads
+        let filesystem = self.filesystem.clone();
+        let path = file.to_path_buf();
+        let compressed = tokio::task::spawn_blocking(move || {
+            let content = filesystem.read_file(path)?.into_bytes();
+            Ok::<_, FilesystemError>(compression::compress(&content, encoding))
+        })
+        .await
+        .map_err(|e| compression_error(e.to_string()))?
+        .map_err(ServerError::FilesystemError)?
+        .map_err(|e| compression_error(e.to_string()))?;
+
+        let compressed = Bytes::from(compressed);
+        if let Some(modified) = last_modified {
+            let key = CacheKey::new(file, modified, size, encoding);
+            self.compressed_cache
+                .insert(&site.compression.cache, key, compressed.clone())
+                .await;
+        }
+
+        Ok(compressed)
+    }
+
+    /// Fetches the compressed variant of a file from the site's cache, if it is cached for this
+    /// version of the file
+    async fn cached_variant(
+        &self,
+        file: &Path,
+        site: &Site,
+        encoding: Encoding,
+        last_modified: Option<std::time::SystemTime>,
+        size: u64,
+    ) -> Option<Bytes> {
+        let key = CacheKey::new(file, last_modified?, size, encoding);
+        self.compressed_cache
+            .get(&site.compression.cache, &key)
+            .await
+    }
+
+    /// Responds with a listing of a directory's content, as HTML or as JSON if the client asks for
+    /// it in its `Accept` header.
+    ///
+    /// The listing can be sorted with the `sort` (`name`, `size` or `modified`) and `order` (`asc`
+    /// or `desc`) query parameters.
+    pub fn respond_with_listing(
+        &self,
+        directory: PathBuf,
+        site: &Site,
+        request: &Parts,
+    ) -> Result<Response<Body>, ServerError> {
+        let root = self.site_root(site);
+        let relative = directory
+            .strip_prefix(&root)
+            .unwrap_or(&directory)
+            .to_path_buf();
+        let mut files = self
             .filesystem
-            .read_file(file)
+            .read_dir(directory)
             .map_err(ServerError::FilesystemError)?;
 
+        // Denied files are left out, the same as hidden ones
+        files.retain(|file| {
+            let name = file.path.file_name().unwrap_or_default();
+            !site.is_denied(&relative.join(name).to_string_lossy())
+        });
+
+        let sorting = Sorting::from_query(request.uri.query());
+        let mut listing = Listing::new(request.uri.path(), files, site.autoindex.show_hidden);
+        listing.sort(sorting);
+
+        let accept = request
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok());
+        let (content, content_type) = if autoindex::prefers_json(accept) {
+            (listing.to_json(), "application/json")
+        } else {
+            (listing.to_html(sorting), "text/html; charset=utf-8")
+        };
+
         let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers.insert(header::VARY, HeaderValue::from_static("Accept"));
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(content.len()));
+
+        Self::apply_response_headers(site, request, content_type, &mut headers);
+
+        let body = if request.method == Method::HEAD {
+            body::empty()
+        } else {
+            body::full(content)
+        };
+
+        Ok(self.respond(Status::Ok { body, headers }))
+    }
+
+    /// Finds the precompressed siblings of a file enabled for the site, in order of preference
+    fn find_precompressed_sidecars(&self, file: &Path, site: &Site) -> Vec<(Encoding, PathBuf)> {
+        site.compression
+            .precompressed
+            .iter()
+            .map(|encoding| (*encoding, encoding::sidecar_path(file, *encoding)))
+            .filter(|(_, path)| self.is_servable_sibling(site, path))
+            .collect()
+    }
+
+    /// Whether a sibling of a file (like a precompressed sidecar or an image variant) may be
+    /// served in its place
+    ///
+    /// It is held to the same rules as any other file: the site must not deny access to it, and
+    /// it has to stay inside the site's root under the site's symlink policy.
+    fn is_servable_sibling(&self, site: &Site, path: &Path) -> bool {
+        let allowed = path
+            .strip_prefix(self.site_root(site))
+            .is_ok_and(|relative| !site.is_denied(&relative.to_string_lossy()));
+
+        allowed
+            && matches!(self.filesystem.exists(path.to_path_buf()), Ok(true))
+            && self
+                .is_confined_to_root(site, path)
+                .is_ok_and(|confined| confined)
+    }
+
+    /// Finds the variants of an image in the formats enabled for the site, in order of preference
+    fn find_image_variants(&self, file: &Path, site: &Site) -> Vec<(ImageFormat, PathBuf)> {
+        if !site.image_variants.applies_to(file) {
+            return Vec::new();
+        }
+
+        site.image_variants
+            .formats
+            .iter()
+            .map(|format| (*format, image_variant::variant_path(file, *format)))
+            .filter(|(_, path)| self.is_servable_sibling(site, path))
+            .collect()
+    }
+
+    /// Opens a stream of (part of) a file's content
+    async fn open_stream(
+        &self,
+        file: &Path,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Result<ContentStream, ServerError> {
+        self.filesystem
+            .read_stream(file.to_path_buf(), offset, length)
+            .await
+            .map_err(ServerError::FilesystemError)
+    }
+
+    /// Opens a stream of (part of) the content of a response
+    async fn open_source(
+        &self,
+        source: &Source,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Result<ContentStream, ServerError> {
+        match source {
+            Source::File(file) => self.open_stream(file, offset, length).await,
+            Source::Memory(content) => {
+                let start = (offset as usize).min(content.len());
+                let end = match length {
+                    Some(length) => start.saturating_add(length as usize).min(content.len()),
+                    None => content.len(),
+                };
+
+                let chunk = content.slice(start..end);
+                Ok(Box::pin(stream::once(async move { Ok(chunk) })))
+            }
+        }
+    }
+
+    /// Returns the byte ranges requested by the client, if the `Range` header should be honored.
+    fn requested_ranges(
+        request: &Parts,
+        size: u64,
+        etag: Option<&EntityTag>,
+        last_modified: Option<std::time::SystemTime>,
+    ) -> Option<Ranges> {
+        // The spec requires us to ignore the `Range` header for any method other than GET
+        if request.method != Method::GET {
+            return None;
+        }
+
+        let value = request.headers.get(header::RANGE)?.to_str().ok()?;
+        let if_range = request
+            .headers
+            .get(header::IF_RANGE)
+            .and_then(|value| value.to_str().ok());
+
+        if !range::if_range_matches(if_range, etag, last_modified) {
+            debug!("If-Range validator does not match, serving the full file");
+            return None;
+        }
+
+        range::parse(value, size)
+    }
+
+    /// Responds with the requested ranges of a file's content.
+    async fn respond_with_ranges(
+        &self,
+        source: &Source,
+        size: u64,
+        ranges: Ranges,
+        mime_type: &str,
+        mut headers: HeaderMap<HeaderValue>,
+    ) -> Result<Response<Body>, ServerError> {
+        let ranges = match ranges {
+            Ranges::Unsatisfiable => {
+                debug!("Requested range is not satisfiable for a file of {size} bytes");
+                return Ok(self.respond(Status::RangeNotSatisfiable { size }));
+            }
+            Ranges::Satisfiable(ranges) => ranges,
+        };
+
+        if let [range] = ranges.as_slice() {
+            debug!("Responding with a single range: {range:?}");
+            headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&range.content_range(size)).unwrap(),
+            );
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(range.length()));
+
+            return Ok(self.respond(Status::PartialContent {
+                body: body::stream(
+                    self.open_source(source, range.start, Some(range.length()))
+                        .await?,
+                ),
+                headers,
+            }));
+        }
+
+        debug!("Responding with {} ranges", ranges.len());
+        let multipart = MultipartByteranges::new(mime_type, size);
+        let chunk = |data: String| -> ContentStream {
+            Box::pin(stream::once(async move { Ok(Bytes::from(data)) }))
+        };
+
+        // Every part is opened up front, so a failure surfaces as an error response rather than a
+        // truncated body.
+        let mut parts = Vec::with_capacity(ranges.len() * 2 + 1);
+        for range in &ranges {
+            parts.push(chunk(multipart.part_header(range)));
+            parts.push(
+                self.open_source(source, range.start, Some(range.length()))
+                    .await?,
+            );
+        }
+        parts.push(chunk(multipart.closing()));
+
         headers.insert(
             header::CONTENT_TYPE,
-            HeaderValue::from_str(mime_type).unwrap(),
+            HeaderValue::from_str(&multipart.content_type()).unwrap(),
+        );
+        headers.insert(
+            header::CONTENT_LENGTH,
+            HeaderValue::from(multipart.content_length(&ranges)),
         );
 
-        site.response_headers.iter().for_each(|(key, value)| {
-            if let Ok(header_name) = HeaderName::from_str(key) {
-                headers.insert(header_name, HeaderValue::from_str(value).unwrap());
-            }
-        });
-
-        Ok(self.respond(Status::Ok {
-            body: content.bytes().to_vec(),
+        Ok(self.respond(Status::PartialContent {
+            body: body::concat(parts),
             headers,
         }))
     }
 
     /// Redirects to the specified target URL or path.
-    fn handle_redirect(&self, rule: RedirectRule) -> Result<Response<Full<Bytes>>, ServerError> {
-        debug!("Redirecting to: {}", rule.target());
-
-        let status = match (rule.is_temporary(), rule.is_replay()) {
-            // Temporary + replay
-            (true, true) => StatusCode::TEMPORARY_REDIRECT, // 307 Temporary Redirect
-            // Permanent + replay
-            (false, true) => StatusCode::PERMANENT_REDIRECT, // 308 Permanent Redirect
-            // Temporary + not replay
-            (true, false) => StatusCode::FOUND, // 302 Found
-            // Permanent + not replay
-            (false, false) => StatusCode::MOVED_PERMANENTLY, // 301 Moved Permanently
+    ///
+    /// Returns `None` when the rule would redirect the request to itself, which rules matching
+    /// with or without a trailing slash (like `/docs -> /docs/`) would otherwise do forever.
+    fn handle_redirect(
+        &self,
+        site: &Site,
+        rule: RedirectRule,
+        captures: &Captures,
+        request: &Parts,
+    ) -> Result<Option<Response<Body>>, ServerError> {
+        let status = StatusCode::from_u16(rule.status_code()).unwrap_or(StatusCode::FOUND);
+
+        // Gone has nothing to redirect to, it is answered like any other error
+        if status == StatusCode::GONE {
+            debug!("Responding with 410 Gone for path: {}", request.uri.path());
+            return Ok(Some(self.respond_with_error(
+                site,
+                status,
+                HeaderMap::new(),
+                request,
+            )));
+        }
+
+        // Captures are only filled into the literal parts of the target, so values from the
+        // request can never end up being interpreted as template variables
+        let context = Self::template_context(request);
+        let source = rule.target();
+        let target = match site.templates().parse(&source) {
+            Ok(template) => template.render_with(&context, |literal| captures.substitute(literal)),
+            Err(_) => captures.substitute(&source),
         };
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::LOCATION,
-            HeaderValue::from_str(&rule.target()).map_err(|e| ServerError::InvalidHeaderValue {
-                header: "Location".to_string(),
-                value: rule.target().to_string(),
-                message: e.to_string(),
-            })?,
-        );
+        // A path target must stay on this host, even if a variable like `{request.path}` turned
+        // it into `//evil.com` or `/\evil.com`
+        let target = if target.starts_with(['/', '\\']) && !source.starts_with("//") {
+            format!("/{}", target.trim_start_matches(['/', '\\']))
+        } else {
+            target
+        };
 
-        debug!("Redirecting to: {}, status: {}", rule.target(), status);
-        Ok(self.respond(Status::Redirect {
-            target: rule.target().to_string(),
-        }))
+        if target == request.uri.path() {
+            debug!("Skipping redirect of {target} to itself");
+            return Ok(None);
+        }
+
+        let mut target = rule.query_handling().apply(&target, request.uri.query());
+        if rule.is_absolute() && target.starts_with('/') && !target.starts_with("//") {
+            target = format!("{}://{}{target}", context.scheme, context.host);
+        }
+
+        // Make sure the target is a valid header value before responding with it
+        HeaderValue::from_str(&target).map_err(|e| ServerError::InvalidHeaderValue {
+            header: "Location".to_string(),
+            value: target.clone(),
+            message: e.to_string(),
+        })?;
+
+        debug!("Redirecting to: {target}, status: {status}");
+        Ok(Some(self.respond(Status::Redirect {
+            code: status,
+            target,
+        })))
     }
 }
 
-impl HyperService<Request<IncomingBody>> for Service {
-    type Response = Response<Full<Bytes>>;
+impl<B> HyperService<Request<B>> for Service
+where
+    B: Send + 'static,
+{
+    type Response = Response<Body>;
     type Error = ServerError;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
-    fn call(&self, req: Request<IncomingBody>) -> Self::Future {
+    fn call(&self, req: Request<B>) -> Self::Future {
         let service = self.clone();
         Box::pin(async move {
             match service.handle_request(req).await {
@@ -26,10 +26,10 @@ pub fn validate_site_name(site_name: &str) -> Result<(), ServerError> {
 /// Helper to get a safe display path for error messages (doesn't leak full absolute paths)
 fn safe_display_path(full_path: &Path) -> String {
     // Try relative to current directory
-    if let Ok(cwd) = std::env::current_dir() {
-        if let Ok(relative) = full_path.strip_prefix(&cwd) {
-            return relative.display().to_string();
-        }
+    if let Ok(cwd) = std::env::current_dir()
+        && let Ok(relative) = full_path.strip_prefix(&cwd)
+    {
+        return relative.display().to_string();
     }
     // Fall back to filename or full path
     full_path
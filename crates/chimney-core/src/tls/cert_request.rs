@@ -230,10 +230,10 @@ pub async fn request_certificate(options: CertRequestOptions) -> Result<CertRequ
             event_handle.abort();
 
             // Check if there was an error
-            if let Ok(error_guard) = cert_error.lock() {
-                if let Some(err) = error_guard.as_ref() {
-                    return Err(ServerError::AcmeCertificateIssuanceFailed(err.clone()));
-                }
+            if let Ok(error_guard) = cert_error.lock()
+                && let Some(err) = error_guard.as_ref()
+            {
+                return Err(ServerError::AcmeCertificateIssuanceFailed(err.clone()));
             }
 
             return Err(ServerError::AcmeCertificateIssuanceFailed(
@@ -247,21 +247,22 @@ pub async fn request_certificate(options: CertRequestOptions) -> Result<CertRequ
             let cert_meta = std::fs::metadata(&cert_path).ok();
             let key_meta = std::fs::metadata(&key_path).ok();
 
-            if let (Some(cert_m), Some(key_m)) = (cert_meta, key_meta) {
-                if cert_m.len() > 0 && key_m.len() > 0 {
-                    info!("Certificate files found and verified");
-                    break;
-                }
+            if let (Some(cert_m), Some(key_m)) = (cert_meta, key_meta)
+                && cert_m.len() > 0
+                && key_m.len() > 0
+            {
+                info!("Certificate files found and verified");
+                break;
             }
         }
 
         // Check for errors
-        if let Ok(error_guard) = cert_error.lock() {
-            if let Some(err) = error_guard.as_ref() {
-                accept_handle.abort();
-                event_handle.abort();
-                return Err(ServerError::AcmeCertificateIssuanceFailed(err.clone()));
-            }
+        if let Ok(error_guard) = cert_error.lock()
+            && let Some(err) = error_guard.as_ref()
+        {
+            accept_handle.abort();
+            event_handle.abort();
+            return Err(ServerError::AcmeCertificateIssuanceFailed(err.clone()));
         }
 
         // Wait a bit before checking again
@@ -0,0 +1,234 @@
+use chimney::{
+    config::{AccessRules, Autoindex, Site, SiteBuilder, TrailingSlash},
+    error::ChimneyError,
+};
+use hyper::StatusCode;
+
+mod common;
+
+const FILES: [(&str, &str); 13] = [
+    ("index.html", "<h1>Home</h1>"),
+    ("chimney.toml", "domain_names = [\"example.com\"]"),
+    ("_redirects", "/old /new"),
+    ("_headers", "/*\n  X-Frame-Options: DENY"),
+    (".env", "SECRET=1"),
+    (".git/config", "[core]"),
+    (".git/index.html", "<h1>Git</h1>"),
+    (".chimney/certs/example.com.pem", "certificate"),
+    (".well-known/security.txt", "Contact: security@example.com"),
+    ("drafts/index.html", "<h1>Draft</h1>"),
+    ("public/app.js", "console.log('app')"),
+    ("public/app.js.map", "{}"),
+    ("public/.nojekyll", ""),
+];
+
+#[test]
+fn test_default_denied_paths() {
+    let access = AccessRules::default();
+
+    for path in [
+        "/.env",
+        ".env",
+        "/.git",
+        "/.git/config",
+        "/assets/.DS_Store",
+        "/.chimney/certs/example.com.pem",
+        "/chimney.toml",
+        "/blog/chimney.toml",
+        "/_redirects",
+        "/_headers",
+        // Case-insensitive filesystems serve these just the same
+        "/Chimney.TOML",
+        "/blog/CHIMNEY.toml",
+        "/_Redirects",
+        "/.ENV",
+    ] {
+        assert!(access.is_denied(path), "{path} should be denied");
+    }
+
+    for path in [
+        "/",
+        "/index.html",
+        "/assets/app.js",
+        "/file.env",
+        "/blog/_redirects",
+        "/.well-known/security.txt",
+    ] {
+        assert!(!access.is_denied(path), "{path} should be allowed");
+    }
+}
+
+#[test]
+fn test_deny_and_allow() {
+    let access = AccessRules::default()
+        .deny("/drafts/**")
+        .deny("*.map")
+        .allow("/drafts/published/**")
+        .allow("/**/.nojekyll");
+
+    assert!(access.is_denied("/drafts/post.html"));
+    assert!(access.is_denied("/Drafts/post.html"));
+    assert!(access.is_denied("/assets/app.js.map"));
+    assert!(!access.is_denied("/drafts/published/post.html"));
+    assert!(!access.is_denied("/.nojekyll"));
+    assert!(access.is_denied("/.env"));
+    assert!(!access.is_denied("/.well-known/security.txt"));
+
+    // Replacing the allow list also drops `.well-known` from it
+    let access = AccessRules {
+        allow: Vec::new(),
+        ..Default::default()
+    };
+    assert!(access.is_denied("/.well-known/security.txt"));
+}
+
+#[test]
+fn test_parse_access_rules() {
+    let parse = |access: &str| {
+        let input = format!(
+            r#"
+            domain_names = ["example.com"]
+            {access}
+            "#
+        );
+        Site::from_string("example".into(), &input)
+    };
+
+    let site = parse(
+        r#"
+        [access]
+        deny = ["/drafts/**"]
+        status = 403
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        site.access,
+        AccessRules::default().deny("/drafts/**").with_status(403)
+    );
+
+    let site = parse("").unwrap();
+    assert_eq!(site.access, AccessRules::default());
+
+    match parse("access = { status = 401 }").unwrap_err() {
+        ChimneyError::ConfigError { field, .. } => assert_eq!(field, "sites.example.access.status"),
+        error => panic!("expected a config error, got {error:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_denies_dotfiles_and_config_files() {
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site"));
+
+    for uri in [
+        "/chimney.toml",
+        "/_redirects",
+        "/_headers",
+        "/.env",
+        "/%2eenv",
+        "/public/../.env",
+        "/.git/config",
+        "/.git/",
+        "/.chimney/certs/example.com.pem",
+        "/public/.nojekyll",
+    ] {
+        assert_eq!(
+            common::get(&service, uri).await.0,
+            StatusCode::NOT_FOUND,
+            "{uri}"
+        );
+    }
+
+    assert_eq!(
+        common::get(&service, "/.well-known/security.txt").await,
+        (StatusCode::OK, "Contact: security@example.com".into())
+    );
+    assert_eq!(
+        common::get(&service, "/public/app.js.map").await.0,
+        StatusCode::OK
+    );
+    assert_eq!(common::get(&service, "/").await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_site_rules_and_status() {
+    let access = AccessRules::default()
+        .deny("/drafts/**")
+        .deny("*.map")
+        .allow("/**/.nojekyll")
+        .with_status(403);
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site").access(access));
+
+    assert_eq!(
+        common::get(&service, "/drafts/").await.0,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        common::get(&service, "/drafts/index.html").await.0,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        common::get(&service, "/public/app.js.map").await.0,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        common::get(&service, "/.env").await.0,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        common::get(&service, "/public/.nojekyll").await.0,
+        StatusCode::OK
+    );
+    assert_eq!(
+        common::get(&service, "/public/app.js").await.0,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_denied_index_file() {
+    // The route itself is allowed, but the file it resolves to is not
+    let access = AccessRules::default().deny("/drafts/index.html");
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site").access(access));
+
+    assert_eq!(
+        common::get(&service, "/drafts/").await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_rewrites_cannot_reach_denied_files() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .rewrite("/config", "/chimney.toml")
+            .fallback_file("index.html"),
+    );
+
+    // Rewrites can't be used to reach denied files, and the fallback doesn't apply either
+    let (status, body) = common::get(&service, "/config").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(!body.contains("domain_names"), "{body}");
+}
+
+#[tokio::test]
+async fn test_denied_directories_are_not_revealed() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .autoindex(Autoindex::enabled().with_hidden(true))
+            .trailing_slash(TrailingSlash::Always),
+    );
+
+    // No redirect to `/.git/`, which would reveal the directory exists
+    assert_eq!(
+        common::get(&service, "/.git").await.0,
+        StatusCode::NOT_FOUND
+    );
+
+    let (status, body) = common::get(&service, "/public/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("app.js"), "{body}");
+    assert!(!body.contains(".nojekyll"), "{body}");
+}
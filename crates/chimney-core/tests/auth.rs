@@ -0,0 +1,422 @@
+use argon2::{Argon2, PasswordHasher, password_hash::SaltString};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chimney::{
+    config::{Auth, Site, SiteBuilder},
+    error::ChimneyError,
+    filesystem::local::LocalFS,
+    server::{
+        auth::{
+            CredentialCache, challenge, parse_basic_credentials, parse_htpasswd, verify_password,
+        },
+        service::Service,
+    },
+};
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+fn bcrypt_hash(password: &str) -> String {
+    bcrypt::hash(password, 4).unwrap()
+}
+
+fn argon2_hash(password: &str) -> String {
+    let salt = SaltString::encode_b64(b"chimney-test-salt").unwrap();
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+fn basic(user: &str, password: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{user}:{password}")))
+}
+
+fn mock_service(auth: Auth) -> (tempfile::TempDir, Service) {
+    let files = [
+        ("index.html", "<h1>Home</h1>".to_string()),
+        ("internal/report.html", "<h1>Report</h1>".to_string()),
+        (
+            "users.htpasswd",
+            format!("# Staging users\nbob:{}\n", bcrypt_hash("hunter2")),
+        ),
+    ];
+
+    let builder = SiteBuilder::new("site")
+        .auth(auth)
+        .redirect("/internal/old", "/internal/report.html");
+
+    // The htpasswd file is loaded along with the configuration
+    common::serve(files, builder)
+}
+
+async fn get(service: &Service, uri: &str, authorization: Option<&str>) -> (StatusCode, String) {
+    let request = match authorization {
+        Some(authorization) => {
+            common::request_with(Method::GET, uri, &[(header::AUTHORIZATION, authorization)])
+        }
+        None => common::request(uri),
+    };
+
+    let response = service.call(request).await.unwrap();
+    let status = response.status();
+    if status == StatusCode::UNAUTHORIZED {
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            r#"Basic realm="Internal", charset="UTF-8""#
+        );
+    }
+
+    (status, common::body_text(response).await)
+}
+
+#[test]
+fn test_parse_basic_credentials() {
+    assert_eq!(
+        parse_basic_credentials(&basic("alice", "pass:word")),
+        Some(("alice".into(), "pass:word".into()))
+    );
+    assert_eq!(
+        parse_basic_credentials("basic YWxpY2U6c2VjcmV0"),
+        Some(("alice".into(), "secret".into()))
+    );
+    assert_eq!(parse_basic_credentials("Bearer YWxpY2U6c2VjcmV0"), None);
+    assert_eq!(parse_basic_credentials("Basic not-base64!"), None);
+    assert_eq!(parse_basic_credentials("Basic YWxpY2U="), None);
+}
+
+#[test]
+fn test_verify_password() {
+    let hash = bcrypt_hash("secret");
+    assert!(verify_password("secret", &hash));
+    assert!(!verify_password("wrong", &hash));
+
+    // `htpasswd -B` writes `$2y$` hashes
+    let hash = hash.replacen("$2b$", "$2y$", 1);
+    assert!(verify_password("secret", &hash));
+
+    let hash = argon2_hash("secret");
+    assert!(verify_password("secret", &hash));
+    assert!(!verify_password("wrong", &hash));
+
+    assert!(!verify_password("secret", "secret"));
+    assert!(!verify_password("secret", "$apr1$salt$hash"));
+}
+
+#[test]
+fn test_parse_htpasswd() {
+    let users = parse_htpasswd("# comment\n\nalice:$2y$05$abc\nbob:$argon2id$xyz\nbroken\n");
+    assert_eq!(users.len(), 2);
+    assert_eq!(users["alice"], "$2y$05$abc");
+    assert_eq!(users["bob"], "$argon2id$xyz");
+}
+
+#[test]
+fn test_challenge() {
+    assert_eq!(
+        challenge(r#"Say "hi""#),
+        r#"Basic realm="Say \"hi\"", charset="UTF-8""#
+    );
+}
+
+#[test]
+fn test_parse_auth() {
+    let parse = |auth: &str| {
+        let input = format!(
+            r#"
+            domain_names = ["example.com"]
+
+            [auth]
+            {auth}
+            "#
+        );
+        Site::from_string("example".into(), &input)
+    };
+
+    let hash = bcrypt_hash("secret");
+    let site = parse(&format!(
+        r#"
+        realm = "Internal"
+        paths = ["/internal/**"]
+        htpasswd_file = ".htpasswd"
+        users = {{ alice = "{hash}" }}
+        "#
+    ))
+    .unwrap();
+    assert_eq!(
+        site.auth,
+        Auth::new("Internal")
+            .path("/internal/**")
+            .user("alice", hash)
+            .with_htpasswd_file(".htpasswd")
+    );
+
+    let errors = [
+        (
+            r#"users = { alice = "secret" }"#,
+            "sites.example.auth.users.alice",
+        ),
+        (r#"paths = ["/internal/**"]"#, "sites.example.auth"),
+        (
+            "realm = \"Line\\nbreak\"\nhtpasswd_file = \".htpasswd\"",
+            "sites.example.auth.realm",
+        ),
+        (
+            r#"htpasswd_file = "../users.htpasswd""#,
+            "sites.example.auth.htpasswd_file",
+        ),
+        (
+            r#"htpasswd_file = "/etc/htpasswd""#,
+            "sites.example.auth.htpasswd_file",
+        ),
+    ];
+    for (auth, expected) in errors {
+        match parse(auth).unwrap_err() {
+            ChimneyError::ConfigError { field, .. } => assert_eq!(field, expected),
+            error => panic!("expected a config error, got {error:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_protects_paths() {
+    let (_dir, service) = mock_service(
+        Auth::new("Internal")
+            .path("/internal/**")
+            .user("alice", argon2_hash("secret"))
+            .with_htpasswd_file("users.htpasswd"),
+    );
+
+    assert_eq!(
+        get(&service, "/", None).await,
+        (StatusCode::OK, "<h1>Home</h1>".into())
+    );
+
+    for uri in [
+        "/internal/report.html",
+        "/internal/",
+        "/internal",
+        "/internal/missing.html",
+        "/INTERNAL/report.html",
+        "/Internal/",
+        "/%69nternal/report.html",
+        "/public/../internal/report.html",
+        "/internal/old",
+    ] {
+        assert_eq!(
+            get(&service, uri, None).await.0,
+            StatusCode::UNAUTHORIZED,
+            "{uri}"
+        );
+    }
+
+    let (status, body) = get(
+        &service,
+        "/internal/report.html",
+        Some(&basic("alice", "wrong")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(!body.contains("Report"));
+
+    assert_eq!(
+        get(
+            &service,
+            "/internal/report.html",
+            Some(&basic("eve", "secret"))
+        )
+        .await
+        .0,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Users come from both the configuration and the htpasswd file, and verified credentials
+    // keep working once they are cached
+    for credentials in [basic("alice", "secret"), basic("bob", "hunter2")]
+        .iter()
+        .cycle()
+        .take(4)
+    {
+        assert_eq!(
+            get(&service, "/internal/report.html", Some(credentials)).await,
+            (StatusCode::OK, "<h1>Report</h1>".into())
+        );
+    }
+    assert_eq!(
+        get(
+            &service,
+            "/internal/report.html",
+            Some(&basic("alice", "wrong"))
+        )
+        .await
+        .0,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // The htpasswd file itself is never served
+    for uri in [
+        "/users.htpasswd",
+        "/Users.HTPASSWD",
+        "/internal/../users.htpasswd",
+    ] {
+        assert_eq!(
+            get(&service, uri, Some(&basic("alice", "secret"))).await.0,
+            StatusCode::NOT_FOUND,
+            "{uri}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_protects_whole_site() {
+    let (_dir, service) = mock_service(Auth::new("Internal").with_htpasswd_file("users.htpasswd"));
+
+    assert_eq!(get(&service, "/", None).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        get(&service, "/", Some(&basic("bob", "hunter2"))).await,
+        (StatusCode::OK, "<h1>Home</h1>".into())
+    );
+
+    // Without any users, nothing is protected
+    let (_dir, service) = mock_service(Auth::new("Internal"));
+    assert_eq!(
+        get(&service, "/internal/report.html", None).await.0,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_protects_rewrite_targets() {
+    let files = [
+        ("index.html", "<h1>Home</h1>"),
+        ("internal/report.html", "<h1>Report</h1>"),
+        ("internal/index.html", "<h1>Internal</h1>"),
+    ];
+    let builder = SiteBuilder::new("site")
+        .auth(
+            Auth::new("Internal")
+                .path("/internal/**")
+                .user("alice", bcrypt_hash("secret")),
+        )
+        .rewrite("/docs/*", "/internal/:splat")
+        .fallback_file("internal/index.html");
+    let (_dir, service) = common::serve(files, builder);
+
+    for uri in ["/docs/report.html", "/docs/", "/missing.html"] {
+        assert_eq!(
+            get(&service, uri, None).await.0,
+            StatusCode::UNAUTHORIZED,
+            "{uri}"
+        );
+    }
+
+    let credentials = basic("alice", "secret");
+    assert_eq!(
+        get(&service, "/docs/report.html", Some(&credentials)).await,
+        (StatusCode::OK, "<h1>Report</h1>".into())
+    );
+    assert_eq!(
+        get(&service, "/missing.html", Some(&credentials)).await,
+        (StatusCode::OK, "<h1>Internal</h1>".into())
+    );
+    assert_eq!(
+        get(&service, "/", None).await,
+        (StatusCode::OK, "<h1>Home</h1>".into())
+    );
+}
+
+#[tokio::test]
+async fn test_protects_fallback_outside_of_root() {
+    let files = [
+        ("public/index.html", "<h1>Home</h1>"),
+        ("offline.html", "<h1>Offline</h1>"),
+    ];
+    let builder = SiteBuilder::new("site")
+        .root("public")
+        .auth(
+            Auth::new("Internal")
+                .path("/offline.html")
+                .user("alice", bcrypt_hash("secret")),
+        )
+        .fallback_file("offline.html");
+    let (_dir, service) = common::serve(files, builder);
+
+    // The fallback isn't inside the root the patterns are relative to, so it needs credentials
+    assert_eq!(
+        get(&service, "/", None).await,
+        (StatusCode::OK, "<h1>Home</h1>".into())
+    );
+    assert_eq!(
+        get(&service, "/missing.html", None).await.0,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        get(&service, "/missing.html", Some(&basic("alice", "secret"))).await,
+        (StatusCode::OK, "<h1>Offline</h1>".into())
+    );
+}
+
+#[test]
+fn test_credential_cache() {
+    let cache = CredentialCache::new(2);
+    cache.insert("alice", "secret", "$2y$hash");
+    assert!(cache.contains("alice", "secret", "$2y$hash"));
+    assert!(!cache.contains("alice", "wrong", "$2y$hash"));
+    assert!(!cache.contains("alice", "secret", "$2y$changed"));
+    assert!(!cache.contains("alicesecret", "", "$2y$hash"));
+
+    // The oldest credentials are evicted first
+    cache.insert("bob", "hunter2", "$2y$hash");
+    cache.insert("carol", "letmein", "$2y$hash");
+    assert_eq!(cache.len(), 2);
+    assert!(!cache.contains("alice", "secret", "$2y$hash"));
+    assert!(cache.contains("carol", "letmein", "$2y$hash"));
+}
+
+#[test]
+fn test_load_htpasswd_file() {
+    let dir = common::site_directory([(
+        "users.htpasswd",
+        format!("bob:{}\n", bcrypt_hash("hunter2")),
+    )]);
+    let directory = dir.path().join("site");
+    let fs = LocalFS::new(dir.path().to_path_buf()).unwrap();
+
+    let mut site = SiteBuilder::new("site")
+        .auth(Auth::new("Internal").with_htpasswd_file("users.htpasswd"))
+        .build();
+    site.load_htpasswd_file(&fs, &directory).unwrap();
+    assert!(site.auth.password_hash("bob").is_some());
+    assert!(site.auth.password_hash("eve").is_none());
+
+    // The file is only read again when the configuration is reloaded
+    std::fs::write(directory.join("users.htpasswd"), "").unwrap();
+    assert!(site.auth.password_hash("bob").is_some());
+    site.load_htpasswd_file(&fs, &directory).unwrap();
+    assert!(site.auth.password_hash("bob").is_none());
+
+    let mut site = SiteBuilder::new("site")
+        .auth(Auth::new("Internal").with_htpasswd_file("missing.htpasswd"))
+        .build();
+    match site.load_htpasswd_file(&fs, &directory).unwrap_err() {
+        ChimneyError::ConfigError { field, .. } => {
+            assert_eq!(field, "sites.site.auth.htpasswd_file")
+        }
+        error => panic!("expected a config error, got {error:?}"),
+    }
+
+    // Not even through a symlink
+    #[cfg(unix)]
+    {
+        std::fs::write(dir.path().join("outside.htpasswd"), "").unwrap();
+        std::os::unix::fs::symlink(
+            dir.path().join("outside.htpasswd"),
+            directory.join("linked.htpasswd"),
+        )
+        .unwrap();
+
+        let mut site = SiteBuilder::new("site")
+            .auth(Auth::new("Internal").with_htpasswd_file("linked.htpasswd"))
+            .build();
+        assert!(site.load_htpasswd_file(&fs, &directory).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use chimney::{
+    config::{Autoindex, Format, SiteBuilder, toml::Toml},
+    filesystem::{AbstractFile, FileType},
+    server::{
+        autoindex::{self, Listing, SortKey, SortOrder, Sorting},
+        service::Service,
+    },
+};
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+fn file(name: &str, size: u64, modified: u64) -> AbstractFile {
+    let mut file = AbstractFile::new(PathBuf::from("/srv").join(name), FileType::File);
+    file.size = Some(size);
+    file.modified_at = Some(UNIX_EPOCH + Duration::from_secs(modified));
+    file
+}
+
+fn directory(name: &str) -> AbstractFile {
+    AbstractFile::new(PathBuf::from("/srv").join(name), FileType::Directory)
+}
+
+fn names(listing: &Listing) -> Vec<&str> {
+    listing
+        .entries
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect()
+}
+
+fn mock_service(autoindex: Autoindex) -> (tempfile::TempDir, Service) {
+    let files = [
+        ("artifacts/build.tar.gz", "binary"),
+        ("artifacts/notes & <readme>.txt", "notes"),
+        ("artifacts/.secret", "hidden"),
+        ("private/data.txt", "data"),
+        ("docs/index.html", "<h1>Docs</h1>"),
+    ];
+
+    let (dir, service) = common::serve(files, SiteBuilder::new("site").autoindex(autoindex));
+    std::fs::create_dir_all(dir.path().join("site/artifacts/v1")).unwrap();
+    (dir, service)
+}
+
+#[test]
+fn test_autoindex_is_enabled_for() {
+    let autoindex = Autoindex::default().with_path("/artifacts");
+    assert!(autoindex.is_enabled_for("/artifacts"));
+    assert!(autoindex.is_enabled_for("/artifacts/"));
+    assert!(autoindex.is_enabled_for("artifacts/v1"));
+    assert!(!autoindex.is_enabled_for("/artifacts-old"));
+    assert!(!autoindex.is_enabled_for("/"));
+
+    assert!(!Autoindex::default().is_enabled_for("/"));
+    assert!(Autoindex::enabled().is_enabled_for("/anything"));
+}
+
+#[test]
+fn test_parse_autoindex_config() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+
+    [sites.example.autoindex]
+    paths = ["/artifacts"]
+    show_hidden = true
+    "#;
+
+    let config = Toml::new(input).parse().unwrap();
+    let autoindex = &config.sites.get("example").unwrap().autoindex;
+    assert!(!autoindex.enabled);
+    assert_eq!(autoindex.paths, vec!["/artifacts"]);
+    assert!(autoindex.show_hidden);
+}
+
+#[test]
+fn test_sorting_from_query() {
+    assert_eq!(Sorting::from_query(None), Sorting::default());
+    assert_eq!(
+        Sorting::from_query(Some("sort=size&order=desc")),
+        Sorting {
+            key: SortKey::Size,
+            order: SortOrder::Descending
+        }
+    );
+    assert_eq!(
+        Sorting::from_query(Some("sort=bogus&order=sideways")),
+        Sorting::default()
+    );
+}
+
+#[test]
+fn test_listing_sorts_directories_first() {
+    let files = vec![
+        file("b.txt", 10, 3),
+        file("a.txt", 30, 1),
+        directory("zeta"),
+        file(".hidden", 1, 1),
+        file("C.txt", 20, 2),
+    ];
+
+    let mut listing = Listing::new("/files", files.clone(), false);
+    assert_eq!(listing.path, "/files/");
+
+    listing.sort(Sorting::default());
+    assert_eq!(names(&listing), vec!["zeta", "a.txt", "b.txt", "C.txt"]);
+
+    listing.sort(Sorting {
+        key: SortKey::Size,
+        order: SortOrder::Descending,
+    });
+    assert_eq!(names(&listing), vec!["zeta", "a.txt", "C.txt", "b.txt"]);
+
+    listing.sort(Sorting {
+        key: SortKey::Modified,
+        order: SortOrder::Ascending,
+    });
+    assert_eq!(names(&listing), vec!["zeta", "a.txt", "C.txt", "b.txt"]);
+
+    let listing = Listing::new("/files", files, true);
+    assert!(names(&listing).contains(&".hidden"));
+}
+
+#[test]
+fn test_prefers_json() {
+    assert!(!autoindex::prefers_json(None));
+    assert!(!autoindex::prefers_json(Some("*/*")));
+    assert!(!autoindex::prefers_json(Some(
+        "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+    )));
+    assert!(autoindex::prefers_json(Some("application/json")));
+    assert!(autoindex::prefers_json(Some(
+        "text/html;q=0.5, application/json"
+    )));
+    assert!(!autoindex::prefers_json(Some("application/json;q=0")));
+}
+
+#[tokio::test]
+async fn test_service_renders_html_listing() {
+    let (_dir, service) = mock_service(Autoindex::default().with_path("/artifacts"));
+    let response = service.call(common::request("/artifacts")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/html; charset=utf-8"
+    );
+
+    let html = common::body_text(response).await;
+    assert!(html.contains("<title>Index of /artifacts/</title>"));
+    assert!(html.contains(r#"<a href="/artifacts/v1/">v1/</a>"#));
+    assert!(html.contains(r#"<a href="/artifacts/build.tar.gz">build.tar.gz</a>"#));
+    assert!(html.contains(
+        r#"<a href="/artifacts/notes%20&amp;%20%3Creadme%3E.txt">notes &amp; &lt;readme&gt;.txt</a>"#
+    ));
+    assert!(html.contains(r#"<a href="../">../</a>"#));
+    assert!(!html.contains(".secret"));
+}
+
+#[tokio::test]
+async fn test_service_renders_json_listing() {
+    let (_dir, service) = mock_service(Autoindex::enabled());
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/artifacts/?sort=size&order=desc",
+            &[(header::ACCEPT, "application/json")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/json"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&common::body_text(response).await).unwrap();
+    assert_eq!(json["path"], "/artifacts/");
+
+    let entries = json["entries"].as_array().unwrap();
+    let names: Vec<_> = entries
+        .iter()
+        .map(|entry| entry["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["v1", "build.tar.gz", "notes & <readme>.txt"]);
+    assert_eq!(entries[0]["directory"], true);
+    assert_eq!(entries[1]["size"], 6);
+    assert!(entries[1]["modified"].is_string());
+}
+
+#[tokio::test]
+async fn test_service_autoindex_is_opt_in() {
+    let (_dir, service) = mock_service(Autoindex::default().with_path("/artifacts"));
+
+    let response = service.call(common::request("/private/")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let (_dir, service) = mock_service(Autoindex::default());
+    let response = service.call(common::request("/artifacts/")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_service_autoindex_paths_are_normalized() {
+    let (_dir, service) = mock_service(Autoindex::default().with_path("/artifacts"));
+
+    for path in ["/artifacts/../private/", "/artifacts/%2e%2e/private/"] {
+        let response = service.call(common::request(path)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+    }
+
+    let response = service
+        .call(common::request("/private/../artifacts/"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_service_prefers_index_file_over_listing() {
+    let (_dir, service) = mock_service(Autoindex::enabled());
+    let response = service.call(common::request("/docs/")).await.unwrap();
+
+    assert_eq!(common::body_text(response).await, "<h1>Docs</h1>");
+}
+
+#[tokio::test]
+async fn test_service_head_listing() {
+    let (_dir, service) = mock_service(Autoindex::enabled());
+    let response = service
+        .call(common::request_with(Method::HEAD, "/artifacts/", &[]))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().contains_key(header::CONTENT_LENGTH));
+    assert!(common::body_text(response).await.is_empty());
+}
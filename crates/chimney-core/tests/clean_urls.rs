@@ -0,0 +1,207 @@
+use chimney::{
+    config::{CleanUrls, Site, SiteBuilder},
+    server::service::Service,
+};
+use hyper::{StatusCode, header, service::Service as _};
+
+mod common;
+
+fn mock_service(builder: SiteBuilder) -> (tempfile::TempDir, Service) {
+    let files = [
+        ("index.html", "<h1>Home</h1>"),
+        ("about.html", "<h1>About</h1>"),
+        ("docs/index.html", "<h1>Docs</h1>"),
+        ("docs/intro.html", "<h1>Intro</h1>"),
+        ("empty.html", "<h1>Empty</h1>"),
+        ("pages/pricing.html", "<h1>Pricing</h1>"),
+        ("spa.html", "<h1>App</h1>"),
+    ];
+
+    let (dir, service) = common::serve(files, builder);
+    std::fs::create_dir_all(dir.path().join("site/empty")).unwrap();
+    (dir, service)
+}
+
+async fn location(service: &Service, uri: &str) -> Option<String> {
+    let response = service.call(common::request(uri)).await.unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY,
+        "uri: {uri}"
+    );
+    response
+        .headers()
+        .get(header::LOCATION)
+        .map(|value| value.to_str().unwrap().to_string())
+}
+
+#[test]
+fn test_canonical_path() {
+    let clean_urls = CleanUrls::enabled().with_redirect(true);
+    let canonical = |path: &str| clean_urls.canonical_path(path, &["index.html"]);
+
+    assert_eq!(canonical("/about.html"), Some("/about".into()));
+    assert_eq!(canonical("/docs/intro.html"), Some("/docs/intro".into()));
+    assert_eq!(canonical("/docs/index.html"), Some("/docs".into()));
+    assert_eq!(canonical("/index.html"), Some("/".into()));
+    assert_eq!(canonical("/about"), None);
+    assert_eq!(canonical("/.html"), None);
+    assert_eq!(canonical("/style.css"), None);
+
+    // Any of the site's index files is redirected to its directory
+    let index_files = ["index.html", "index.htm"];
+    assert_eq!(
+        clean_urls.canonical_path("/docs/index.htm", &index_files),
+        Some("/docs".into())
+    );
+    assert_eq!(
+        clean_urls.canonical_path("/docs/xindex.htm", &index_files),
+        None
+    );
+
+    // Redirects never leave the site
+    assert_eq!(canonical("//example.org.html"), Some("/example.org".into()));
+    assert_eq!(
+        canonical("//example.org/index.html"),
+        Some("/example.org".into())
+    );
+
+    // Nothing is redirected unless asked for
+    assert_eq!(
+        CleanUrls::enabled().canonical_path("/about.html", &["index.html"]),
+        None
+    );
+}
+
+#[test]
+fn test_parse_clean_urls() {
+    let input = r#"
+    domain_names = ["example.com"]
+    clean_urls = { enabled = true, redirect = true }
+    "#;
+
+    let site = Site::from_string("example".into(), input).unwrap();
+    assert_eq!(site.clean_urls, CleanUrls::enabled().with_redirect(true));
+
+    let site = Site::from_string("example".into(), r#"domain_names = ["example.com"]"#).unwrap();
+    assert_eq!(site.clean_urls, CleanUrls::default());
+}
+
+#[tokio::test]
+async fn test_disabled_by_default() {
+    let (_dir, service) = mock_service(SiteBuilder::new("site"));
+
+    assert_eq!(
+        common::get(&service, "/about").await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(common::get(&service, "/about.html").await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_resolves_html_files() {
+    let (_dir, service) = mock_service(SiteBuilder::new("site").clean_urls(CleanUrls::enabled()));
+
+    assert_eq!(
+        common::get(&service, "/about").await,
+        (StatusCode::OK, "<h1>About</h1>".into())
+    );
+    assert_eq!(
+        common::get(&service, "/docs/intro").await,
+        (StatusCode::OK, "<h1>Intro</h1>".into())
+    );
+
+    // Directory indexes still take precedence, a directory without one falls back to the file
+    assert_eq!(
+        common::get(&service, "/docs").await,
+        (StatusCode::OK, "<h1>Docs</h1>".into())
+    );
+    assert_eq!(
+        common::get(&service, "/empty").await,
+        (StatusCode::OK, "<h1>Empty</h1>".into())
+    );
+
+    // Without redirects, the original paths keep working
+    assert_eq!(common::get(&service, "/about.html").await.0, StatusCode::OK);
+    assert_eq!(
+        common::get(&service, "/missing").await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        common::get(&service, "/about.html.html").await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_redirects_to_clean_urls() {
+    let (_dir, service) =
+        mock_service(SiteBuilder::new("site").clean_urls(CleanUrls::enabled().with_redirect(true)));
+
+    assert_eq!(
+        location(&service, "/about.html").await,
+        Some("/about".into())
+    );
+    assert_eq!(
+        location(&service, "/about.html?ref=nav").await,
+        Some("/about?ref=nav".into())
+    );
+    assert_eq!(
+        location(&service, "/docs/index.html").await,
+        Some("/docs".into())
+    );
+    assert_eq!(location(&service, "/index.html").await, Some("/".into()));
+
+    // `/empty` is a directory without an index file, so it serves `empty.html` as well
+    assert_eq!(
+        location(&service, "/empty.html").await,
+        Some("/empty".into())
+    );
+
+    // Files that don't exist are not redirected
+    assert_eq!(
+        common::get(&service, "/missing.html").await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_only_redirects_to_the_same_file() {
+    let (dir, service) =
+        mock_service(SiteBuilder::new("site").clean_urls(CleanUrls::enabled().with_redirect(true)));
+
+    // `/docs` serves the directory's index, so `docs.html` has to stay reachable as is
+    std::fs::write(dir.path().join("site/docs.html"), "<h1>Docs page</h1>").unwrap();
+    assert_eq!(
+        common::get(&service, "/docs.html").await,
+        (StatusCode::OK, "<h1>Docs page</h1>".into())
+    );
+}
+
+#[tokio::test]
+async fn test_clean_urls_with_rewrites_and_fallback() {
+    let (_dir, service) = mock_service(
+        SiteBuilder::new("site")
+            .clean_urls(CleanUrls::enabled().with_redirect(true))
+            .rewrite("/pricing", "/pages/pricing")
+            .rewrite("/legacy", "/about.html")
+            .fallback_file("spa.html"),
+    );
+
+    // Rewrite targets are resolved with clean URLs too
+    assert_eq!(
+        common::get(&service, "/pricing").await,
+        (StatusCode::OK, "<h1>Pricing</h1>".into())
+    );
+
+    // ...but never redirected
+    assert_eq!(
+        common::get(&service, "/legacy").await,
+        (StatusCode::OK, "<h1>About</h1>".into())
+    );
+
+    assert_eq!(
+        common::get(&service, "/settings/profile").await,
+        (StatusCode::OK, "<h1>App</h1>".into())
+    );
+}
@@ -0,0 +1,114 @@
+//! Helpers shared by the integration tests that serve requests for a test site
+//!
+//! Every test crate only uses some of them.
+#![allow(dead_code)]
+
+use std::{path::Path, sync::Arc};
+
+use chimney::{
+    config::{Config, SiteBuilder},
+    filesystem::local::LocalFS,
+    server::{body::Body, service::Service},
+};
+use http_body_util::BodyExt;
+use hyper::{Method, Request, Response, StatusCode, header, service::Service as _};
+use tempfile::TempDir;
+
+/// The domain test sites are served on
+pub const DOMAIN: &str = "example.com";
+
+/// Creates a sites directory with a single site, `site`, containing the given files
+///
+/// Paths are relative to the site's directory, their parent directories are created as needed.
+pub fn site_directory<P, C>(files: impl IntoIterator<Item = (P, C)>) -> TempDir
+where
+    P: AsRef<Path>,
+    C: AsRef<[u8]>,
+{
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("site");
+    std::fs::create_dir_all(&root).unwrap();
+
+    for (file, content) in files {
+        let path = root.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(path, content).unwrap();
+    }
+
+    dir
+}
+
+/// The configuration of a plain HTTP server for the sites in `sites_directory`, with the site
+/// built by `builder` served on [`DOMAIN`]
+pub fn config(sites_directory: &Path, builder: SiteBuilder) -> Config {
+    let mut config = Config::default();
+    config.https = None;
+    config.sites_directory = sites_directory.to_string_lossy().to_string();
+    config.sites.add(builder.domain(DOMAIN).build()).unwrap();
+
+    config
+}
+
+/// Creates a service for the site built by `builder`, served from the sites directory
+pub fn service(dir: &TempDir, builder: SiteBuilder) -> Service {
+    service_with_config(dir, config(dir.path(), builder))
+}
+
+/// Creates a service with the given configuration, serving files from the sites directory
+pub fn service_with_config(dir: &TempDir, config: Config) -> Service {
+    let fs = LocalFS::new(dir.path().to_path_buf()).unwrap();
+    Service::new(Arc::new(fs), config.into()).unwrap()
+}
+
+/// Creates a service for the site built by `builder`, serving the given files
+///
+/// The sites directory is returned along with the service, as it is removed once dropped.
+pub fn serve<P, C>(
+    files: impl IntoIterator<Item = (P, C)>,
+    builder: SiteBuilder,
+) -> (TempDir, Service)
+where
+    P: AsRef<Path>,
+    C: AsRef<[u8]>,
+{
+    let dir = site_directory(files);
+    let service = service(&dir, builder);
+    (dir, service)
+}
+
+/// A GET request for `uri` on [`DOMAIN`]
+pub fn request(uri: &str) -> Request<()> {
+    request_with(Method::GET, uri, &[])
+}
+
+/// A request for `uri` on [`DOMAIN`] with the given method and extra headers
+pub fn request_with(
+    method: Method,
+    uri: &str,
+    headers: &[(header::HeaderName, &str)],
+) -> Request<()> {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::HOST, DOMAIN);
+    for (name, value) in headers {
+        builder = builder.header(name, *value);
+    }
+
+    builder.body(()).unwrap()
+}
+
+/// Sends a GET request for `uri` and returns the status and the body of the response
+pub async fn get(service: &Service, uri: &str) -> (StatusCode, String) {
+    let response = service.call(request(uri)).await.unwrap();
+    let status = response.status();
+    (status, body_text(response).await)
+}
+
+/// Reads the body of a response as text
+pub async fn body_text(response: Response<Body>) -> String {
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8_lossy(&body).to_string()
+}
@@ -0,0 +1,361 @@
+use std::io::Read;
+use std::time::{Duration, UNIX_EPOCH};
+
+use chimney::{
+    config::{Compression, CompressionCache, Encoding, Format, SiteBuilder, toml::Toml},
+    server::compression::{self, CacheKey, CompressedCache},
+};
+use http_body_util::BodyExt;
+use hyper::{Method, StatusCode, body::Bytes, header, service::Service as _};
+
+mod common;
+
+fn page() -> String {
+    "<p>Hello, world!</p>\n".repeat(200)
+}
+
+fn decompress(content: &[u8], encoding: Encoding) -> Vec<u8> {
+    let mut output = Vec::new();
+    match encoding {
+        Encoding::Gzip => {
+            flate2::read::GzDecoder::new(content)
+                .read_to_end(&mut output)
+                .unwrap();
+        }
+        Encoding::Brotli => {
+            brotli::Decompressor::new(content, 4096)
+                .read_to_end(&mut output)
+                .unwrap();
+        }
+        Encoding::Zstd => output = zstd::decode_all(content).unwrap(),
+    }
+    output
+}
+
+fn files() -> [(&'static str, String); 3] {
+    [
+        ("index.html", page()),
+        ("tiny.txt", "tiny".to_string()),
+        ("image.png", page()),
+    ]
+}
+
+#[test]
+fn test_is_compressible() {
+    assert!(compression::is_compressible("text/html"));
+    assert!(compression::is_compressible("text/css; charset=utf-8"));
+    assert!(compression::is_compressible("application/json"));
+    assert!(compression::is_compressible("application/ld+json"));
+    assert!(compression::is_compressible("image/svg+xml"));
+    assert!(compression::is_compressible("application/wasm"));
+
+    assert!(!compression::is_compressible("image/png"));
+    assert!(!compression::is_compressible("video/mp4"));
+    assert!(!compression::is_compressible("application/gzip"));
+    assert!(!compression::is_compressible("font/woff2"));
+}
+
+#[test]
+fn test_compress_round_trip() {
+    let content = page();
+    for encoding in [Encoding::Gzip, Encoding::Brotli, Encoding::Zstd] {
+        let compressed = compression::compress(content.as_bytes(), encoding).unwrap();
+        assert!(compressed.len() < content.len());
+        assert_eq!(decompress(&compressed, encoding), content.as_bytes());
+    }
+}
+
+#[test]
+fn test_parse_dynamic_compression_config() {
+    let input = r#"
+    [sites.memory]
+    domain_names = ["memory.example.com"]
+
+    [sites.memory.compression]
+    dynamic = true
+    encodings = ["gzip"]
+    min_size = 10
+    cache = "memory"
+
+    [sites.disk]
+    domain_names = ["disk.example.com"]
+
+    [sites.disk.compression]
+    cache = { disk = "/var/cache/chimney" }
+    "#;
+
+    let config = Toml::new(input).parse().unwrap();
+
+    let memory = &config.sites.get("memory").unwrap().compression;
+    assert!(memory.dynamic);
+    assert_eq!(memory.encodings, vec![Encoding::Gzip]);
+    assert_eq!(memory.min_size, 10);
+    assert_eq!(memory.max_size, Compression::default_max_size());
+    assert_eq!(memory.cache, CompressionCache::Memory);
+
+    let disk = &config.sites.get("disk").unwrap().compression;
+    assert!(!disk.dynamic);
+    assert_eq!(
+        disk.cache,
+        CompressionCache::Disk("/var/cache/chimney".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_memory_cache_evicts_oldest_entries() {
+    let cache = CompressedCache::new(10);
+    let modified = UNIX_EPOCH + Duration::from_secs(1);
+    let key = |name: &str| CacheKey::new(name.as_ref(), modified, 100, Encoding::Gzip);
+    let memory = CompressionCache::Memory;
+
+    cache.insert(&memory, key("a"), Bytes::from("12345")).await;
+    cache.insert(&memory, key("b"), Bytes::from("12345")).await;
+    assert_eq!(cache.memory_entries(), 2);
+
+    cache.insert(&memory, key("c"), Bytes::from("123")).await;
+    assert_eq!(cache.memory_entries(), 2);
+    assert!(cache.get(&memory, &key("a")).await.is_none());
+    assert_eq!(cache.get(&memory, &key("c")).await.unwrap(), "123");
+
+    // A changed file is a different key
+    let stale = CacheKey::new("c".as_ref(), modified, 101, Encoding::Gzip);
+    assert!(cache.get(&memory, &stale).await.is_none());
+
+    // Nothing is stored when caching is disabled
+    let none = CompressionCache::None;
+    cache.insert(&none, key("d"), Bytes::from("1")).await;
+    assert!(cache.get(&none, &key("d")).await.is_none());
+}
+
+#[tokio::test]
+async fn test_disk_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let directory = dir.path().join("cache");
+    let config = CompressionCache::Disk(directory.to_string_lossy().to_string());
+
+    let cache = CompressedCache::default();
+    let key = CacheKey::new("index.html".as_ref(), UNIX_EPOCH, 100, Encoding::Brotli);
+    assert!(cache.get(&config, &key).await.is_none());
+
+    cache
+        .insert(&config, key.clone(), Bytes::from("compressed"))
+        .await;
+    assert!(key.disk_path(&directory).exists());
+    assert_eq!(cache.get(&config, &key).await.unwrap(), "compressed");
+    assert_eq!(cache.memory_entries(), 0);
+
+    // Entries are named by a stable digest, so they survive restarts
+    assert_eq!(
+        key.disk_path(&directory),
+        directory.join("dd623cf169ac8bc3d9eed37226da51c8.br")
+    );
+}
+
+#[tokio::test]
+async fn test_disk_cache_evicts_oldest_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let directory = dir.path().join("cache");
+    let config = CompressionCache::Disk(directory.to_string_lossy().to_string());
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("unrelated.txt"), "1234567890").unwrap();
+
+    let cache = CompressedCache::default().with_disk_capacity(10);
+    let key = |name: &str| CacheKey::new(name.as_ref(), UNIX_EPOCH, 100, Encoding::Gzip);
+
+    cache.insert(&config, key("a"), Bytes::from("12345")).await;
+    let oldest = UNIX_EPOCH + Duration::from_secs(1);
+    std::fs::File::options()
+        .write(true)
+        .open(key("a").disk_path(&directory))
+        .unwrap()
+        .set_modified(oldest)
+        .unwrap();
+
+    cache.insert(&config, key("b"), Bytes::from("12345")).await;
+    cache.insert(&config, key("c"), Bytes::from("123")).await;
+
+    assert!(cache.get(&config, &key("a")).await.is_none());
+    assert_eq!(cache.get(&config, &key("b")).await.unwrap(), "12345");
+    assert_eq!(cache.get(&config, &key("c")).await.unwrap(), "123");
+
+    // Variants larger than the cache are never stored, other files are left alone
+    cache
+        .insert(&config, key("d"), Bytes::from("12345678901"))
+        .await;
+    assert!(cache.get(&config, &key("d")).await.is_none());
+    assert!(directory.join("unrelated.txt").exists());
+}
+
+#[tokio::test]
+async fn test_service_does_not_compress_by_default() {
+    let (_dir, service) = common::serve(
+        files(),
+        SiteBuilder::new("site").compression(Compression::default()),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/",
+            &[(header::ACCEPT_ENCODING, "gzip, br")],
+        ))
+        .await
+        .unwrap();
+
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert!(!response.headers().contains_key(header::VARY));
+}
+
+#[tokio::test]
+async fn test_service_compresses_on_the_fly() {
+    let (_dir, service) = common::serve(
+        files(),
+        SiteBuilder::new("site").compression(
+            Compression::default()
+                .with_dynamic(true)
+                .with_cache(CompressionCache::Memory),
+        ),
+    );
+
+    let plain = service.call(common::request("/")).await.unwrap();
+    assert!(!plain.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(
+        plain.headers().get(header::VARY).unwrap(),
+        "Accept-Encoding"
+    );
+
+    for _ in 0..2 {
+        let response = service
+            .call(common::request_with(
+                Method::GET,
+                "/",
+                &[(header::ACCEPT_ENCODING, "br;q=0.5, gzip")],
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert_ne!(
+            response.headers().get(header::ETAG),
+            plain.headers().get(header::ETAG)
+        );
+
+        let content_length: usize = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len(), content_length);
+        assert_eq!(decompress(&body, Encoding::Gzip), page().as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn test_service_skips_small_and_incompressible_files() {
+    let (_dir, service) = common::serve(
+        files(),
+        SiteBuilder::new("site").compression(Compression::default().with_dynamic(true)),
+    );
+
+    for path in ["/tiny.txt", "/image.png"] {
+        let response = service
+            .call(common::request_with(
+                Method::GET,
+                path,
+                &[(header::ACCEPT_ENCODING, "gzip")],
+            ))
+            .await
+            .unwrap();
+
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert!(!response.headers().contains_key(header::VARY));
+    }
+}
+
+#[tokio::test]
+async fn test_service_serves_ranges_uncompressed() {
+    let (_dir, service) = common::serve(
+        files(),
+        SiteBuilder::new("site").compression(
+            Compression::default()
+                .with_dynamic(true)
+                .with_encodings([Encoding::Zstd]),
+        ),
+    );
+
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/",
+            &[
+                (header::ACCEPT_ENCODING, "zstd"),
+                (header::RANGE, "bytes=0-3"),
+            ],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(
+        response.headers().get(header::VARY).unwrap(),
+        "Accept-Encoding"
+    );
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, page().as_bytes()[..4]);
+}
+
+#[tokio::test]
+async fn test_service_head_skips_compression() {
+    let cache = tempfile::tempdir().unwrap();
+    let (_dir, service) = common::serve(
+        files(),
+        SiteBuilder::new("site").compression(Compression::default().with_dynamic(true).with_cache(
+            CompressionCache::Disk(cache.path().to_string_lossy().to_string()),
+        )),
+    );
+    let head = || {
+        let mut request =
+            common::request_with(Method::GET, "/", &[(header::ACCEPT_ENCODING, "gzip")]);
+        *request.method_mut() = Method::HEAD;
+        request
+    };
+
+    // Nothing is compressed (or cached) just to answer a `HEAD` request
+    let response = service.call(head()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_ENCODING).unwrap(),
+        "gzip"
+    );
+    assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+    assert_eq!(cache.path().read_dir().unwrap().count(), 0);
+
+    // Once a `GET` has cached the variant, its length is known
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/",
+            &[(header::ACCEPT_ENCODING, "gzip")],
+        ))
+        .await
+        .unwrap();
+    let length = response.headers()[header::CONTENT_LENGTH].clone();
+
+    let response = service.call(head()).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_LENGTH], length);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.is_empty());
+}
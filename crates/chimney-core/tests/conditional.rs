@@ -0,0 +1,356 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chimney::{
+    config::{EtagStrategy, SiteBuilder},
+    filesystem::{AbstractFile, FileType},
+    server::conditional::{self, EntityTag, EtagCache, Precondition},
+};
+use hyper::{HeaderMap, Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in pairs {
+        map.insert(name.clone(), value.parse().unwrap());
+    }
+    map
+}
+
+const FILES: [(&str, &str); 1] = [("index.html", "<h1>Hello</h1>")];
+
+#[test]
+fn test_entity_tag_display() {
+    assert_eq!(EntityTag::strong("abc").to_string(), "\"abc\"");
+    assert_eq!(EntityTag::weak("abc").to_string(), "W/\"abc\"");
+}
+
+#[test]
+fn test_entity_tag_parse_list() {
+    let tags = EntityTag::parse_list(r#""a", W/"b",  "c,d""#).unwrap();
+    assert_eq!(
+        tags,
+        vec![
+            EntityTag::strong("a"),
+            EntityTag::weak("b"),
+            EntityTag::strong("c,d")
+        ]
+    );
+
+    assert!(EntityTag::parse_list("*").is_none());
+    assert!(EntityTag::parse_list("garbage").unwrap().is_empty());
+}
+
+#[test]
+fn test_entity_tag_comparison() {
+    let strong = EntityTag::strong("1");
+    let weak = EntityTag::weak("1");
+
+    assert!(strong.strong_eq(&EntityTag::strong("1")));
+    assert!(!strong.strong_eq(&weak));
+    assert!(!weak.strong_eq(&weak));
+    assert!(strong.weak_eq(&weak));
+    assert!(!strong.weak_eq(&EntityTag::strong("2")));
+}
+
+#[test]
+fn test_entity_tag_from_metadata() {
+    let mut file = AbstractFile::new("index.html".into(), FileType::File);
+    assert!(EntityTag::from_metadata(&file).is_none());
+
+    file.size = Some(255);
+    file.modified_at = Some(UNIX_EPOCH + Duration::from_secs(16));
+    let tag = EntityTag::from_metadata(&file).unwrap();
+    assert!(tag.is_weak());
+    assert_eq!(tag.tag(), "10-ff");
+}
+
+#[test]
+fn test_entity_tag_from_content_is_stable() {
+    let first = EntityTag::from_content(b"hello");
+    assert!(!first.is_weak());
+    assert_eq!(first, EntityTag::from_content(b"hello"));
+    assert_ne!(first, EntityTag::from_content(b"world"));
+
+    // The first half of the SHA-256 digest, followed by the length
+    assert_eq!(first.tag(), "2cf24dba5fb0a30e26e83b2ac5b9e29e-5");
+}
+
+#[test]
+fn test_etag_cache() {
+    let cache = EtagCache::new(2);
+    let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let tag = EntityTag::from_content(b"hello");
+
+    cache.insert("/a".as_ref(), modified, 5, tag.clone());
+    assert_eq!(cache.get("/a".as_ref(), modified, 5), Some(tag.clone()));
+
+    // Another version of the file doesn't get the tag
+    assert_eq!(cache.get("/a".as_ref(), modified, 6), None);
+    assert_eq!(
+        cache.get("/a".as_ref(), modified + Duration::from_secs(1), 5),
+        None
+    );
+
+    // A new version replaces the previous one, and the oldest files are evicted first
+    cache.insert("/a".as_ref(), modified, 6, tag.clone());
+    cache.insert("/b".as_ref(), modified, 5, tag.clone());
+    assert_eq!(cache.len(), 2);
+    cache.insert("/c".as_ref(), modified, 5, tag.clone());
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get("/a".as_ref(), modified, 6), None);
+    assert!(cache.get("/c".as_ref(), modified, 5).is_some());
+}
+
+#[test]
+fn test_evaluate_without_conditions() {
+    let etag = EntityTag::strong("1");
+    let result = conditional::evaluate(&Method::GET, &HeaderMap::new(), Some(&etag), None);
+    assert_eq!(result, Precondition::Passed);
+}
+
+#[test]
+fn test_evaluate_if_none_match() {
+    let etag = EntityTag::strong("1");
+
+    let matching = headers(&[(header::IF_NONE_MATCH, r#"W/"1""#)]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &matching, Some(&etag), None),
+        Precondition::NotModified
+    );
+    assert_eq!(
+        conditional::evaluate(&Method::HEAD, &matching, Some(&etag), None),
+        Precondition::NotModified
+    );
+    assert_eq!(
+        conditional::evaluate(&Method::POST, &matching, Some(&etag), None),
+        Precondition::Failed
+    );
+
+    let other = headers(&[(header::IF_NONE_MATCH, r#""2""#)]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &other, Some(&etag), None),
+        Precondition::Passed
+    );
+
+    let wildcard = headers(&[(header::IF_NONE_MATCH, "*")]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &wildcard, None, None),
+        Precondition::NotModified
+    );
+}
+
+#[test]
+fn test_evaluate_if_match_uses_strong_comparison() {
+    let strong = EntityTag::strong("1");
+    let weak = EntityTag::weak("1");
+
+    let condition = headers(&[(header::IF_MATCH, r#""1""#)]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &condition, Some(&strong), None),
+        Precondition::Passed
+    );
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &condition, Some(&weak), None),
+        Precondition::Failed
+    );
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &condition, None, None),
+        Precondition::Failed
+    );
+
+    let wildcard = headers(&[(header::IF_MATCH, "*")]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &wildcard, None, None),
+        Precondition::Passed
+    );
+}
+
+#[test]
+fn test_evaluate_if_modified_since() {
+    let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let same = conditional::format_http_date(modified);
+    let earlier = conditional::format_http_date(modified - Duration::from_secs(60));
+
+    let condition = headers(&[(header::IF_MODIFIED_SINCE, same.as_str())]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &condition, None, Some(modified)),
+        Precondition::NotModified
+    );
+
+    // Sub-second precision on the file must not defeat the comparison
+    assert_eq!(
+        conditional::evaluate(
+            &Method::GET,
+            &condition,
+            None,
+            Some(modified + Duration::from_millis(500))
+        ),
+        Precondition::NotModified
+    );
+
+    // Only applies to GET and HEAD
+    assert_eq!(
+        conditional::evaluate(&Method::POST, &condition, None, Some(modified)),
+        Precondition::Passed
+    );
+
+    let condition = headers(&[(header::IF_MODIFIED_SINCE, earlier.as_str())]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &condition, None, Some(modified)),
+        Precondition::Passed
+    );
+
+    // Invalid dates are ignored
+    let condition = headers(&[(header::IF_MODIFIED_SINCE, "yesterday")]);
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &condition, None, Some(modified)),
+        Precondition::Passed
+    );
+}
+
+#[test]
+fn test_evaluate_if_none_match_takes_precedence_over_if_modified_since() {
+    let modified = SystemTime::now();
+    let etag = EntityTag::strong("1");
+    let condition = headers(&[
+        (header::IF_NONE_MATCH, r#""2""#),
+        (
+            header::IF_MODIFIED_SINCE,
+            conditional::format_http_date(modified).as_str(),
+        ),
+    ]);
+
+    assert_eq!(
+        conditional::evaluate(&Method::GET, &condition, Some(&etag), Some(modified)),
+        Precondition::Passed
+    );
+}
+
+#[test]
+fn test_evaluate_if_unmodified_since() {
+    let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let earlier = conditional::format_http_date(modified - Duration::from_secs(60));
+
+    let condition = headers(&[(header::IF_UNMODIFIED_SINCE, earlier.as_str())]);
+    assert_eq!(
+        conditional::evaluate(&Method::PUT, &condition, None, Some(modified)),
+        Precondition::Failed
+    );
+
+    // If-Match takes precedence over If-Unmodified-Since
+    let etag = EntityTag::strong("1");
+    let condition = headers(&[
+        (header::IF_MATCH, r#""1""#),
+        (header::IF_UNMODIFIED_SINCE, earlier.as_str()),
+    ]);
+    assert_eq!(
+        conditional::evaluate(&Method::PUT, &condition, Some(&etag), Some(modified)),
+        Precondition::Passed
+    );
+}
+
+#[tokio::test]
+async fn test_service_revalidates_with_etag() {
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site").etag(EtagStrategy::Weak));
+
+    let response = service.call(common::request("/index.html")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let etag = response.headers().get(header::ETAG).unwrap().clone();
+    assert!(etag.to_str().unwrap().starts_with("W/"));
+    assert!(response.headers().contains_key(header::LAST_MODIFIED));
+
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/index.html",
+            &[(header::IF_NONE_MATCH, etag.to_str().unwrap())],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers().get(header::ETAG), Some(&etag));
+
+    assert!(common::body_text(response).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_service_revalidates_with_last_modified() {
+    let (_dir, service) =
+        common::serve(FILES, SiteBuilder::new("site").etag(EtagStrategy::Disabled));
+
+    let response = service.call(common::request("/")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(header::ETAG));
+
+    let last_modified = response.headers().get(header::LAST_MODIFIED).unwrap();
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/",
+            &[(header::IF_MODIFIED_SINCE, last_modified.to_str().unwrap())],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn test_service_strong_etag_and_if_match() {
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site").etag(EtagStrategy::Strong));
+
+    let response = service.call(common::request("/index.html")).await.unwrap();
+    let etag = response.headers().get(header::ETAG).unwrap().clone();
+    assert!(!etag.to_str().unwrap().starts_with("W/"));
+
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/index.html",
+            &[(header::IF_MATCH, etag.to_str().unwrap())],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/index.html",
+            &[(header::IF_MATCH, r#""stale""#)],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn test_service_strong_etag_is_cached_until_the_file_changes() {
+    let (dir, service) = common::serve(FILES, SiteBuilder::new("site").etag(EtagStrategy::Strong));
+    let path = dir.path().join("site/index.html");
+    let etag = |response: &hyper::Response<_>| response.headers()[header::ETAG].clone();
+
+    let first = etag(&service.call(common::request("/")).await.unwrap());
+    assert_eq!(
+        first,
+        EntityTag::from_content(b"<h1>Hello</h1>").to_header_value()
+    );
+
+    // Same size and modification time, so the cached tag is served without hashing the file
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    std::fs::write(&path, "<h1>Howdy</h1>").unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(modified).unwrap();
+    assert_eq!(
+        etag(&service.call(common::request("/")).await.unwrap()),
+        first
+    );
+
+    file.set_modified(modified + Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(
+        etag(&service.call(common::request("/")).await.unwrap()),
+        EntityTag::from_content(b"<h1>Howdy</h1>").to_header_value()
+    );
+}
@@ -0,0 +1,168 @@
+use chimney::{
+    config::{Format, SiteBuilder, toml::Toml},
+    server::error_page,
+};
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+const NOT_FOUND_PAGE: &str = "<h1>Nothing to see here</h1>";
+const METHOD_PAGE: &str = "<h1>Not like that</h1>";
+
+const FILES: [(&str, &str); 3] = [
+    ("public/index.html", "<h1>Home</h1>"),
+    ("public/404.html", NOT_FOUND_PAGE),
+    ("public/errors/405.html", METHOD_PAGE),
+];
+
+#[test]
+fn test_parse_error_pages() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+
+    [sites.example.error_pages]
+    404 = "404.html"
+    503 = "maintenance.html"
+    "#;
+
+    let config = Toml::new(input).parse().unwrap();
+    let site = config.sites.get("example").unwrap();
+    assert_eq!(site.error_page(404), Some("404.html"));
+    assert_eq!(site.error_page(503), Some("maintenance.html"));
+    assert_eq!(site.error_page(500), None);
+}
+
+#[test]
+fn test_parse_invalid_error_page_status() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+    error_pages = { 200 = "ok.html" }
+    "#;
+
+    assert!(Toml::new(input).parse().is_err());
+}
+
+#[test]
+fn test_default_page() {
+    let page = error_page::default_page(StatusCode::NOT_FOUND);
+    assert!(page.contains("<title>404 Not Found</title>"));
+    assert!(page.contains("chimney"));
+}
+
+#[tokio::test]
+async fn test_service_custom_not_found_page() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .root("public")
+            .error_page(404, "404.html"),
+    );
+    let response = service.call(common::request("/missing")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get(header::CONTENT_LENGTH).unwrap(),
+        &NOT_FOUND_PAGE.len().to_string()
+    );
+
+    assert_eq!(common::body_text(response).await, NOT_FOUND_PAGE);
+}
+
+#[tokio::test]
+async fn test_service_default_not_found_page() {
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site").root("public"));
+    let response = service.call(common::request("/missing")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/html; charset=utf-8"
+    );
+
+    assert_eq!(
+        common::body_text(response).await,
+        error_page::default_page(StatusCode::NOT_FOUND)
+    );
+}
+
+#[tokio::test]
+async fn test_service_missing_error_page_falls_back_to_default() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .root("public")
+            .error_page(404, "nope.html"),
+    );
+    let response = service.call(common::request("/missing")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        common::body_text(response).await,
+        error_page::default_page(StatusCode::NOT_FOUND)
+    );
+}
+
+#[tokio::test]
+async fn test_service_method_not_allowed_page_keeps_allow_header() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .root("public")
+            .error_page(405, "/errors/405.html"),
+    );
+    let response = service
+        .call(common::request_with(Method::DELETE, "/", &[]))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(
+        response.headers().get(header::ALLOW).unwrap(),
+        "GET, HEAD, OPTIONS"
+    );
+
+    assert_eq!(common::body_text(response).await, METHOD_PAGE);
+}
+
+#[tokio::test]
+async fn test_service_head_error_page_has_no_body() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .root("public")
+            .error_page(404, "404.html"),
+    );
+    let response = service
+        .call(common::request_with(Method::HEAD, "/missing", &[]))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers().get(header::CONTENT_LENGTH).unwrap(),
+        &NOT_FOUND_PAGE.len().to_string()
+    );
+
+    assert!(common::body_text(response).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_service_fallback_file_takes_precedence() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .root("public")
+            .fallback_file("public/index.html")
+            .error_page(404, "404.html"),
+    );
+    let response = service.call(common::request("/missing")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(common::body_text(response).await, "<h1>Home</h1>");
+}
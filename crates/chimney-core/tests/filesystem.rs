@@ -1,4 +1,4 @@
-use chimney::filesystem::{local::LocalFS, mock::MockFilesystem, Filesystem};
+use chimney::filesystem::{local::LocalFS, mock::MockFilesystem, Filesystem, STREAM_CHUNK_SIZE};
 
 // Local filesystem tests
 #[test]
@@ -108,3 +108,75 @@ fn test_mock_filesystem_exists() {
     let exists = fs.exists(non_existent_path).unwrap();
     assert!(!exists);
 }
+
+async fn collect_stream(stream: chimney::filesystem::ContentStream) -> (Vec<u8>, usize) {
+    use futures_util::TryStreamExt;
+
+    let chunks: Vec<_> = stream.try_collect().await.unwrap();
+    let count = chunks.len();
+    (chunks.concat(), count)
+}
+
+#[tokio::test]
+async fn test_local_fs_read_stream() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("file.txt");
+    std::fs::write(&path, "0123456789").unwrap();
+
+    let fs = LocalFS::new(temp_dir.path().to_path_buf()).unwrap();
+
+    let stream = fs.read_stream(path.clone(), 0, None).await.unwrap();
+    let (content, _) = collect_stream(stream).await;
+    assert_eq!(content, b"0123456789");
+
+    let stream = fs.read_stream(path.clone(), 3, Some(4)).await.unwrap();
+    let (content, _) = collect_stream(stream).await;
+    assert_eq!(content, b"3456");
+
+    let stream = fs.read_stream(path, 8, Some(100)).await.unwrap();
+    let (content, _) = collect_stream(stream).await;
+    assert_eq!(content, b"89");
+}
+
+#[tokio::test]
+async fn test_local_fs_read_stream_in_chunks() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("large.bin");
+    let data: Vec<u8> = (0..STREAM_CHUNK_SIZE * 3 + 10)
+        .map(|i| (i % 251) as u8)
+        .collect();
+    std::fs::write(&path, &data).unwrap();
+
+    let fs = LocalFS::new(temp_dir.path().to_path_buf()).unwrap();
+    let stream = fs.read_stream(path, 0, None).await.unwrap();
+    let (content, chunks) = collect_stream(stream).await;
+
+    assert_eq!(content, data);
+    assert!(chunks > 1);
+}
+
+#[tokio::test]
+async fn test_local_fs_read_stream_not_found() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let fs = LocalFS::new(temp_dir.path().to_path_buf()).unwrap();
+
+    assert!(fs
+        .read_stream(temp_dir.path().join("missing.txt"), 0, None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_mock_filesystem_read_stream() {
+    let fs = MockFilesystem;
+    let path = std::path::PathBuf::from("data/note.txt");
+    let full = fs.read_file(path.clone()).unwrap();
+
+    let stream = fs.read_stream(path.clone(), 0, None).await.unwrap();
+    let (content, _) = collect_stream(stream).await;
+    assert_eq!(content, full.bytes());
+
+    let stream = fs.read_stream(path, 2, Some(3)).await.unwrap();
+    let (content, _) = collect_stream(stream).await;
+    assert_eq!(content, &full.bytes()[2..5]);
+}
@@ -0,0 +1,196 @@
+use chimney::config::{Format, HeaderRule, SiteBuilder, glob_matches, toml::Toml};
+use hyper::{StatusCode, header, service::Service as _};
+
+mod common;
+
+const FILES: [(&str, &[u8]); 3] = [
+    ("index.html", b"<h1>Home</h1>"),
+    ("assets/js/app.js", b"console.log(1)"),
+    ("assets/logo.png", &[0x89, b'P', b'N', b'G']),
+];
+
+#[test]
+fn test_glob_matches() {
+    assert!(glob_matches("/assets/**", "/assets/app.js"));
+    assert!(glob_matches("/assets/**", "/assets/js/app.js"));
+    assert!(!glob_matches("/assets/**", "/static/app.js"));
+
+    assert!(glob_matches("/assets/*", "/assets/app.js"));
+    assert!(!glob_matches("/assets/*", "/assets/js/app.js"));
+
+    assert!(glob_matches("/docs/**/index.html", "/docs/index.html"));
+    assert!(glob_matches(
+        "/docs/**/index.html",
+        "/docs/v1/guide/index.html"
+    ));
+
+    assert!(glob_matches("/img/logo.???", "/img/logo.png"));
+    assert!(!glob_matches("/img/logo.???", "/img/logo.webp"));
+
+    // Patterns without a slash match the file name in any directory
+    assert!(glob_matches("*.html", "/index.html"));
+    assert!(glob_matches("*.html", "/blog/posts/hello.html"));
+    assert!(!glob_matches("*.html", "/blog/posts/hello.htm"));
+}
+
+#[test]
+fn test_header_rule_matches() {
+    let rule = HeaderRule::mime("image/*");
+    assert!(rule.matches("/logo.png", "image/png"));
+    assert!(!rule.matches("/index.html", "text/html"));
+
+    let rule = HeaderRule::path("/docs/**").with_mime("text/html");
+    assert!(rule.matches("/docs/index.html", "text/html; charset=utf-8"));
+    assert!(!rule.matches("/docs/app.js", "text/javascript"));
+    assert!(!rule.matches("/index.html", "text/html"));
+
+    assert!(HeaderRule::new().matches("/anything", "application/octet-stream"));
+}
+
+#[test]
+fn test_parse_header_rules() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+
+    [[sites.example.header_rules]]
+    path = "/assets/**"
+    set = { "Cache-Control" = "public, max-age=31536000, immutable" }
+
+    [[sites.example.header_rules]]
+    mime = "text/html"
+    set = { "Cache-Control" = "no-cache" }
+    append = { "Link" = "</app.css>; rel=preload; as=style" }
+    remove = ["X-Powered-By"]
+    "#;
+
+    let config = Toml::new(input).parse().unwrap();
+    let site = config.sites.get("example").unwrap();
+    assert_eq!(
+        site.header_rules,
+        vec![
+            HeaderRule::path("/assets/**")
+                .set("Cache-Control", "public, max-age=31536000, immutable"),
+            HeaderRule::mime("text/html")
+                .set("Cache-Control", "no-cache")
+                .append("Link", "</app.css>; rel=preload; as=style")
+                .remove("X-Powered-By"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_invalid_header_rule() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+
+    [[sites.example.header_rules]]
+    set = { "Not A Header" = "value" }
+    "#;
+
+    assert!(Toml::new(input).parse().is_err());
+}
+
+#[tokio::test]
+async fn test_service_applies_matching_rules() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .header_rule(
+                HeaderRule::path("/assets/**")
+                    .set("Cache-Control", "public, max-age=31536000, immutable"),
+            )
+            .header_rule(HeaderRule::path("*.html").set("Cache-Control", "no-cache")),
+    );
+
+    let response = service
+        .call(common::request("/assets/js/app.js"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CACHE_CONTROL).unwrap(),
+        "public, max-age=31536000, immutable"
+    );
+
+    let response = service.call(common::request("/index.html")).await.unwrap();
+    assert_eq!(
+        response.headers().get(header::CACHE_CONTROL).unwrap(),
+        "no-cache"
+    );
+}
+
+#[tokio::test]
+async fn test_service_later_rules_override_earlier_ones() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .response_header("Cache-Control", "no-store")
+            .header_rule(HeaderRule::path("/assets/**").set("Cache-Control", "max-age=60"))
+            .header_rule(HeaderRule::mime("image/*").set("Cache-Control", "max-age=3600")),
+    );
+
+    let response = service
+        .call(common::request("/assets/logo.png"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get(header::CACHE_CONTROL).unwrap(),
+        "max-age=3600"
+    );
+
+    let response = service
+        .call(common::request("/assets/js/app.js"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get(header::CACHE_CONTROL).unwrap(),
+        "max-age=60"
+    );
+
+    let response = service.call(common::request("/index.html")).await.unwrap();
+    assert_eq!(
+        response.headers().get(header::CACHE_CONTROL).unwrap(),
+        "no-store"
+    );
+}
+
+#[tokio::test]
+async fn test_service_append_and_remove() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .response_header("X-Powered-By", "chimney")
+            .response_header("Link", "</app.css>; rel=preload; as=style")
+            .header_rule(
+                HeaderRule::mime("text/html")
+                    .append("Link", "</app.js>; rel=preload; as=script")
+                    .remove("X-Powered-By"),
+            ),
+    );
+
+    let response = service.call(common::request("/index.html")).await.unwrap();
+    assert!(response.headers().get("X-Powered-By").is_none());
+
+    let links = response
+        .headers()
+        .get_all(header::LINK)
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        links,
+        vec![
+            "</app.css>; rel=preload; as=style",
+            "</app.js>; rel=preload; as=script"
+        ]
+    );
+
+    // Files that don't match the rule are left untouched
+    let response = service
+        .call(common::request("/assets/js/app.js"))
+        .await
+        .unwrap();
+    assert_eq!(response.headers().get("X-Powered-By").unwrap(), "chimney");
+}
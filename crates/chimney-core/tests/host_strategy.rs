@@ -23,7 +23,7 @@ fn create_config_handle(config: Config) -> ConfigHandle {
 fn create_service(config: Config) -> Service {
     let fs = Arc::new(MockFilesystem);
     let config_handle = create_config_handle(config);
-    Service::new(fs, config_handle)
+    Service::new(fs, config_handle).unwrap()
 }
 
 #[tokio::test]
@@ -0,0 +1,229 @@
+use chimney::{
+    config::{I18n, LocaleStrategy, Site, SiteBuilder},
+    error::ChimneyError,
+    server::{
+        body::Body,
+        language::{cookie_value, negotiate, parse_accept_language},
+        service::Service,
+    },
+};
+use hyper::{Method, Response, StatusCode, header, service::Service as _};
+
+mod common;
+
+const FILES: [(&str, &str); 5] = [
+    ("en/index.html", "<h1>en</h1>"),
+    ("fr/index.html", "<h1>fr</h1>"),
+    ("de/index.html", "<h1>de</h1>"),
+    ("fr/about.html", "<h1>À propos</h1>"),
+    ("robots.txt", "User-agent: *"),
+];
+
+async fn get(
+    service: &Service,
+    uri: &str,
+    headers: &[(header::HeaderName, &str)],
+) -> Response<Body> {
+    let request = common::request_with(Method::GET, uri, headers);
+    service.call(request).await.unwrap()
+}
+
+fn vary(response: &Response<Body>) -> Vec<&str> {
+    response
+        .headers()
+        .get_all(header::VARY)
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_parse_accept_language() {
+    let accepted = parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, *;q=0.5, de;q=oops");
+    let ranges: Vec<_> = accepted
+        .iter()
+        .map(|entry| (entry.range.as_str(), entry.quality))
+        .collect();
+
+    assert_eq!(
+        ranges,
+        [
+            ("fr-ch", 1.0),
+            ("fr", 0.9),
+            ("en", 0.8),
+            ("*", 0.5),
+            ("de", 0.0)
+        ]
+    );
+}
+
+#[test]
+fn test_negotiate() {
+    let locales = ["en".to_string(), "fr".to_string(), "pt-BR".to_string()];
+
+    assert_eq!(negotiate(Some("fr"), &locales), Some("fr"));
+    assert_eq!(negotiate(Some("FR"), &locales), Some("fr"));
+    assert_eq!(
+        negotiate(Some("de, fr;q=0.5, en;q=0.7"), &locales),
+        Some("en")
+    );
+    assert_eq!(negotiate(Some("fr-CH"), &locales), Some("fr"));
+    assert_eq!(negotiate(Some("pt"), &locales), Some("pt-BR"));
+    assert_eq!(negotiate(Some("en;q=0, fr;q=0.1"), &locales), Some("fr"));
+    assert_eq!(negotiate(Some("frr"), &locales), None);
+    assert_eq!(negotiate(Some("de, *"), &locales), None);
+    assert_eq!(negotiate(None, &locales), None);
+}
+
+#[test]
+fn test_cookie_value() {
+    assert_eq!(cookie_value("theme=dark; locale=fr", "locale"), Some("fr"));
+    assert_eq!(cookie_value("locale=\"de\"", "locale"), Some("de"));
+    assert_eq!(cookie_value("my_locale=fr", "locale"), None);
+}
+
+#[test]
+fn test_parse_i18n() {
+    let parse = |i18n: &str| {
+        let input = format!(
+            r#"
+            domain_names = ["example.com"]
+
+            [i18n]
+            {i18n}
+            "#
+        );
+        Site::from_string("example".into(), &input)
+    };
+
+    let site = parse(
+        r#"
+        locales = ["en", "fr", "de"]
+        default_locale = "fr"
+        cookie = "locale"
+        strategy = "rewrite"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        site.i18n,
+        I18n::new(["en", "fr", "de"])
+            .with_default_locale("fr")
+            .with_cookie("locale")
+            .with_strategy(LocaleStrategy::Rewrite)
+    );
+    assert_eq!(site.i18n.default_locale(), Some("fr"));
+
+    let errors = [
+        (r#"locales = ["en", "../fr"]"#, "sites.example.i18n.locales"),
+        (
+            r#"locales = ["en"]
+            default_locale = "fr""#,
+            "sites.example.i18n.default_locale",
+        ),
+    ];
+    for (i18n, expected) in errors {
+        match parse(i18n).unwrap_err() {
+            ChimneyError::ConfigError { field, .. } => assert_eq!(field, expected),
+            error => panic!("expected a config error, got {error:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_redirects_to_negotiated_locale() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").i18n(I18n::new(["en", "fr", "de"]).with_cookie("locale")),
+    );
+
+    let response = get(
+        &service,
+        "/?ref=home",
+        &[(header::ACCEPT_LANGUAGE, "fr-CH, de;q=0.8")],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()[header::LOCATION], "/fr/?ref=home");
+    assert_eq!(vary(&response), ["Accept-Language", "Cookie"]);
+    assert!(!response.headers().contains_key(header::CONTENT_LANGUAGE));
+
+    // The cookie wins over the header, unless it isn't one of the locales
+    let response = get(
+        &service,
+        "/",
+        &[
+            (header::ACCEPT_LANGUAGE, "fr"),
+            (header::COOKIE, "theme=dark; locale=de"),
+        ],
+    )
+    .await;
+    assert_eq!(response.headers()[header::LOCATION], "/de/");
+
+    let response = get(
+        &service,
+        "/",
+        &[
+            (header::ACCEPT_LANGUAGE, "fr"),
+            (header::COOKIE, "locale=it"),
+        ],
+    )
+    .await;
+    assert_eq!(response.headers()[header::LOCATION], "/fr/");
+
+    // Without a match, the default locale is used
+    let response = get(&service, "/", &[(header::ACCEPT_LANGUAGE, "ja")]).await;
+    assert_eq!(response.headers()[header::LOCATION], "/en/");
+    let response = get(&service, "/", &[]).await;
+    assert_eq!(response.headers()[header::LOCATION], "/en/");
+}
+
+#[tokio::test]
+async fn test_rewrites_to_negotiated_locale() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").i18n(
+            I18n::new(["en", "fr", "de"])
+                .with_default_locale("de")
+                .with_strategy(LocaleStrategy::Rewrite),
+        ),
+    );
+
+    let response = get(&service, "/", &[(header::ACCEPT_LANGUAGE, "fr")]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "fr");
+    assert_eq!(vary(&response), ["Accept-Language"]);
+    assert_eq!(common::body_text(response).await, "<h1>fr</h1>");
+
+    let response = get(&service, "/", &[]).await;
+    assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "de");
+}
+
+#[tokio::test]
+async fn test_locale_subtrees() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").i18n(I18n::new(["en", "fr", "de"])),
+    );
+
+    // Pages inside a locale are served as they are, in their locale
+    let response = get(
+        &service,
+        "/fr/about.html",
+        &[(header::ACCEPT_LANGUAGE, "de")],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "fr");
+    assert!(vary(&response).is_empty());
+
+    let response = get(&service, "/robots.txt", &[(header::ACCEPT_LANGUAGE, "de")]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(header::CONTENT_LANGUAGE));
+
+    // Sites without locales are left alone
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site").i18n(I18n::default()));
+    let response = get(&service, "/", &[(header::ACCEPT_LANGUAGE, "fr")]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(vary(&response).is_empty());
+}
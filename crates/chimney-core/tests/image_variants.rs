@@ -0,0 +1,225 @@
+use chimney::{
+    config::{AccessRules, ImageFormat, ImageVariants, Site, SiteBuilder},
+    server::{
+        body::Body,
+        image_variant::{negotiate, variant_path},
+        service::Service,
+    },
+};
+use hyper::{Method, Response, StatusCode, header, service::Service as _};
+use std::path::Path;
+
+mod common;
+
+const BROWSER_ACCEPT: &str = "image/avif,image/webp,image/apng,image/*,*/*;q=0.8";
+
+const FILES: [(&str, &str); 7] = [
+    ("img/photo.jpg", "jpeg"),
+    ("img/photo.avif", "avif"),
+    ("img/photo.webp", "webp"),
+    ("img/banner.png", "png"),
+    ("img/banner.webp", "webp"),
+    ("img/logo.svg", "svg"),
+    ("img/logo.webp", "webp"),
+];
+
+async fn get(service: &Service, uri: &str, accept: Option<&str>) -> Response<Body> {
+    let request = match accept {
+        Some(accept) => common::request_with(Method::GET, uri, &[(header::ACCEPT, accept)]),
+        None => common::request(uri),
+    };
+
+    let response = service.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    response
+}
+
+async fn served(service: &Service, uri: &str, accept: Option<&str>) -> (String, String) {
+    let response = get(service, uri, accept).await;
+    let content_type = response.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .to_string();
+    (content_type, common::body_text(response).await)
+}
+
+fn vary(response: &Response<Body>) -> Vec<&str> {
+    response
+        .headers()
+        .get_all(header::VARY)
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_negotiate() {
+    let available = [ImageFormat::Avif, ImageFormat::Webp];
+
+    assert_eq!(
+        negotiate(Some(BROWSER_ACCEPT), &available),
+        Some(ImageFormat::Avif)
+    );
+    assert_eq!(
+        negotiate(Some("image/webp,*/*"), &available),
+        Some(ImageFormat::Webp)
+    );
+    assert_eq!(
+        negotiate(Some("image/avif;q=0.5, image/webp"), &available),
+        Some(ImageFormat::Webp)
+    );
+    assert_eq!(
+        negotiate(Some("image/avif;q=0, image/webp;q=0"), &available),
+        None
+    );
+
+    // Wildcards don't mean the client can decode every format
+    assert_eq!(negotiate(Some("image/*,*/*;q=0.8"), &available), None);
+    assert_eq!(negotiate(None, &available), None);
+}
+
+#[test]
+fn test_variant_path() {
+    assert_eq!(
+        variant_path(Path::new("img/photo.jpg"), ImageFormat::Avif),
+        Path::new("img/photo.avif")
+    );
+    assert_eq!(
+        variant_path(Path::new("img/photo.old.png"), ImageFormat::Webp),
+        Path::new("img/photo.old.webp")
+    );
+}
+
+#[test]
+fn test_parse_image_variants() {
+    let site = Site::from_string(
+        "example".into(),
+        r#"
+        domain_names = ["example.com"]
+
+        [image_variants]
+        formats = ["avif", "webp", "jxl"]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        site.image_variants,
+        ImageVariants::new([ImageFormat::Avif, ImageFormat::Webp, ImageFormat::Jxl])
+    );
+    assert!(site.image_variants.applies_to(Path::new("photo.JPG")));
+    assert!(!site.image_variants.applies_to(Path::new("logo.svg")));
+    assert!(!ImageVariants::default().applies_to(Path::new("photo.jpg")));
+}
+
+#[tokio::test]
+async fn test_serves_accepted_variant() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .image_variants(ImageVariants::new([ImageFormat::Avif, ImageFormat::Webp])),
+    );
+
+    assert_eq!(
+        served(&service, "/img/photo.jpg", Some(BROWSER_ACCEPT)).await,
+        ("image/avif".into(), "avif".into())
+    );
+    assert_eq!(
+        served(&service, "/img/photo.jpg", Some("image/webp,*/*")).await,
+        ("image/webp".into(), "webp".into())
+    );
+    assert_eq!(
+        served(&service, "/img/photo.jpg", Some("*/*")).await,
+        ("image/jpeg".into(), "jpeg".into())
+    );
+
+    // Only the variants that exist are considered
+    assert_eq!(
+        served(&service, "/img/banner.png", Some(BROWSER_ACCEPT)).await,
+        ("image/webp".into(), "webp".into())
+    );
+
+    let response = get(&service, "/img/photo.jpg", None).await;
+    assert_eq!(vary(&response), ["Accept"]);
+}
+
+#[tokio::test]
+async fn test_variants_only_for_configured_images() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").image_variants(
+            ImageVariants::new([ImageFormat::Webp, ImageFormat::Avif]).with_extensions(["jpg"]),
+        ),
+    );
+
+    // The site's preference decides between formats the client accepts equally
+    assert_eq!(
+        served(&service, "/img/photo.jpg", Some(BROWSER_ACCEPT))
+            .await
+            .1,
+        "webp"
+    );
+
+    let response = get(&service, "/img/banner.png", Some(BROWSER_ACCEPT)).await;
+    assert!(vary(&response).is_empty());
+    assert_eq!(
+        served(&service, "/img/banner.png", Some(BROWSER_ACCEPT))
+            .await
+            .1,
+        "png"
+    );
+    assert_eq!(
+        served(&service, "/img/logo.svg", Some(BROWSER_ACCEPT))
+            .await
+            .1,
+        "svg"
+    );
+
+    // Variants are disabled unless the site enables them
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").image_variants(ImageVariants::default()),
+    );
+    let response = get(&service, "/img/photo.jpg", Some(BROWSER_ACCEPT)).await;
+    assert!(vary(&response).is_empty());
+    assert_eq!(
+        served(&service, "/img/photo.jpg", Some(BROWSER_ACCEPT))
+            .await
+            .1,
+        "jpeg"
+    );
+}
+
+#[tokio::test]
+async fn test_variants_held_to_the_site_rules() {
+    let dir = common::site_directory([
+        ("img/photo.jpg", "jpeg"),
+        ("img/photo.webp", "webp"),
+        ("img/banner.png", "png"),
+    ]);
+    std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+
+    // A variant pointing outside of the root is never served
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(
+        dir.path().join("secret.txt"),
+        dir.path().join("site/img/banner.avif"),
+    )
+    .unwrap();
+
+    let builder = SiteBuilder::new("site")
+        .image_variants(ImageVariants::new([ImageFormat::Avif, ImageFormat::Webp]))
+        .access(AccessRules::default().deny("*.webp"));
+    let service = common::service(&dir, builder);
+
+    assert_eq!(
+        served(&service, "/img/banner.png", Some(BROWSER_ACCEPT)).await,
+        ("image/png".into(), "png".into())
+    );
+
+    // Neither is a variant the site denies access to
+    assert_eq!(
+        served(&service, "/img/photo.jpg", Some(BROWSER_ACCEPT)).await,
+        ("image/jpeg".into(), "jpeg".into())
+    );
+}
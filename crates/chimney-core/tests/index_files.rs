@@ -0,0 +1,167 @@
+use chimney::{
+    config::{Autoindex, CleanUrls, Site, SiteBuilder},
+    error::ChimneyError,
+    server::service::Service,
+};
+use hyper::{StatusCode, service::Service as _};
+
+mod common;
+
+fn mock_service(builder: SiteBuilder) -> (tempfile::TempDir, Service) {
+    let files = [
+        ("index.html", "<h1>Home</h1>"),
+        ("legacy/index.htm", "<h1>Legacy</h1>"),
+        ("legacy/default.html", "<h1>Default</h1>"),
+        ("docs/index.html", "<h1>Docs</h1>"),
+        ("docs/README.html", "<h1>Docs readme</h1>"),
+        ("readme/README.html", "<h1>Readme</h1>"),
+    ];
+
+    let (dir, service) = common::serve(files, builder);
+    std::fs::create_dir_all(dir.path().join("site/empty")).unwrap();
+    (dir, service)
+}
+
+fn legacy_site() -> SiteBuilder {
+    SiteBuilder::new("site").default_index_files([
+        "index.html",
+        "index.htm",
+        "default.html",
+        "README.html",
+    ])
+}
+
+#[test]
+fn test_parse_index_files() {
+    let parse = |fields: &str| {
+        let input = format!(
+            r#"
+            domain_names = ["example.com"]
+            {fields}
+            "#
+        );
+        Site::from_string("example".into(), &input)
+    };
+
+    // The single file form keeps working
+    let site = parse(r#"default_index_file = "main.html""#).unwrap();
+    assert_eq!(site.default_index_file, Some("main.html".to_string()));
+    assert_eq!(site.index_files(), ["main.html"]);
+
+    let site = parse(r#"default_index_files = ["index.html", "index.htm"]"#).unwrap();
+    assert_eq!(site.index_files(), ["index.html", "index.htm"]);
+
+    let site = parse("").unwrap();
+    assert_eq!(site.index_files(), ["index.html"]);
+
+    for fields in [
+        "default_index_files = []",
+        "default_index_file = \"main.html\"\ndefault_index_files = [\"index.htm\"]",
+    ] {
+        match parse(fields).unwrap_err() {
+            ChimneyError::ConfigError { field, .. } => {
+                assert_eq!(field, "sites.example.default_index_files")
+            }
+            error => panic!("expected a config error, got {error:?}"),
+        }
+    }
+    assert!(parse("default_index_file = 42").is_err());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_index_file() {
+    let site = SiteBuilder::new("site").build();
+    assert_eq!(site.index_file(), "index.html");
+
+    let site = SiteBuilder::new("site")
+        .default_index_file("main.html")
+        .build();
+    assert_eq!(site.index_file(), "main.html");
+
+    let site = SiteBuilder::new("site")
+        .default_index_files(["default.html", "index.htm"])
+        .build();
+    assert_eq!(site.index_file(), "default.html");
+}
+
+#[test]
+fn test_index_files_round_trip() {
+    let site = legacy_site().domain("example.com").build();
+    let output = site.to_toml_string().unwrap();
+
+    let parsed = Site::from_string("site".into(), &output).unwrap();
+    assert_eq!(parsed.default_index_files, site.default_index_files);
+    assert_eq!(parsed.index_files(), site.index_files());
+}
+
+#[tokio::test]
+async fn test_index_files_are_tried_in_order() {
+    let (_dir, service) = mock_service(legacy_site());
+
+    assert_eq!(
+        common::get(&service, "/").await,
+        (StatusCode::OK, "<h1>Home</h1>".into())
+    );
+    assert_eq!(
+        common::get(&service, "/legacy/").await,
+        (StatusCode::OK, "<h1>Legacy</h1>".into())
+    );
+    assert_eq!(
+        common::get(&service, "/docs").await,
+        (StatusCode::OK, "<h1>Docs</h1>".into())
+    );
+    assert_eq!(
+        common::get(&service, "/readme/").await,
+        (StatusCode::OK, "<h1>Readme</h1>".into())
+    );
+    assert_eq!(
+        common::get(&service, "/empty/").await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_single_index_file() {
+    let (_dir, service) = mock_service(SiteBuilder::new("site").default_index_file("index.htm"));
+
+    assert_eq!(
+        common::get(&service, "/legacy/").await,
+        (StatusCode::OK, "<h1>Legacy</h1>".into())
+    );
+    assert_eq!(
+        common::get(&service, "/docs/").await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_listing_only_without_any_index_file() {
+    let (_dir, service) = mock_service(legacy_site().autoindex(Autoindex::enabled()));
+
+    assert_eq!(
+        common::get(&service, "/readme/").await,
+        (StatusCode::OK, "<h1>Readme</h1>".into())
+    );
+
+    let (status, body) = common::get(&service, "/empty/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("Index of /empty"), "{body}");
+}
+
+#[tokio::test]
+async fn test_clean_urls_redirect_every_index_file() {
+    let (_dir, service) =
+        mock_service(legacy_site().clean_urls(CleanUrls::enabled().with_redirect(true)));
+
+    for uri in ["/legacy/index.htm", "/readme/README.html"] {
+        let response = service.call(common::request(uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY, "{uri}");
+    }
+
+    // `/legacy` serves `index.htm`, so `default.html` is a different page
+    assert_eq!(
+        common::get(&service, "/legacy/default.html").await,
+        (StatusCode::OK, "<h1>Default</h1>".into())
+    );
+}
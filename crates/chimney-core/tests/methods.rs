@@ -0,0 +1,131 @@
+use chimney::config::{Format, SiteBuilder, toml::Toml};
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+const CONTENT: &str = "<h1>Thank you!</h1>";
+
+#[test]
+fn test_site_allowed_methods() {
+    let site = SiteBuilder::new("site").build();
+    assert_eq!(site.allowed_methods(), vec!["GET", "HEAD", "OPTIONS"]);
+    assert!(site.is_method_allowed("GET"));
+    assert!(!site.is_method_allowed("POST"));
+
+    let site = SiteBuilder::new("site")
+        .allow_method("post")
+        .allow_method("POST")
+        .allow_method("get")
+        .build();
+    assert_eq!(
+        site.allowed_methods(),
+        vec!["GET", "HEAD", "OPTIONS", "POST"]
+    );
+    assert!(site.is_method_allowed("POST"));
+    assert!(!site.is_method_allowed("post"));
+    assert!(!site.is_method_allowed("get"));
+}
+
+#[test]
+fn test_parse_allowed_methods() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+    allowed_methods = ["POST", "put"]
+    "#;
+
+    let config = Toml::new(input).parse().unwrap();
+    let site = config.sites.get("example").unwrap();
+    assert!(site.is_method_allowed("POST"));
+    assert!(site.is_method_allowed("PUT"));
+    assert!(!site.is_method_allowed("DELETE"));
+}
+
+#[test]
+fn test_parse_invalid_allowed_method() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+    allowed_methods = ["NOT A METHOD"]
+    "#;
+
+    assert!(Toml::new(input).parse().is_err());
+}
+
+#[tokio::test]
+async fn test_service_head_has_headers_without_body() {
+    let (_dir, service) = common::serve([("thanks.html", CONTENT)], SiteBuilder::new("site"));
+    let response = service
+        .call(common::request_with(Method::HEAD, "/thanks.html", &[]))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_LENGTH).unwrap(),
+        &CONTENT.len().to_string()
+    );
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/html; charset=utf-8"
+    );
+
+    assert!(common::body_text(response).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_service_options_lists_allowed_methods() {
+    let (_dir, service) = common::serve(
+        [("thanks.html", CONTENT)],
+        SiteBuilder::new("site").allow_method("POST"),
+    );
+    let response = service
+        .call(common::request_with(Method::OPTIONS, "/thanks.html", &[]))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        response.headers().get(header::ALLOW).unwrap(),
+        "GET, HEAD, OPTIONS, POST"
+    );
+}
+
+#[tokio::test]
+async fn test_service_rejects_disallowed_methods() {
+    let (_dir, service) = common::serve([("thanks.html", CONTENT)], SiteBuilder::new("site"));
+
+    for method in [Method::POST, Method::PUT, Method::DELETE, Method::PATCH] {
+        let response = service
+            .call(common::request_with(method, "/thanks.html", &[]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            response.headers().get(header::ALLOW).unwrap(),
+            "GET, HEAD, OPTIONS"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_service_serves_allowlisted_methods() {
+    let (_dir, service) = common::serve(
+        [("thanks.html", CONTENT)],
+        SiteBuilder::new("site").allow_method("POST"),
+    );
+    let response = service
+        .call(common::request_with(Method::POST, "/thanks.html", &[]))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(common::body_text(response).await, CONTENT);
+
+    let response = service
+        .call(common::request_with(Method::DELETE, "/thanks.html", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+}
@@ -1,5 +1,53 @@
-use chimney::server::mimetype::{DEFAULT_MIME_TYPE, from_extension, from_path};
-use std::path::PathBuf;
+use chimney::{
+    config::{Config, Format, HeaderRule, Site, SiteBuilder, toml::Toml},
+    error::ChimneyError,
+    server::{
+        body::Body,
+        mimetype::{DEFAULT_MIME_TYPE, MimeRegistry, from_extension, from_path, sniff},
+        service::Service,
+    },
+};
+use hyper::{Response, StatusCode, header, service::Service as _};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+mod common;
+
+const MIME_TYPES: &str = r#"
+# Comments and empty lines are skipped
+text/x-gemini                  gmi gemini
+
+application/vnd.custom+xml     cxml
+image/x-icon                   ico
+application/x-no-extensions
+"#;
+
+const FILES: [(&str, &[u8]); 9] = [
+    ("index.html", b"content"),
+    ("app.js", b"content"),
+    ("app.js.map", b"content"),
+    ("clip.ts", b"content"),
+    ("notes.txt", b"content"),
+    ("model.glb", b"content"),
+    ("about", b"\n<!DOCTYPE html>\n<title>About</title>"),
+    ("logo", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+    ("export.dat", b"[{\"id\": 1}]"),
+];
+
+async fn get(service: &Service, uri: &str) -> Response<Body> {
+    let response = service.call(common::request(uri)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    response
+}
+
+async fn content_type(service: &Service, uri: &str) -> String {
+    get(service, uri).await.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
 
 #[test]
 fn test_from_extension() {
@@ -14,8 +62,278 @@ fn test_from_path() {
     assert_eq!(from_path(PathBuf::from("file.txt")), "text/plain");
     assert_eq!(from_path(PathBuf::from("file.html")), "text/html");
     assert_eq!(from_path(PathBuf::from("file.jpg")), "image/jpeg");
+    assert_eq!(from_path(PathBuf::from("file.unknown")), DEFAULT_MIME_TYPE);
+}
+
+#[test]
+fn test_registry_content_type() {
+    let registry = MimeRegistry::default();
+    let content_type = |path: &str| registry.content_type(Path::new(path), &HashMap::new());
+
+    assert_eq!(content_type("index.html"), "text/html; charset=utf-8");
+    assert_eq!(content_type("app.mjs"), "text/javascript; charset=utf-8");
+    assert_eq!(
+        content_type("feed.rss"),
+        "application/rss+xml; charset=utf-8"
+    );
+    assert_eq!(content_type("main.ts"), "text/typescript; charset=utf-8");
+    assert_eq!(content_type("app.js.map"), "application/json");
+    assert_eq!(
+        content_type("site.webmanifest"),
+        "application/manifest+json"
+    );
+    assert_eq!(content_type("model.glb"), "model/gltf-binary");
+    assert_eq!(content_type("photo.JPG"), "image/jpeg");
+    assert_eq!(content_type("LICENSE"), DEFAULT_MIME_TYPE);
+
+    let registry = MimeRegistry::default().with_charset(None::<String>);
+    assert_eq!(
+        registry.content_type(Path::new("index.html"), &HashMap::new()),
+        "text/html"
+    );
+}
+
+#[test]
+fn test_registry_parse() {
+    let mut registry = MimeRegistry::default();
+    registry.parse(MIME_TYPES).unwrap();
+
+    assert_eq!(registry.lookup("gmi"), "text/x-gemini");
+    assert_eq!(registry.lookup(".GEMINI"), "text/x-gemini");
+    assert_eq!(registry.lookup("cxml"), "application/vnd.custom+xml");
+    assert_eq!(registry.lookup("ico"), "image/x-icon");
+    assert_eq!(registry.lookup("html"), "text/html");
+
+    let error = registry
+        .parse("text/plain txt\nnot-a-type foo")
+        .unwrap_err();
+    assert!(error.contains("line 2"), "{error}");
+}
+
+#[test]
+fn test_registry_overrides() {
+    let registry = MimeRegistry::default();
+    let overrides = HashMap::from([
+        ("ts".to_string(), "video/mp2t".to_string()),
+        (
+            ".TXT".to_string(),
+            "text/plain; charset=iso-8859-1".to_string(),
+        ),
+    ]);
+
+    assert_eq!(
+        registry.content_type(Path::new("clip.ts"), &overrides),
+        "video/mp2t"
+    );
+    assert_eq!(
+        registry.content_type(Path::new("notes.txt"), &overrides),
+        "text/plain; charset=iso-8859-1"
+    );
+    assert_eq!(
+        registry.content_type(Path::new("index.html"), &overrides),
+        "text/html; charset=utf-8"
+    );
+}
+
+#[test]
+fn test_parse_site_mime_types() {
+    let parse = |mime_types: &str| {
+        let input = format!(
+            r#"
+            domain_names = ["example.com"]
+
+            [mime_types]
+            {mime_types}
+            "#
+        );
+        Site::from_string("example".into(), &input)
+    };
+
+    let site = parse(r#"ts = "video/mp2t""#).unwrap();
+    assert_eq!(site.mime_types["ts"], "video/mp2t");
+
+    match parse(r#"ts = "typescript""#).unwrap_err() {
+        ChimneyError::ConfigError { field, .. } => assert_eq!(field, "sites.example.mime_types.ts"),
+        error => panic!("expected a config error, got {error:?}"),
+    }
+}
+
+#[test]
+fn test_load_mime_types() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mime.types");
+    std::fs::write(&path, MIME_TYPES).unwrap();
+
+    let mut config = Config::default();
+    config.mime_types_files = vec![path];
+    config.default_charset = "iso-8859-1".into();
+    config.load_mime_types().unwrap();
+
+    let content_type = |path: &str| {
+        config
+            .mime_types()
+            .content_type(Path::new(path), &HashMap::new())
+    };
+    assert_eq!(
+        content_type("hello.gmi"),
+        "text/x-gemini; charset=iso-8859-1"
+    );
+    assert_eq!(content_type("index.html"), "text/html; charset=iso-8859-1");
+
+    config.default_charset = String::new();
+    config.load_mime_types().unwrap();
     assert_eq!(
-        from_path(PathBuf::from("file.unknown")),
-        DEFAULT_MIME_TYPE
+        config
+            .mime_types()
+            .content_type(Path::new("index.html"), &HashMap::new()),
+        "text/html"
+    );
+
+    config.mime_types_files = vec![dir.path().join("missing.types")];
+    match config.load_mime_types().unwrap_err() {
+        ChimneyError::ConfigError { field, .. } => assert_eq!(field, "mime_types_files"),
+        error => panic!("expected a config error, got {error:?}"),
+    }
+}
+
+#[test]
+fn test_parse_loads_mime_types() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mime.types");
+    std::fs::write(&path, MIME_TYPES).unwrap();
+
+    let input = format!("mime_types_files = [{:?}]", path.to_string_lossy());
+    let config = Toml::from(input.as_str()).parse().unwrap();
+    assert_eq!(
+        config
+            .mime_types()
+            .content_type(Path::new("hello.gmi"), &HashMap::new()),
+        "text/x-gemini; charset=utf-8"
+    );
+
+    let input = format!(
+        "mime_types_files = [{:?}]",
+        dir.path().join("missing.types").to_string_lossy()
+    );
+    match Toml::from(input.as_str()).parse().unwrap_err() {
+        ChimneyError::ConfigError { field, .. } => assert_eq!(field, "mime_types_files"),
+        error => panic!("expected a config error, got {error:?}"),
+    }
+
+    let input = r#"default_charset = "utf 8""#;
+    assert!(Toml::from(input).parse().is_err());
+}
+
+#[tokio::test]
+async fn test_service_content_types() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").mime_type("ts", "video/mp2t"),
+    );
+
+    assert_eq!(
+        content_type(&service, "/").await,
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(
+        content_type(&service, "/app.js").await,
+        "text/javascript; charset=utf-8"
+    );
+    assert_eq!(
+        content_type(&service, "/app.js.map").await,
+        "application/json"
+    );
+    assert_eq!(content_type(&service, "/clip.ts").await, "video/mp2t");
+    assert_eq!(
+        content_type(&service, "/model.glb").await,
+        "model/gltf-binary"
+    );
+
+    // Sites without the override serve `.ts` as TypeScript
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site"));
+    assert_eq!(
+        content_type(&service, "/clip.ts").await,
+        "text/typescript; charset=utf-8"
+    );
+}
+
+#[test]
+fn test_sniff() {
+    assert_eq!(sniff(b"<!DOCTYPE html><html></html>"), Some("text/html"));
+    assert_eq!(
+        sniff(b"\xef\xbb\xbf  <HTML lang=\"en\">"),
+        Some("text/html")
+    );
+    assert_eq!(sniff(b"<!-- generated -->"), Some("text/html"));
+    assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0"), Some("image/png"));
+    assert_eq!(sniff(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+    assert_eq!(sniff(b"GIF89a\x01\0"), Some("image/gif"));
+    assert_eq!(sniff(b"RIFF\x24\0\0\0WEBPVP8 "), Some("image/webp"));
+    assert_eq!(sniff(b"%PDF-1.7\n"), Some("application/pdf"));
+    assert_eq!(sniff(b"\0asm\x01\0\0\0"), Some("application/wasm"));
+    assert_eq!(
+        sniff(b"  {\"name\": \"chimney\"}"),
+        Some("application/json")
+    );
+    assert_eq!(sniff(b"[]"), Some("application/json"));
+
+    for content in [
+        &b""[..],
+        b"hello world",
+        b"<htmlish>",
+        b"[section]\nkey = value",
+        b"{ not json",
+        b"RIFF\x24\0\0\0WAVEfmt ",
+    ] {
+        assert_eq!(sniff(content), None, "{content:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_service_content_sniffing() {
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site"));
+    assert_eq!(content_type(&service, "/about").await, DEFAULT_MIME_TYPE);
+    assert_eq!(content_type(&service, "/logo").await, DEFAULT_MIME_TYPE);
+
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site").content_sniffing(true));
+    assert_eq!(
+        content_type(&service, "/about").await,
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(content_type(&service, "/logo").await, "image/png");
+    assert_eq!(
+        content_type(&service, "/export.dat").await,
+        "application/json"
+    );
+
+    // Known extensions are never sniffed, even if the content doesn't match
+    assert_eq!(
+        content_type(&service, "/model.glb").await,
+        "model/gltf-binary"
+    );
+}
+
+#[tokio::test]
+async fn test_service_nosniff() {
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site"));
+    for uri in ["/", "/model.glb"] {
+        let response = get(&service, uri).await;
+        assert_eq!(
+            response.headers()[header::X_CONTENT_TYPE_OPTIONS],
+            "nosniff",
+            "{uri}"
+        );
+    }
+
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .header_rule(HeaderRule::path("/**").remove("X-Content-Type-Options")),
+    );
+    let response = get(&service, "/").await;
+    assert!(
+        !response
+            .headers()
+            .contains_key(header::X_CONTENT_TYPE_OPTIONS)
     );
 }
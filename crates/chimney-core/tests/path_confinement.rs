@@ -0,0 +1,263 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chimney::{
+    config::{Compression, Encoding, SiteBuilder, SymlinkPolicy},
+    error::ServerError,
+    filesystem::{AbstractFile, Content, Filesystem, FilesystemError, mock::MockFilesystem},
+    server::{request_path::normalize, service::Service},
+};
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+/// The mock filesystem, keeping track of every path the service asks for
+#[derive(Default)]
+struct RecordingFilesystem {
+    inner: MockFilesystem,
+    paths: Mutex<Vec<PathBuf>>,
+}
+
+impl RecordingFilesystem {
+    fn record(&self, path: &Path) {
+        self.paths.lock().unwrap().push(path.to_path_buf());
+    }
+}
+
+impl Filesystem for RecordingFilesystem {
+    fn read_dir(&self, path: PathBuf) -> Result<Vec<AbstractFile>, FilesystemError> {
+        self.record(&path);
+        self.inner.read_dir(path)
+    }
+
+    fn list_files(&self, path: PathBuf) -> Result<Vec<PathBuf>, FilesystemError> {
+        self.record(&path);
+        self.inner.list_files(path)
+    }
+
+    fn read_file(&self, path: PathBuf) -> Result<Content, FilesystemError> {
+        self.record(&path);
+        self.inner.read_file(path)
+    }
+
+    fn exists(&self, path: PathBuf) -> Result<bool, FilesystemError> {
+        self.record(&path);
+        self.inner.exists(path)
+    }
+
+    fn stat(&self, path: PathBuf) -> Result<AbstractFile, FilesystemError> {
+        self.record(&path);
+        self.inner.stat(path)
+    }
+
+    fn canonicalize(&self, path: PathBuf) -> Result<PathBuf, FilesystemError> {
+        self.record(&path);
+        self.inner.canonicalize(path)
+    }
+}
+
+/// A service for the `public` directory of the mock filesystem
+fn mock_service(builder: SiteBuilder) -> (Arc<RecordingFilesystem>, Service) {
+    let config = common::config(Path::new(""), builder);
+    let fs = Arc::new(RecordingFilesystem::default());
+    (fs.clone(), Service::new(fs, config.into()).unwrap())
+}
+
+const TRAVERSALS: [&str; 8] = [
+    "/../index.html",
+    "/public/../../index.html",
+    "/%2e%2e/index.html",
+    "/%2E%2E%2Findex.html",
+    "/..%2f..%2fdata/note.txt",
+    "/.%2e/.%2e/index.html",
+    "/..%5cindex.html",
+    "/style.css%00.html",
+];
+
+#[test]
+fn test_normalize() {
+    assert_eq!(normalize("/").unwrap(), "");
+    assert_eq!(normalize("").unwrap(), "");
+    assert_eq!(normalize("/docs/").unwrap(), "docs");
+    assert_eq!(
+        normalize("//docs//guide/./intro").unwrap(),
+        "docs/guide/intro"
+    );
+    assert_eq!(normalize("/docs/../about.html").unwrap(), "about.html");
+    assert_eq!(normalize("/my%20file.html").unwrap(), "my file.html");
+    assert_eq!(normalize("/caf%C3%A9").unwrap(), "café");
+    assert_eq!(normalize("/a%2Fb").unwrap(), "a/b");
+    assert_eq!(normalize("/a/%2e%2e/b").unwrap(), "b");
+
+    for path in TRAVERSALS {
+        assert!(
+            matches!(normalize(path), Err(ServerError::InvalidRequestPath { .. })),
+            "{path} should be rejected"
+        );
+    }
+    assert!(normalize("/%ff").is_err());
+}
+
+#[tokio::test]
+async fn test_serves_normalized_paths() {
+    let (_fs, service) = mock_service(SiteBuilder::new("public"));
+
+    let (status, body) = common::get(&service, "/style.css").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("font-family"));
+
+    assert_eq!(
+        common::get(&service, "/./style.css").await.0,
+        StatusCode::OK
+    );
+    assert_eq!(
+        common::get(&service, "/missing/../script.js").await.0,
+        StatusCode::OK
+    );
+    assert_eq!(
+        common::get(&service, "/%73tyle.css").await.0,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_traversal_never_reaches_the_filesystem() {
+    let (fs, service) = mock_service(SiteBuilder::new("public"));
+
+    for uri in TRAVERSALS {
+        assert_eq!(
+            common::get(&service, uri).await.0,
+            StatusCode::BAD_REQUEST,
+            "{uri}"
+        );
+    }
+
+    // Rewrites are resolved the same way, so they can't escape either
+    let (fs_with_rewrite, service) =
+        mock_service(SiteBuilder::new("public").rewrite("/notes", "/../data/note.txt"));
+    assert_eq!(
+        common::get(&service, "/notes").await.0,
+        StatusCode::BAD_REQUEST
+    );
+
+    for fs in [fs, fs_with_rewrite] {
+        let paths = fs.paths.lock().unwrap();
+        assert!(
+            paths.iter().all(|path| path.starts_with("public")),
+            "{paths:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_symlinks_within_root() {
+    let (_fs, service) = mock_service(SiteBuilder::new("public"));
+
+    // `main.css` points to `style.css` next to it, `data` to a directory outside of the root
+    let (status, body) = common::get(&service, "/main.css").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("font-family"));
+    assert_eq!(
+        common::get(&service, "/data/note.txt").await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_sidecars_within_root() {
+    let compression = Compression::default().with_precompressed([Encoding::Brotli]);
+    let (_fs, service) = mock_service(SiteBuilder::new("public").compression(compression));
+
+    // `script.js.br` points outside of the root, so the original is served instead
+    let request = common::request_with(
+        Method::GET,
+        "/script.js",
+        &[(header::ACCEPT_ENCODING, "br")],
+    );
+    let response = service.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+
+    assert_eq!(
+        common::body_text(response).await,
+        "console.log('Hello, Chimney!');"
+    );
+}
+
+#[tokio::test]
+async fn test_symlinks_follow() {
+    let (_fs, service) = mock_service(SiteBuilder::new("public").symlinks(SymlinkPolicy::Follow));
+
+    assert_eq!(common::get(&service, "/main.css").await.0, StatusCode::OK);
+    assert_eq!(
+        common::get(&service, "/data/note.txt").await,
+        (StatusCode::OK, "This is a note for Chimney.".into())
+    );
+}
+
+#[tokio::test]
+async fn test_symlinks_never() {
+    let (_fs, service) = mock_service(SiteBuilder::new("public").symlinks(SymlinkPolicy::Never));
+
+    assert_eq!(common::get(&service, "/style.css").await.0, StatusCode::OK);
+    assert_eq!(
+        common::get(&service, "/main.css").await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        common::get(&service, "/data/note.txt").await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_site_root_outside_of_sites_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("sites/site")).unwrap();
+    std::fs::create_dir_all(dir.path().join("outside")).unwrap();
+    std::fs::write(dir.path().join("outside/secret.txt"), "secret").unwrap();
+
+    let builder = SiteBuilder::new("site").root("../../outside");
+    let config = common::config(&dir.path().join("sites"), builder);
+    let service = common::service_with_config(&dir, config);
+
+    let (status, body) = common::get(&service, "/secret.txt").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!body.contains("secret"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_local_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let dir = common::site_directory([("v2/index.html", "<h1>v2</h1>")]);
+    let root = dir.path().join("site");
+    std::fs::write(dir.path().join("passwd"), "root:x:0:0").unwrap();
+    symlink(root.join("v2"), root.join("latest")).unwrap();
+    symlink(dir.path().join("passwd"), root.join("passwd")).unwrap();
+
+    let service = |policy| common::service(&dir, SiteBuilder::new("site").symlinks(policy));
+
+    let within_root = service(SymlinkPolicy::WithinRoot);
+    assert_eq!(
+        common::get(&within_root, "/latest/").await,
+        (StatusCode::OK, "<h1>v2</h1>".into())
+    );
+    assert_eq!(
+        common::get(&within_root, "/passwd").await.0,
+        StatusCode::NOT_FOUND
+    );
+
+    let never = service(SymlinkPolicy::Never);
+    assert_eq!(common::get(&never, "/v2/").await.0, StatusCode::OK);
+    assert_eq!(
+        common::get(&never, "/latest/").await.0,
+        StatusCode::NOT_FOUND
+    );
+
+    let follow = service(SymlinkPolicy::Follow);
+    assert_eq!(common::get(&follow, "/passwd").await.0, StatusCode::OK);
+}
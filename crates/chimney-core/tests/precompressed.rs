@@ -0,0 +1,235 @@
+use std::path::Path;
+
+use chimney::{
+    config::{AccessRules, Compression, Encoding, Format, SiteBuilder, toml::Toml},
+    server::{encoding, quality::parse_quality},
+};
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+const FILES: [(&str, &str); 4] = [
+    ("app.js", "console.log('plain')"),
+    ("app.js.br", "brotli"),
+    ("app.js.gz", "gzip"),
+    ("style.css", "body {}"),
+];
+
+#[test]
+fn test_negotiate_prefers_highest_quality() {
+    let available = [Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+
+    assert_eq!(encoding::negotiate(None, &available), None);
+    assert_eq!(
+        encoding::negotiate(Some("gzip, deflate, br"), &available),
+        Some(Encoding::Brotli)
+    );
+    assert_eq!(
+        encoding::negotiate(Some("br;q=0.5, gzip;q=0.8"), &available),
+        Some(Encoding::Gzip)
+    );
+    assert_eq!(
+        encoding::negotiate(Some("x-gzip"), &available),
+        Some(Encoding::Gzip)
+    );
+    assert_eq!(encoding::negotiate(Some("identity"), &available), None);
+}
+
+#[test]
+fn test_negotiate_wildcard_and_rejections() {
+    let available = [Encoding::Brotli, Encoding::Gzip];
+
+    assert_eq!(
+        encoding::negotiate(Some("*"), &available),
+        Some(Encoding::Brotli)
+    );
+    assert_eq!(
+        encoding::negotiate(Some("br;q=0, *"), &available),
+        Some(Encoding::Gzip)
+    );
+    assert_eq!(encoding::negotiate(Some("*;q=0"), &available), None);
+    assert_eq!(encoding::negotiate(Some("gzip;q=abc"), &available), None);
+    assert_eq!(encoding::negotiate(Some("zstd"), &available), None);
+}
+
+#[test]
+fn test_parse_quality() {
+    assert_eq!(parse_quality(" br "), ("br", 1.0));
+    assert_eq!(parse_quality("gzip;q=0.5"), ("gzip", 0.5));
+    assert_eq!(
+        parse_quality("image/webp; level=1; Q=0.8"),
+        ("image/webp", 0.8)
+    );
+    assert_eq!(parse_quality("fr;q=2"), ("fr", 1.0));
+    assert_eq!(parse_quality("de;q=oops"), ("de", 0.0));
+}
+
+#[test]
+fn test_sidecar_path() {
+    assert_eq!(
+        encoding::sidecar_path(Path::new("/site/app.js"), Encoding::Zstd),
+        Path::new("/site/app.js.zst")
+    );
+}
+
+#[test]
+fn test_parse_compression_config() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+
+    [sites.example.compression]
+    precompressed = ["gzip", "br"]
+    "#;
+
+    let config = Toml::new(input).parse().unwrap();
+    let site = config.sites.get("example").unwrap();
+    assert_eq!(
+        site.compression.precompressed,
+        vec![Encoding::Gzip, Encoding::Brotli]
+    );
+}
+
+#[tokio::test]
+async fn test_service_serves_best_precompressed_sidecar() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").compression(Compression::default()),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/app.js",
+            &[(header::ACCEPT_ENCODING, "gzip, br")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_ENCODING).unwrap(),
+        "br"
+    );
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/javascript; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get(header::VARY).unwrap(),
+        "Accept-Encoding"
+    );
+
+    assert_eq!(common::body_text(response).await, "brotli");
+}
+
+#[tokio::test]
+async fn test_service_serves_original_without_accept_encoding() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").compression(Compression::default()),
+    );
+    let response = service.call(common::request("/app.js")).await.unwrap();
+
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(
+        response.headers().get(header::VARY).unwrap(),
+        "Accept-Encoding"
+    );
+
+    assert_eq!(common::body_text(response).await, "console.log('plain')");
+}
+
+#[tokio::test]
+async fn test_service_skips_denied_sidecars() {
+    let builder = SiteBuilder::new("site").access(AccessRules::default().deny("*.br"));
+    let (_dir, service) = common::serve(FILES, builder);
+
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/app.js",
+            &[(header::ACCEPT_ENCODING, "br")],
+        ))
+        .await
+        .unwrap();
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+
+    assert_eq!(common::body_text(response).await, "console.log('plain')");
+}
+
+#[tokio::test]
+async fn test_service_without_sidecars_does_not_vary() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").compression(Compression::default()),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/style.css",
+            &[(header::ACCEPT_ENCODING, "br")],
+        ))
+        .await
+        .unwrap();
+
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert!(!response.headers().contains_key(header::VARY));
+}
+
+#[tokio::test]
+async fn test_service_respects_site_precompressed_config() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .compression(Compression::default().with_precompressed([Encoding::Gzip])),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/app.js",
+            &[(header::ACCEPT_ENCODING, "br, gzip")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.headers().get(header::CONTENT_ENCODING).unwrap(),
+        "gzip"
+    );
+
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").compression(Compression::default().with_precompressed([])),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/app.js",
+            &[(header::ACCEPT_ENCODING, "br")],
+        ))
+        .await
+        .unwrap();
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+}
+
+#[tokio::test]
+async fn test_service_precompressed_etag_differs_from_original() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").compression(Compression::default()),
+    );
+    let plain = service.call(common::request("/app.js")).await.unwrap();
+    let encoded = service
+        .call(common::request_with(
+            Method::GET,
+            "/app.js",
+            &[(header::ACCEPT_ENCODING, "br")],
+        ))
+        .await
+        .unwrap();
+
+    assert_ne!(
+        plain.headers().get(header::ETAG),
+        encoded.headers().get(header::ETAG)
+    );
+}
@@ -0,0 +1,290 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use chimney::{
+    config::{EtagStrategy, SiteBuilder},
+    server::{
+        conditional::{self, EntityTag},
+        range::{self, ByteRange, MAX_RANGES, MultipartByteranges, Ranges},
+    },
+};
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+const CONTENT: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+
+fn satisfiable(ranges: &[(u64, u64)]) -> Option<Ranges> {
+    Some(Ranges::Satisfiable(
+        ranges
+            .iter()
+            .map(|(start, end)| ByteRange::new(*start, *end))
+            .collect(),
+    ))
+}
+
+#[test]
+fn test_parse_single_ranges() {
+    assert_eq!(range::parse("bytes=0-9", 100), satisfiable(&[(0, 9)]));
+    assert_eq!(range::parse("bytes=90-", 100), satisfiable(&[(90, 99)]));
+    assert_eq!(range::parse("bytes=-10", 100), satisfiable(&[(90, 99)]));
+    assert_eq!(range::parse("bytes=-500", 100), satisfiable(&[(0, 99)]));
+    assert_eq!(range::parse("bytes=50-5000", 100), satisfiable(&[(50, 99)]));
+    assert_eq!(range::parse("Bytes = 0-0", 100), satisfiable(&[(0, 0)]));
+}
+
+#[test]
+fn test_parse_multiple_ranges_are_coalesced() {
+    assert_eq!(
+        range::parse("bytes=0-9, 20-29", 100),
+        satisfiable(&[(0, 9), (20, 29)])
+    );
+    assert_eq!(
+        range::parse("bytes=20-29,0-9,5-14,30-31", 100),
+        satisfiable(&[(0, 14), (20, 31)])
+    );
+}
+
+#[test]
+fn test_parse_unsatisfiable_ranges() {
+    assert_eq!(range::parse("bytes=100-", 100), Some(Ranges::Unsatisfiable));
+    assert_eq!(range::parse("bytes=-0", 100), Some(Ranges::Unsatisfiable));
+    assert_eq!(range::parse("bytes=0-", 0), Some(Ranges::Unsatisfiable));
+
+    // Unsatisfiable ranges are dropped when at least one other range can be served
+    assert_eq!(
+        range::parse("bytes=200-300,0-1", 100),
+        satisfiable(&[(0, 1)])
+    );
+}
+
+#[test]
+fn test_parse_invalid_ranges_are_ignored() {
+    assert_eq!(range::parse("items=0-9", 100), None);
+    assert_eq!(range::parse("bytes=9-0", 100), None);
+    assert_eq!(range::parse("bytes=a-b", 100), None);
+    assert_eq!(range::parse("bytes=10", 100), None);
+    assert_eq!(range::parse("0-10", 100), None);
+}
+
+#[test]
+fn test_parse_too_many_ranges_is_ignored() {
+    let specs = (0..=MAX_RANGES)
+        .map(|i| format!("{}-{}", i * 10, i * 10 + 1))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    assert_eq!(range::parse(&format!("bytes={specs}"), 10_000), None);
+}
+
+#[test]
+fn test_if_range_matches() {
+    let strong = EntityTag::strong("abc");
+    let weak = EntityTag::weak("abc");
+    let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let date = conditional::format_http_date(modified);
+
+    assert!(range::if_range_matches(None, None, None));
+    assert!(range::if_range_matches(
+        Some(r#""abc""#),
+        Some(&strong),
+        None
+    ));
+    assert!(!range::if_range_matches(
+        Some(r#""xyz""#),
+        Some(&strong),
+        None
+    ));
+    assert!(!range::if_range_matches(
+        Some(r#"W/"abc""#),
+        Some(&weak),
+        None
+    ));
+    assert!(range::if_range_matches(Some(&date), None, Some(modified)));
+    assert!(!range::if_range_matches(
+        Some(&date),
+        None,
+        Some(modified + Duration::from_secs(1))
+    ));
+    assert!(!range::if_range_matches(
+        Some("garbage"),
+        None,
+        Some(modified)
+    ));
+}
+
+#[test]
+fn test_multipart_content_length() {
+    let multipart = MultipartByteranges::new("text/plain", 100);
+    let ranges = [ByteRange::new(0, 9), ByteRange::new(20, 29)];
+
+    let mut body = String::new();
+    for range in &ranges {
+        body.push_str(&multipart.part_header(range));
+        body.push_str(&"x".repeat(range.length() as usize));
+    }
+    body.push_str(&multipart.closing());
+
+    assert_eq!(multipart.content_length(&ranges), body.len() as u64);
+    assert!(multipart.content_type().ends_with(multipart.boundary()));
+}
+
+#[tokio::test]
+async fn test_service_advertises_range_support() {
+    let (_dir, service) = common::serve(
+        [("video.mp4", CONTENT)],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let response = service.call(common::request("/video.mp4")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::ACCEPT_RANGES).unwrap(),
+        "bytes"
+    );
+}
+
+#[tokio::test]
+async fn test_service_single_range() {
+    let (_dir, service) = common::serve(
+        [("video.mp4", CONTENT)],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/video.mp4",
+            &[(header::RANGE, "bytes=10-15")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response.headers().get(header::CONTENT_RANGE).unwrap(),
+        "bytes 10-15/36"
+    );
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "video/mp4"
+    );
+
+    let body = common::body_text(response).await;
+    assert_eq!(body, "abcdef");
+}
+
+#[tokio::test]
+async fn test_service_multiple_ranges() {
+    let (_dir, service) = common::serve(
+        [("video.mp4", CONTENT)],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/video.mp4",
+            &[(header::RANGE, "bytes=0-1,-2")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap();
+
+    let body = common::body_text(response).await;
+    assert_eq!(
+        body,
+        format!(
+            "\r\n--{boundary}\r\nContent-Type: video/mp4\r\nContent-Range: bytes 0-1/36\r\n\r\n01\
+             \r\n--{boundary}\r\nContent-Type: video/mp4\r\nContent-Range: bytes 34-35/36\r\n\r\nyz\
+             \r\n--{boundary}--\r\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_service_unsatisfiable_range() {
+    let (_dir, service) = common::serve(
+        [("video.mp4", CONTENT)],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/video.mp4",
+            &[(header::RANGE, "bytes=100-")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        response.headers().get(header::CONTENT_RANGE).unwrap(),
+        "bytes */36"
+    );
+}
+
+#[tokio::test]
+async fn test_service_ignores_range_for_other_methods() {
+    let (_dir, service) = common::serve(
+        [("video.mp4", CONTENT)],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::HEAD,
+            "/video.mp4",
+            &[(header::RANGE, "bytes=0-1")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_service_if_range() {
+    let (_dir, service) = common::serve(
+        [("video.mp4", CONTENT)],
+        SiteBuilder::new("site").etag(EtagStrategy::Strong),
+    );
+    let response = service.call(common::request("/video.mp4")).await.unwrap();
+    let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/video.mp4",
+            &[
+                (header::RANGE, "bytes=0-1"),
+                (header::IF_RANGE, etag.to_str().unwrap()),
+            ],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/video.mp4",
+            &[
+                (header::RANGE, "bytes=0-1"),
+                (header::IF_RANGE, r#""stale""#),
+            ],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = common::body_text(response).await;
+    assert_eq!(body, CONTENT);
+}
@@ -44,7 +44,7 @@ fn test_redirect_service_creation() {
     let config = create_test_config_with_https(HostDetectionStrategy::Auto);
     let config_handle = create_config_handle(config);
     let fs = Arc::new(MockFilesystem);
-    let service = Service::new(fs, config_handle.clone());
+    let service = Service::new(fs, config_handle.clone()).unwrap();
 
     // Create redirect service for HTTP (should redirect)
     let redirect_service_http = RedirectService::new(service.clone(), config_handle.clone(), false);
@@ -64,7 +64,7 @@ fn test_redirect_config_with_manual_host_detection() {
 
     let config_handle = create_config_handle(config);
     let fs = Arc::new(MockFilesystem);
-    let service = Service::new(fs, config_handle.clone());
+    let service = Service::new(fs, config_handle.clone()).unwrap();
     let redirect_service = RedirectService::new(service, config_handle, false);
 
     assert!(std::ptr::addr_of!(redirect_service) as usize != 0);
@@ -166,7 +166,7 @@ fn test_redirect_with_no_https_config() {
 
     let config_handle = create_config_handle(config);
     let fs = Arc::new(MockFilesystem);
-    let service = Service::new(fs, config_handle.clone());
+    let service = Service::new(fs, config_handle.clone()).unwrap();
     let redirect_service = RedirectService::new(service, config_handle, false);
 
     // Service should be created even without HTTPS config
@@ -0,0 +1,213 @@
+use chimney::config::{QueryHandling, RedirectRule, Site, SiteBuilder};
+use hyper::{StatusCode, header, service::Service as _};
+
+mod common;
+
+const FILES: [(&str, &str); 1] = [("gone.html", "<h1>Gone for good</h1>")];
+
+async fn redirect(rule: RedirectRule, uri: &str) -> (StatusCode, Option<String>) {
+    let (_dir, service) =
+        common::serve(FILES, SiteBuilder::new("site").redirect_rule("/old", rule));
+    let response = service.call(common::request(uri)).await.unwrap();
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .map(|value| value.to_str().unwrap().to_string());
+    (response.status(), location)
+}
+
+#[test]
+fn test_status_code_matrix() {
+    let cases = [
+        (false, false, 301),
+        (true, false, 302),
+        (false, true, 308),
+        (true, true, 307),
+    ];
+
+    for (temporary, replay, expected) in cases {
+        let rule = RedirectRule::new("/new".into(), temporary, replay);
+        assert_eq!(
+            rule.status_code(),
+            expected,
+            "temporary: {temporary}, replay: {replay}"
+        );
+    }
+
+    assert_eq!(RedirectRule::Target("/new".into()).status_code(), 301);
+
+    // An explicit status code wins over the flags
+    let rule = RedirectRule::new("/new".into(), false, true).with_status(303);
+    assert_eq!(rule.status_code(), 303);
+    assert_eq!(RedirectRule::gone().status_code(), 410);
+}
+
+#[test]
+fn test_query_handling() {
+    let query = Some("utm_source=mail&page=2");
+
+    assert_eq!(
+        QueryHandling::Preserve.apply("/new", query),
+        "/new?utm_source=mail&page=2"
+    );
+    assert_eq!(
+        QueryHandling::Preserve.apply("/new?page=1", query),
+        "/new?page=1"
+    );
+    assert_eq!(
+        QueryHandling::Merge.apply("/new?page=1", query),
+        "/new?page=1&utm_source=mail"
+    );
+    assert_eq!(
+        QueryHandling::Merge.apply("/new?", query),
+        "/new?utm_source=mail&page=2"
+    );
+    assert_eq!(QueryHandling::Drop.apply("/new", query), "/new");
+    assert_eq!(QueryHandling::Preserve.apply("/new", None), "/new");
+
+    // The query string goes before the fragment
+    assert_eq!(
+        QueryHandling::Preserve.apply("/new#section", query),
+        "/new?utm_source=mail&page=2#section"
+    );
+}
+
+#[test]
+fn test_validate() {
+    assert!(RedirectRule::Target("/new".into()).validate().is_ok());
+    assert!(RedirectRule::gone().validate().is_ok());
+    assert!(
+        RedirectRule::new("/new".into(), false, false)
+            .with_status(200)
+            .validate()
+            .is_err()
+    );
+    assert!(
+        RedirectRule::new(String::new(), false, false)
+            .validate()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_parse_redirect_config() {
+    let input = r#"
+    domain_names = ["example.com"]
+
+    [redirects]
+    "/see-other" = { to = "/result", status = 303 }
+    "/removed" = { status = 410 }
+    "/search" = { to = "/find?source=old", query = "merge", absolute = true }
+    "#;
+
+    let site = Site::from_string("example".into(), input).unwrap();
+
+    let rule = site.find_redirect_rule("/see-other").unwrap();
+    assert_eq!(rule.status_code(), 303);
+    assert_eq!(rule.query_handling(), QueryHandling::Preserve);
+
+    assert_eq!(
+        site.find_redirect_rule("/removed").unwrap(),
+        RedirectRule::gone()
+    );
+
+    let rule = site.find_redirect_rule("/search").unwrap();
+    assert_eq!(rule.query_handling(), QueryHandling::Merge);
+    assert!(rule.is_absolute());
+
+    let input = r#"
+    domain_names = ["example.com"]
+    redirects = { "/old" = { to = "/new", status = 404 } }
+    "#;
+    assert!(Site::from_string("example".into(), input).is_err());
+
+    let input = r#"
+    domain_names = ["example.com"]
+    redirects = { "/old" = { temporary = true } }
+    "#;
+    assert!(Site::from_string("example".into(), input).is_err());
+}
+
+#[tokio::test]
+async fn test_service_responds_with_rule_status() {
+    let cases = [
+        (
+            RedirectRule::Target("/new".into()),
+            StatusCode::MOVED_PERMANENTLY,
+        ),
+        (
+            RedirectRule::new("/new".into(), true, false),
+            StatusCode::FOUND,
+        ),
+        (
+            RedirectRule::new("/new".into(), true, true),
+            StatusCode::TEMPORARY_REDIRECT,
+        ),
+        (
+            RedirectRule::new("/new".into(), false, true),
+            StatusCode::PERMANENT_REDIRECT,
+        ),
+        (
+            RedirectRule::Target("/new".into()).with_status(303),
+            StatusCode::SEE_OTHER,
+        ),
+    ];
+
+    for (rule, expected) in cases {
+        let (status, location) = redirect(rule.clone(), "/old").await;
+        assert_eq!(status, expected, "rule: {rule:?}");
+        assert_eq!(location.as_deref(), Some("/new"));
+    }
+}
+
+#[tokio::test]
+async fn test_service_query_string() {
+    let (_, location) = redirect(RedirectRule::Target("/new".into()), "/old?a=1&b=2").await;
+    assert_eq!(location.as_deref(), Some("/new?a=1&b=2"));
+
+    let rule = RedirectRule::Target("/new?a=0".into()).with_query(QueryHandling::Merge);
+    let (_, location) = redirect(rule, "/old?a=1&b=2").await;
+    assert_eq!(location.as_deref(), Some("/new?a=0&b=2"));
+
+    let rule = RedirectRule::Target("/new".into()).with_query(QueryHandling::Drop);
+    let (_, location) = redirect(rule, "/old?a=1").await;
+    assert_eq!(location.as_deref(), Some("/new"));
+}
+
+#[tokio::test]
+async fn test_service_absolute_targets() {
+    let rule = RedirectRule::Target("/new".into()).with_absolute(true);
+    let (_, location) = redirect(rule.clone(), "/old").await;
+    assert_eq!(location.as_deref(), Some("http://example.com/new"));
+
+    let (_dir, service) =
+        common::serve(FILES, SiteBuilder::new("site").redirect_rule("/old", rule));
+    let mut request = common::request("/old");
+    request
+        .headers_mut()
+        .insert("x-forwarded-proto", "https".parse().unwrap());
+    let response = service.call(request).await.unwrap();
+    assert_eq!(
+        response.headers().get(header::LOCATION).unwrap(),
+        "https://example.com/new"
+    );
+
+    // Targets that are already absolute are left alone
+    let rule = RedirectRule::Target("https://example.org/new".into()).with_absolute(true);
+    let (_, location) = redirect(rule, "/old").await;
+    assert_eq!(location.as_deref(), Some("https://example.org/new"));
+}
+
+#[tokio::test]
+async fn test_service_gone() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .redirect_rule("/old", RedirectRule::gone())
+            .error_page(410, "gone.html"),
+    );
+
+    let response = service.call(common::request("/old")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::GONE);
+    assert!(response.headers().get(header::LOCATION).is_none());
+}
@@ -0,0 +1,331 @@
+use chimney::config::{RedirectRule, RewriteRule, RoutePattern, RoutePatterns, Site, SiteBuilder};
+use hyper::{StatusCode, header, service::Service as _};
+
+mod common;
+
+const NO_FILES: [(&str, &str); 0] = [];
+
+fn target(site: &Site, path: &str) -> Option<String> {
+    site.find_redirect_rule(path).map(|rule| rule.target())
+}
+
+#[test]
+fn test_is_pattern() {
+    assert!(!RoutePattern::is_pattern("/about"));
+    assert!(!RoutePattern::is_pattern("/a:b"));
+    assert!(RoutePattern::is_pattern("/blog/*"));
+    assert!(RoutePattern::is_pattern("/posts/:slug"));
+    assert!(RoutePattern::is_pattern(r"^/posts/(\d+)$"));
+}
+
+#[test]
+fn test_splat_pattern() {
+    let pattern = RoutePattern::parse("/blog/*").unwrap();
+
+    let captures = pattern.matches("/blog/2024/hello").unwrap();
+    assert_eq!(captures.get("splat"), Some("2024/hello"));
+    assert_eq!(pattern.matches("/blog").unwrap().get("splat"), Some(""));
+    assert_eq!(pattern.matches("/blog/").unwrap().get("splat"), Some(""));
+    assert!(pattern.matches("/blogs/hello").is_none());
+}
+
+#[test]
+fn test_param_pattern() {
+    let pattern = RoutePattern::parse("/posts/:year/:slug").unwrap();
+
+    let captures = pattern.matches("/posts/2024/hello").unwrap();
+    assert_eq!(captures.get("year"), Some("2024"));
+    assert_eq!(captures.get("slug"), Some("hello"));
+    assert!(pattern.matches("/posts/2024").is_none());
+    assert!(pattern.matches("/posts/2024/hello/world").is_none());
+    assert!(pattern.matches("/posts//hello").is_none());
+
+    // A single trailing slash is optional
+    let captures = pattern.matches("/posts/2024/hello/").unwrap();
+    assert_eq!(captures.get("slug"), Some("hello"));
+    assert!(pattern.matches("/posts/2024/hello//").is_none());
+}
+
+#[test]
+fn test_regex_pattern() {
+    let pattern = RoutePattern::parse(r"^/p/(\d+)-(?<slug>[a-z-]+)$").unwrap();
+
+    let captures = pattern.matches("/p/42-hello-world").unwrap();
+    assert_eq!(captures.get("1"), Some("42"));
+    assert_eq!(captures.get("2"), Some("hello-world"));
+    assert_eq!(captures.get("slug"), Some("hello-world"));
+    assert!(pattern.matches("/p/hello").is_none());
+
+    // Optional groups that don't take part in the match capture an empty value
+    let captures = RoutePattern::parse(r"^/files(?:/(?<rest>.*))?$")
+        .unwrap()
+        .matches("/files")
+        .unwrap();
+    assert_eq!(captures.get("rest"), Some(""));
+    assert_eq!(captures.substitute("/to/$rest"), "/to/");
+}
+
+#[test]
+fn test_invalid_patterns() {
+    assert!(RoutePattern::parse("/blog/*/comments").is_err());
+    assert!(RoutePattern::parse("/posts/:1st").is_err());
+    assert!(RoutePattern::parse("^/posts/(").is_err());
+}
+
+#[test]
+fn test_substitute() {
+    let captures = RoutePattern::parse("/docs/:version/*")
+        .unwrap()
+        .matches("/docs/v2/guide/intro")
+        .unwrap();
+
+    assert_eq!(
+        captures.substitute("/docs-:version/*"),
+        "/docs-v2/guide/intro"
+    );
+    assert_eq!(
+        captures.substitute("https://example.com:8443/:version/:splat"),
+        "https://example.com:8443/v2/guide/intro"
+    );
+    assert_eq!(captures.substitute("/:unknown"), "/:unknown");
+
+    let captures = RoutePattern::parse(r"^/p/(?<id>\d+)$")
+        .unwrap()
+        .matches("/p/7")
+        .unwrap();
+    assert_eq!(captures.substitute("/posts/$1"), "/posts/7");
+    assert_eq!(captures.substitute("/posts/${id}.html"), "/posts/7.html");
+    assert_eq!(captures.substitute("/posts/$id"), "/posts/7");
+
+    // Values that would change what the target means are encoded
+    let captures = RoutePattern::parse("/go/*")
+        .unwrap()
+        .matches("/go/a b?c#d")
+        .unwrap();
+    assert_eq!(captures.substitute("/to/:splat"), "/to/a%20b%3Fc%23d");
+}
+
+#[test]
+fn test_substitute_stays_on_host() {
+    let captures = RoutePattern::parse("/go/*")
+        .unwrap()
+        .matches("/go//evil.com")
+        .unwrap();
+    assert_eq!(captures.substitute("/:splat"), "/evil.com");
+    assert_eq!(
+        captures.substitute("https://example.com/:splat"),
+        "https://example.com//evil.com"
+    );
+}
+
+#[test]
+fn test_redirect_precedence() {
+    let site = SiteBuilder::new("site")
+        .redirect("/blog/*", "/news/:splat")
+        .redirect("/blog/:slug", "/articles/:slug")
+        .redirect("/blog/featured", "/featured")
+        .redirect(r"^/p/(\d+)$", "/posts/$1")
+        .build();
+
+    // Exact paths win regardless of where they are declared
+    assert_eq!(target(&site, "/blog/featured"), Some("/featured".into()));
+
+    // Patterns are tried in declaration order, so the splat shadows the parameter
+    assert_eq!(target(&site, "/blog/hello"), Some("/news/hello".into()));
+    assert_eq!(target(&site, "/p/12"), Some("/posts/12".into()));
+    assert_eq!(target(&site, "/p/twelve"), None);
+}
+
+#[test]
+fn test_parse_redirect_patterns_in_order() {
+    let input = r#"
+    domain_names = ["example.com"]
+
+    [redirects]
+    "/posts/:slug" = "/articles/:slug"
+    "/posts/*" = "/articles"
+    "^/(\\d{4})/(.*)$" = { to = "/archive/$1/$2", temporary = true }
+    "#;
+
+    let site = Site::from_string("example".into(), input).unwrap();
+    assert_eq!(
+        target(&site, "/posts/hello"),
+        Some("/articles/hello".into())
+    );
+    assert_eq!(target(&site, "/posts/a/b"), Some("/articles".into()));
+
+    let rule = site.find_redirect_rule("/2024/hello").unwrap();
+    assert_eq!(rule.target(), "/archive/2024/hello");
+    assert!(rule.is_temporary());
+
+    let input = r#"
+    domain_names = ["example.com"]
+    redirects = { "/blog/*/comments" = "/comments" }
+    "#;
+    assert!(Site::from_string("example".into(), input).is_err());
+}
+
+#[test]
+fn test_compiled_patterns() {
+    let sources = ["/about", "/blog/*", "/blog/*/comments", r"^/p/(\d+)$"].map(String::from);
+    let patterns = RoutePatterns::compile(&sources);
+
+    // Plain paths are looked up directly and invalid patterns are skipped
+    assert_eq!(patterns.len(), 2);
+    assert_eq!(patterns.find("/about"), None);
+    let (source, captures) = patterns.find("/blog/hello").unwrap();
+    assert_eq!(source, "/blog/*");
+    assert_eq!(captures.get("splat"), Some("hello"));
+    assert_eq!(patterns.find("/p/7").unwrap().0, r"^/p/(\d+)$");
+
+    // Rules added after the site is built are compiled too
+    let mut site = SiteBuilder::new("site").build();
+    site.add_redirect_rule("/old/*".into(), RedirectRule::Target("/new/:splat".into()));
+    assert_eq!(target(&site, "/old/page"), Some("/new/page".into()));
+
+    site.redirects.insert(
+        "/legacy/*".into(),
+        RedirectRule::Target("/new/:splat".into()),
+    );
+    assert_eq!(target(&site, "/legacy/page"), None);
+    site.compile_routes();
+    assert_eq!(target(&site, "/legacy/page"), Some("/new/page".into()));
+}
+
+#[tokio::test]
+async fn test_service_pattern_redirect() {
+    let (_dir, service) = common::serve(
+        NO_FILES,
+        SiteBuilder::new("site").redirect("/blog/:slug", "https://{request.host}/posts/:slug"),
+    );
+
+    let response = service
+        .call(common::request("/blog/hello-world"))
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+    assert_eq!(
+        response.headers().get(header::LOCATION).unwrap(),
+        "https://example.com/posts/hello-world"
+    );
+}
+
+#[tokio::test]
+async fn test_service_captures_are_not_rendered_as_templates() {
+    let (_dir, service) = common::serve(
+        NO_FILES,
+        SiteBuilder::new("site").redirect("/go/*", "/target/:splat"),
+    );
+
+    let response = service
+        .call(common::request("/go/{env.HOME}"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get(header::LOCATION).unwrap(),
+        "/target/{env.HOME}"
+    );
+}
+
+#[tokio::test]
+async fn test_service_redirect_stays_on_host() {
+    let (_dir, service) = common::serve(
+        NO_FILES,
+        SiteBuilder::new("site")
+            .redirect("/go/*", "/:splat")
+            .redirect("/to/*", "{request.path}"),
+    );
+
+    for uri in ["/go//evil.com", "/go/%2F%2Fevil.com", "/go/%2F/evil.com"] {
+        let response = service.call(common::request(uri)).await.unwrap();
+        let location = response.headers().get(header::LOCATION).unwrap();
+        assert!(!location.to_str().unwrap().starts_with("//"), "{uri}");
+    }
+
+    let response = service
+        .call(common::request("//to/evil.com"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get(header::LOCATION).unwrap(),
+        "/to/evil.com"
+    );
+}
+
+#[tokio::test]
+async fn test_service_matches_normalized_paths() {
+    let (_dir, service) = common::serve(
+        NO_FILES,
+        SiteBuilder::new("site")
+            .redirect("/posts/:slug", "/articles/:slug")
+            .redirect("/about", "/team")
+            .redirect("/docs", "/docs/"),
+    );
+
+    for (uri, location) in [
+        ("/posts/hello/", "/articles/hello"),
+        ("/p%6fsts/hello", "/articles/hello"),
+        ("/drafts/../posts/hello", "/articles/hello"),
+        ("/about/", "/team"),
+        ("/docs", "/docs/"),
+    ] {
+        let response = service.call(common::request(uri)).await.unwrap();
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            location,
+            "{uri}"
+        );
+    }
+
+    // A rule matching with the trailing slash doesn't redirect the request to itself
+    let response = service.call(common::request("/docs/")).await.unwrap();
+    assert!(!response.status().is_redirection());
+}
+
+#[test]
+fn test_rewrite_patterns() {
+    let site = SiteBuilder::new("site")
+        .rewrite("/docs/:version/*", "/docs-:version/*")
+        .rewrite("/api/*", "/backend/api/$1")
+        .rewrite("/docs/latest", "/docs-v3/index.html")
+        .build();
+
+    let target = |path: &str| site.find_rewrite_rule(path).map(|rule| rule.target());
+    assert_eq!(
+        target("/docs/v2/guide/intro.html"),
+        Some("/docs-v2/guide/intro.html".into())
+    );
+    assert_eq!(target("/docs/latest"), Some("/docs-v3/index.html".into()));
+    assert_eq!(target("/api/users/1"), Some("/backend/api/users/1".into()));
+    assert_eq!(target("/about"), None);
+
+    // Rules added after the site is built are compiled too
+    let mut site = SiteBuilder::new("site").build();
+    site.add_rewrite_rule("/app/*".into(), RewriteRule::new("/app.html".into()));
+    assert_eq!(
+        site.find_rewrite_rule("/app/settings").unwrap().target(),
+        "/app.html"
+    );
+
+    let input = r#"
+    domain_names = ["example.com"]
+    rewrites = { "^/posts/(" = "/posts.html" }
+    "#;
+    assert!(Site::from_string("example".into(), input).is_err());
+}
+
+#[tokio::test]
+async fn test_service_pattern_rewrite() {
+    let (_dir, service) = common::serve(
+        [("docs-v2/guide/intro.html", "<h1>Intro</h1>")],
+        SiteBuilder::new("site").rewrite("/docs/:version/*", "/docs-:version/*"),
+    );
+
+    let response = service
+        .call(common::request("/docs/v2/guide/intro.html"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(common::body_text(response).await, "<h1>Intro</h1>");
+}
@@ -54,7 +54,7 @@ fn mock_config() -> Config {
 
 fn mock_server(config: Config) -> Server {
     let fs = Arc::new(filesystem::mock::MockFilesystem);
-    Server::new(fs, config.into())
+    Server::new(fs, config.into()).unwrap()
 }
 
 #[tokio::test]
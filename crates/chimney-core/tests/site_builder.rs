@@ -75,6 +75,7 @@ fn test_site_builder_default_index_file() {
         .build();
 
     assert_eq!(site.default_index_file, Some("index.htm".to_string()));
+    assert_eq!(site.index_files(), ["index.htm"]);
 }
 
 #[test]
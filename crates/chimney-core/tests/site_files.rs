@@ -0,0 +1,270 @@
+use std::sync::Arc;
+
+use chimney::{
+    config::{
+        Config, HeaderRule, RedirectRule, RewriteRule, SiteBuilder,
+        netlify::{parse_headers, parse_redirects},
+    },
+    error::{ChimneyError, ServerError},
+    filesystem::local::LocalFS,
+    server::service::Service,
+};
+use hyper::{StatusCode, header, service::Service as _};
+
+mod common;
+
+const REDIRECTS: &str = r#"
+# Old blog URLs
+/blog/*            /news/:splat
+/posts/:slug       /articles/:slug   302
+/see-other         /result           303!
+/removed           /                 410
+/docs/*            /documentation/:splat 200
+/*                 /index.html       200
+"#;
+
+const HEADERS: &str = r#"
+# Every page
+/*
+  X-Frame-Options: DENY
+  Link: </style.css>; rel=preload
+  Link: </app.js>; rel=preload
+  ! X-Powered-By
+
+/assets/*
+  Cache-Control: public, max-age=31536000, immutable
+
+/posts/:slug
+  X-Robots-Tag: noindex
+"#;
+
+const FILES: [(&str, &str); 4] = [
+    ("index.html", "<h1>App</h1>"),
+    ("assets/app.js", "console.log('app')"),
+    ("_redirects", REDIRECTS),
+    ("_headers", HEADERS),
+];
+
+fn parse_error_field(error: ChimneyError) -> String {
+    match error {
+        ChimneyError::ParseError { field, .. } => field,
+        error => panic!("expected a parse error, got {error:?}"),
+    }
+}
+
+#[test]
+fn test_parse_redirects() {
+    let parsed = parse_redirects(REDIRECTS).unwrap();
+
+    assert_eq!(
+        parsed.redirects.keys().collect::<Vec<_>>(),
+        ["/blog/*", "/posts/:slug", "/see-other", "/removed"]
+    );
+    assert_eq!(parsed.redirects["/blog/*"].status_code(), 301);
+    assert_eq!(parsed.redirects["/posts/:slug"].status_code(), 302);
+    assert_eq!(parsed.redirects["/see-other"].status_code(), 303);
+    assert_eq!(parsed.redirects["/removed"].status_code(), 410);
+
+    // Only forced rules apply to paths that match a file
+    assert!(parsed.redirects["/blog/*"].is_files_first());
+    assert!(!parsed.redirects["/see-other"].is_files_first());
+
+    assert_eq!(parsed.rewrites["/docs/*"].target(), "/documentation/:splat");
+    assert!(parsed.rewrites["/docs/*"].is_files_first());
+    assert_eq!(parsed.fallback.as_deref(), Some("/index.html"));
+}
+
+#[test]
+fn test_parse_redirects_first_rule_wins() {
+    let parsed = parse_redirects("/old /first\n/old /second 302").unwrap();
+    assert_eq!(
+        parsed.redirects["/old"],
+        RedirectRule::Target("/first".into()).with_files_first(true)
+    );
+
+    // A forced catch-all applies to every path, so it is kept as a rewrite
+    let parsed = parse_redirects("/* /index.html 200!").unwrap();
+    assert!(parsed.fallback.is_none());
+    assert_eq!(
+        parsed.rewrites["/*"],
+        RewriteRule::new("/index.html".into())
+    );
+}
+
+#[test]
+fn test_parse_unsupported_redirects() {
+    let cases = [
+        ("/old", "_redirects:1"),
+        ("/store id=:id /products/:id 301", "_redirects:1"),
+        ("/old /new 302 Country=us", "_redirects:1"),
+        (
+            "\n/api/* https://api.example.com/:splat 200",
+            "_redirects:2",
+        ),
+        ("/old /new 404", "_redirects:1"),
+        ("/old /new moved", "_redirects:1"),
+        (
+            "https://old.example.com/* https://example.com/:splat 301!",
+            "_redirects:1",
+        ),
+        ("# comment\n\n/blog/*/comments /comments", "_redirects:3"),
+    ];
+
+    for (input, field) in cases {
+        let error = parse_redirects(input).unwrap_err();
+        assert_eq!(parse_error_field(error), field, "input: {input}");
+    }
+}
+
+#[test]
+fn test_parse_headers() {
+    let rules = parse_headers(HEADERS).unwrap();
+
+    assert_eq!(
+        rules[0],
+        HeaderRule::path("/**")
+            .set("X-Frame-Options", "DENY")
+            .set("Link", "</style.css>; rel=preload, </app.js>; rel=preload")
+            .remove("X-Powered-By")
+    );
+    assert_eq!(
+        rules[1],
+        HeaderRule::path("/assets/**").set("Cache-Control", "public, max-age=31536000, immutable")
+    );
+    assert_eq!(
+        rules[2],
+        HeaderRule::path("/posts/*").set("X-Robots-Tag", "noindex")
+    );
+}
+
+#[test]
+fn test_parse_unsupported_headers() {
+    let cases = [
+        ("  X-Frame-Options: DENY", "_headers:1"),
+        (
+            "https://example.com/*\n  X-Frame-Options: DENY",
+            "_headers:1",
+        ),
+        ("/*\n  X-Frame-Options DENY", "_headers:2"),
+        ("/*\n  Bad Header: value", "_headers:2"),
+        ("/*\n\n  X-Host: {request.hots}", "_headers:3"),
+    ];
+
+    for (input, field) in cases {
+        let error = parse_headers(input).unwrap_err();
+        assert_eq!(parse_error_field(error), field, "input: {input}");
+    }
+}
+
+#[test]
+fn test_import_site_files() {
+    let dir = common::site_directory(FILES);
+    let fs = LocalFS::new(dir.path().to_path_buf()).unwrap();
+    let root = dir.path().join("site");
+
+    let mut site = SiteBuilder::new("site")
+        .redirect("/posts/:slug", "/configured/:slug")
+        .header_rule(HeaderRule::path("/**").set("X-Frame-Options", "SAMEORIGIN"))
+        .build();
+
+    site.import_site_files(&fs, &root).unwrap();
+
+    // Configured rules take precedence over imported ones
+    let rule = site.find_redirect_rule("/posts/hello").unwrap();
+    assert_eq!(rule.target(), "/configured/hello");
+    assert_eq!(
+        site.find_redirect_rule("/blog/a").unwrap().target(),
+        "/news/a"
+    );
+    assert_eq!(
+        site.find_rewrite_rule("/docs/intro.html").unwrap().target(),
+        "/documentation/intro.html"
+    );
+    assert_eq!(site.header_rules.len(), 4);
+    assert_eq!(
+        site.header_rules.last().unwrap(),
+        &HeaderRule::path("/**").set("X-Frame-Options", "SAMEORIGIN")
+    );
+    assert_eq!(site.fallback_file.as_deref(), Some("./index.html"));
+
+    // Importing again (on reload) doesn't duplicate anything
+    site.import_site_files(&fs, &root).unwrap();
+    assert_eq!(site.header_rules.len(), 4);
+    assert_eq!(site.redirects.len(), 4);
+
+    // A site without the files is left alone
+    let mut site = SiteBuilder::new("empty").build();
+    site.import_site_files(&fs, dir.path()).unwrap();
+    assert!(site.redirects.is_empty());
+    assert!(site.header_rules.is_empty());
+}
+
+#[test]
+fn test_import_reports_the_file_and_line() {
+    let dir = common::site_directory([("_redirects", "/a /b\n/c /d 418\n")]);
+
+    let mut config = Config::default();
+    config.sites_directory = dir.path().to_string_lossy().to_string();
+    config.sites.add(SiteBuilder::new("site").build()).unwrap();
+
+    let fs = Arc::new(LocalFS::new(dir.path().to_path_buf()).unwrap());
+    let error = config.clone().import_site_files(fs.as_ref()).unwrap_err();
+    assert_eq!(parse_error_field(error), "_redirects:2");
+
+    // A service can't be created for a configuration that fails to load
+    match Service::new(fs, config.into()) {
+        Err(ServerError::ConfigLoadFailed(error)) => {
+            assert_eq!(parse_error_field(error), "_redirects:2")
+        }
+        Err(error) => panic!("expected a load error, got {error:?}"),
+        Ok(_) => panic!("expected a load error"),
+    }
+}
+
+#[tokio::test]
+async fn test_service_uses_imported_rules() {
+    // The service imports the files when it is created
+    let (dir, service) = common::serve(FILES, SiteBuilder::new("site"));
+
+    let response = service.call(common::request("/blog/hello")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(
+        response.headers().get(header::LOCATION).unwrap(),
+        "/news/hello"
+    );
+
+    let response = service.call(common::request("/removed")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::GONE);
+
+    let response = service
+        .call(common::request("/assets/app.js"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("X-Frame-Options").unwrap(), "DENY");
+    assert_eq!(
+        response.headers().get(header::CACHE_CONTROL).unwrap(),
+        "public, max-age=31536000, immutable"
+    );
+
+    // Files shadow the rules that aren't forced
+    std::fs::create_dir_all(dir.path().join("site/blog")).unwrap();
+    std::fs::write(dir.path().join("site/blog/kept.html"), "<h1>Kept</h1>").unwrap();
+    std::fs::write(dir.path().join("site/see-other"), "<h1>Shadowed</h1>").unwrap();
+
+    let response = service
+        .call(common::request("/blog/kept.html"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = service.call(common::request("/see-other")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+    // The catch-all only applies to paths that don't match a file
+    let response = service
+        .call(common::request("/settings/profile"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(common::body_text(response).await, "<h1>App</h1>");
+}
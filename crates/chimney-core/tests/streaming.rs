@@ -0,0 +1,113 @@
+use chimney::{
+    config::{EtagStrategy, SiteBuilder},
+    filesystem::STREAM_CHUNK_SIZE,
+    server::conditional::EntityTag,
+};
+use http_body_util::BodyExt;
+use hyper::{Method, StatusCode, header, service::Service as _};
+
+mod common;
+
+fn large_content() -> Vec<u8> {
+    (0..STREAM_CHUNK_SIZE * 4 + 123)
+        .map(|i| (i % 251) as u8)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_service_streams_file_in_chunks() {
+    let (_dir, service) = common::serve(
+        [("large.bin", large_content())],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let response = service.call(common::request("/large.bin")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_LENGTH).unwrap(),
+        &large_content().len().to_string()
+    );
+
+    let mut body = response.into_body();
+    let mut frames = 0;
+    let mut content = Vec::new();
+    while let Some(frame) = body.frame().await {
+        let data = frame.unwrap().into_data().unwrap();
+        assert!(data.len() <= STREAM_CHUNK_SIZE);
+        content.extend_from_slice(&data);
+        frames += 1;
+    }
+
+    assert!(frames > 1);
+    assert_eq!(content, large_content());
+}
+
+#[tokio::test]
+async fn test_service_streams_range_with_content_length() {
+    let (_dir, service) = common::serve(
+        [("large.bin", large_content())],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let start = STREAM_CHUNK_SIZE - 10;
+    let end = STREAM_CHUNK_SIZE * 2 + 10;
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/large.bin",
+            &[(header::RANGE, &format!("bytes={start}-{end}"))],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response.headers().get(header::CONTENT_LENGTH).unwrap(),
+        &(end - start + 1).to_string()
+    );
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, large_content()[start..=end]);
+}
+
+#[tokio::test]
+async fn test_service_multipart_content_length_matches_body() {
+    let (_dir, service) = common::serve(
+        [("large.bin", large_content())],
+        SiteBuilder::new("site").etag(EtagStrategy::Weak),
+    );
+    let response = service
+        .call(common::request_with(
+            Method::GET,
+            "/large.bin",
+            &[(header::RANGE, "bytes=0-99,-100")],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let content_length: usize = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body.len(), content_length);
+}
+
+#[tokio::test]
+async fn test_service_strong_etag_is_computed_from_stream() {
+    let (_dir, service) = common::serve(
+        [("large.bin", large_content())],
+        SiteBuilder::new("site").etag(EtagStrategy::Strong),
+    );
+    let response = service.call(common::request("/large.bin")).await.unwrap();
+
+    assert_eq!(
+        response.headers().get(header::ETAG).unwrap(),
+        &EntityTag::from_content(&large_content()).to_header_value()
+    );
+}
@@ -0,0 +1,151 @@
+use chimney::config::{
+    Format, HeaderRule, SiteBuilder, Template, TemplateContext, render_template, toml::Toml,
+};
+use hyper::{StatusCode, header, service::Service as _};
+
+mod common;
+
+const CONTEXT: TemplateContext = TemplateContext {
+    scheme: "https",
+    host: "example.com",
+    path: "/blog/hello",
+    query: "page=2",
+    method: "GET",
+};
+
+#[test]
+fn test_render_request_variables() {
+    let template =
+        Template::parse("{request.scheme}://{request.host}{request.path}?{request.query}").unwrap();
+    assert!(template.has_variables());
+    assert_eq!(
+        template.render(&CONTEXT),
+        "https://example.com/blog/hello?page=2"
+    );
+
+    assert_eq!(render_template("{request.method}", &CONTEXT), "GET");
+}
+
+#[test]
+fn test_render_env_variables() {
+    // SAFETY: the variable is unique to this test
+    unsafe { std::env::set_var("CHIMNEY_TEST_TEMPLATE_RELEASE", "v1.2.3") };
+
+    assert_eq!(
+        render_template("release-{env.CHIMNEY_TEST_TEMPLATE_RELEASE}", &CONTEXT),
+        "release-v1.2.3"
+    );
+    assert_eq!(
+        render_template("{env.CHIMNEY_TEST_TEMPLATE_UNSET}", &CONTEXT),
+        ""
+    );
+}
+
+#[test]
+fn test_other_braces_are_kept() {
+    let value = r#"{"group":"default","max_age":86400}"#;
+    let template = Template::parse(value).unwrap();
+    assert!(!template.has_variables());
+    assert_eq!(template.render(&CONTEXT), value);
+
+    assert_eq!(
+        render_template("{ {request.host} }", &CONTEXT),
+        "{ example.com }"
+    );
+}
+
+#[test]
+fn test_invalid_templates() {
+    assert!(Template::parse("{request.hostname}").is_err());
+    assert!(Template::parse("{env.}").is_err());
+    assert!(Template::parse("{env.NOT-VALID}").is_err());
+}
+
+#[test]
+fn test_parse_invalid_template_in_config() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+    response_headers = { "X-Host" = "{request.hots}" }
+    "#;
+    assert!(Toml::new(input).parse().is_err());
+
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+    redirects = { "/old" = "/new?{request.querry}" }
+    "#;
+    assert!(Toml::new(input).parse().is_err());
+
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+    response_headers = { "X-Host" = "{request.host}" }
+    redirects = { "/old" = { to = "https://{request.host}/new", temporary = true } }
+    "#;
+    assert!(Toml::new(input).parse().is_ok());
+}
+
+#[test]
+fn test_templates_are_parsed_once() {
+    let input = r#"
+    [sites.example]
+    domain_names = ["example.com"]
+    response_headers = { "X-Host" = "{request.host}", "X-Static" = "static" }
+    redirects = { "/old" = "/new?{request.query}", "/plain" = "/new" }
+    "#;
+    let config = Toml::new(input).parse().unwrap();
+    let site = config.sites.get("example").unwrap();
+    assert_eq!(site.templates().len(), 2);
+    assert_eq!(
+        site.templates().render("{request.host}", &CONTEXT),
+        "example.com"
+    );
+
+    // Headers added later are parsed as well
+    let mut site = SiteBuilder::new("site").build();
+    assert!(site.templates().is_empty());
+    site.add_response_header("X-Path".into(), "{request.path}".into());
+    assert_eq!(site.templates().len(), 1);
+}
+
+#[tokio::test]
+async fn test_service_renders_response_headers() {
+    // SAFETY: the variable is unique to this test
+    unsafe { std::env::set_var("CHIMNEY_TEST_SERVICE_RELEASE", "2024.1") };
+
+    let (_dir, service) = common::serve(
+        [("index.html", "<h1>Home</h1>")],
+        SiteBuilder::new("site")
+            .response_header("X-Release", "{env.CHIMNEY_TEST_SERVICE_RELEASE}")
+            .header_rule(HeaderRule::path("*.html").set(
+                "Link",
+                "<https://{request.host}{request.path}>; rel=canonical",
+            )),
+    );
+
+    let response = service.call(common::request("/index.html")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("X-Release").unwrap(), "2024.1");
+    assert_eq!(
+        response.headers().get(header::LINK).unwrap(),
+        "<https://example.com/index.html>; rel=canonical"
+    );
+}
+
+#[tokio::test]
+async fn test_service_renders_redirect_target() {
+    let (_dir, service) = common::serve(
+        [("index.html", "<h1>Home</h1>")],
+        SiteBuilder::new("site").redirect("/search", "/find?{request.query}"),
+    );
+
+    let response = service
+        .call(common::request("/search?q=chimney"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get(header::LOCATION).unwrap(),
+        "/find?q=chimney"
+    );
+}
@@ -1,19 +1,14 @@
-use chimney::config::{Https, Site};
+use chimney::config::{Https, Site, SiteBuilder};
 use chimney::tls::config::{process_site_https_config, TlsMode};
-use std::collections::HashMap;
 
 fn create_test_site(name: &str, domains: Vec<String>, https_config: Option<Https>) -> Site {
-    Site {
-        name: name.to_string(),
-        domain_names: domains,
-        https_config,
-        root: ".".to_string(),
-        fallback_file: None,
-        default_index_file: Some("index.html".to_string()),
-        response_headers: HashMap::new(),
-        redirects: HashMap::new(),
-        rewrites: HashMap::new(),
-    }
+    let mut site = SiteBuilder::new(name)
+        .root(".")
+        .default_index_file("index.html")
+        .build();
+    site.domain_names = domains;
+    site.https_config = https_config;
+    site
 }
 
 #[test]
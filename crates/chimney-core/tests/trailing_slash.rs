@@ -0,0 +1,171 @@
+use chimney::{
+    config::{CleanUrls, Site, SiteBuilder, TrailingSlash},
+    server::service::Service,
+};
+use hyper::{StatusCode, header, service::Service as _};
+
+mod common;
+
+const FILES: [(&str, &str); 4] = [
+    ("index.html", "<h1>Home</h1>"),
+    ("about.html", "<h1>About</h1>"),
+    ("docs/index.html", "<h1>Docs</h1>"),
+    ("docs/guide/index.html", "<h1>Guide</h1>"),
+];
+
+/// Returns the status of the response, and where it redirects to if it does
+async fn get(service: &Service, uri: &str) -> (StatusCode, Option<String>) {
+    let response = service.call(common::request(uri)).await.unwrap();
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .map(|value| value.to_str().unwrap().to_string());
+    (response.status(), location)
+}
+
+fn redirect(target: &str) -> (StatusCode, Option<String>) {
+    (StatusCode::MOVED_PERMANENTLY, Some(target.to_string()))
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(
+        TrailingSlash::Always.normalize("/docs", true),
+        Some("/docs/".into())
+    );
+    assert_eq!(TrailingSlash::Always.normalize("/docs/", true), None);
+    assert_eq!(TrailingSlash::Always.normalize("/about", false), None);
+    assert_eq!(
+        TrailingSlash::Never.normalize("/docs/", true),
+        Some("/docs".into())
+    );
+    assert_eq!(
+        TrailingSlash::Never.normalize("/docs//", false),
+        Some("/docs".into())
+    );
+    assert_eq!(TrailingSlash::Never.normalize("/docs", true), None);
+    assert_eq!(TrailingSlash::Preserve.normalize("/docs/", true), None);
+    assert_eq!(TrailingSlash::Preserve.normalize("/docs", true), None);
+
+    // The root is left alone, and redirects never leave the site
+    assert_eq!(TrailingSlash::Never.normalize("/", true), None);
+    assert_eq!(TrailingSlash::Never.normalize("//", true), None);
+    assert_eq!(
+        TrailingSlash::Never.normalize("//example.org/", false),
+        Some("/example.org".into())
+    );
+    assert_eq!(
+        TrailingSlash::Always.normalize("//example.org", true),
+        Some("/example.org/".into())
+    );
+}
+
+#[test]
+fn test_parse_trailing_slash() {
+    let parse = |policy: &str| {
+        let input = format!(
+            r#"
+            domain_names = ["example.com"]
+            trailing_slash = "{policy}"
+            "#
+        );
+        Site::from_string("example".into(), &input).map(|site| site.trailing_slash)
+    };
+
+    assert_eq!(parse("always").unwrap(), TrailingSlash::Always);
+    assert_eq!(parse("never").unwrap(), TrailingSlash::Never);
+    assert_eq!(parse("preserve").unwrap(), TrailingSlash::Preserve);
+    assert!(parse("sometimes").is_err());
+
+    let site = Site::from_string("example".into(), r#"domain_names = ["example.com"]"#).unwrap();
+    assert_eq!(site.trailing_slash, TrailingSlash::Preserve);
+}
+
+#[tokio::test]
+async fn test_preserve_by_default() {
+    let (_dir, service) = common::serve(FILES, SiteBuilder::new("site"));
+
+    assert_eq!(get(&service, "/docs").await, (StatusCode::OK, None));
+    assert_eq!(get(&service, "/docs/").await, (StatusCode::OK, None));
+    assert_eq!(get(&service, "/about.html").await, (StatusCode::OK, None));
+}
+
+#[tokio::test]
+async fn test_always() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").trailing_slash(TrailingSlash::Always),
+    );
+
+    assert_eq!(get(&service, "/docs").await, redirect("/docs/"));
+    assert_eq!(get(&service, "/docs/guide").await, redirect("/docs/guide/"));
+    assert_eq!(
+        get(&service, "/docs?page=2").await,
+        redirect("/docs/?page=2")
+    );
+    assert_eq!(get(&service, "/docs/").await, (StatusCode::OK, None));
+    assert_eq!(get(&service, "/").await, (StatusCode::OK, None));
+
+    // Only directories get a slash
+    assert_eq!(get(&service, "/about.html").await, (StatusCode::OK, None));
+    assert_eq!(
+        get(&service, "/missing").await,
+        (StatusCode::NOT_FOUND, None)
+    );
+}
+
+#[tokio::test]
+async fn test_never() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site").trailing_slash(TrailingSlash::Never),
+    );
+
+    assert_eq!(get(&service, "/docs/").await, redirect("/docs"));
+    assert_eq!(get(&service, "/docs/guide/").await, redirect("/docs/guide"));
+    assert_eq!(
+        get(&service, "/docs/?page=2").await,
+        redirect("/docs?page=2")
+    );
+    assert_eq!(get(&service, "/about.html/").await, redirect("/about.html"));
+    assert_eq!(get(&service, "/docs").await, (StatusCode::OK, None));
+    assert_eq!(get(&service, "/").await, (StatusCode::OK, None));
+}
+
+#[tokio::test]
+async fn test_redirect_rules_take_precedence() {
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .trailing_slash(TrailingSlash::Never)
+            .redirect("/old/", "/docs"),
+    );
+
+    assert_eq!(get(&service, "/old/").await, redirect("/docs"));
+}
+
+#[tokio::test]
+async fn test_with_clean_urls() {
+    let clean_urls = CleanUrls::enabled().with_redirect(true);
+
+    // The clean URL redirect goes straight to the form the policy expects
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .clean_urls(clean_urls)
+            .trailing_slash(TrailingSlash::Always),
+    );
+    assert_eq!(get(&service, "/docs/index.html").await, redirect("/docs/"));
+    assert_eq!(get(&service, "/about.html").await, redirect("/about"));
+    assert_eq!(get(&service, "/about").await, (StatusCode::OK, None));
+
+    let (_dir, service) = common::serve(
+        FILES,
+        SiteBuilder::new("site")
+            .clean_urls(clean_urls)
+            .trailing_slash(TrailingSlash::Never),
+    );
+    assert_eq!(get(&service, "/docs/index.html").await, redirect("/docs"));
+    assert_eq!(get(&service, "/about/").await, redirect("/about"));
+    assert_eq!(get(&service, "/about").await, (StatusCode::OK, None));
+}
@@ -0,0 +1,241 @@
+use chimney::config::{
+    Autoindex, CleanUrls, HeaderRule, RedirectRule, Site, TrailingSlash,
+    vercel::{ImportFormat, convert, convert_serve, convert_vercel},
+};
+
+const VERCEL: &str = r#"{
+  "$schema": "https://openapi.vercel.sh/vercel.json",
+  "buildCommand": "npm run build",
+  "redirects": [
+    { "source": "/old", "destination": "/new" },
+    { "source": "/blog/:path*", "destination": "/news/:path*", "permanent": false },
+    { "source": "/posts/:slug", "destination": "/articles/:slug", "statusCode": 301 },
+    { "source": "/p/:id(\\d+)", "destination": "/posts/:id" },
+    { "source": "/old", "destination": "/ignored" },
+    {
+      "source": "/beta",
+      "destination": "/",
+      "has": [{ "type": "cookie", "key": "beta" }]
+    }
+  ],
+  "rewrites": [
+    { "source": "/docs/(.*)", "destination": "/documentation/$1" },
+    { "source": "/api/:path*", "destination": "https://api.example.com/:path*" },
+    { "source": "/(.*)", "destination": "/index.html" }
+  ],
+  "headers": [
+    {
+      "source": "/assets/(.*)",
+      "headers": [
+        { "key": "Cache-Control", "value": "public, max-age=31536000, immutable" }
+      ]
+    },
+    {
+      "source": "/:path*",
+      "headers": [
+        { "key": "X-Frame-Options", "value": "DENY" },
+        { "key": "Link", "value": "</a.css>; rel=preload" },
+        { "key": "Link", "value": "</b.js>; rel=preload" }
+      ]
+    },
+    {
+      "source": "/users/:id(\\d+)",
+      "headers": [{ "key": "X-Robots-Tag", "value": "noindex" }]
+    }
+  ],
+  "cleanUrls": true,
+  "trailingSlash": false
+}"#;
+
+const SERVE: &str = r#"{
+  "public": "dist",
+  "redirects": [
+    { "source": "/old", "destination": "/new" },
+    { "source": "/temporary/**", "destination": "/elsewhere", "type": 302 },
+    { "source": "/teapot", "destination": "/", "type": 418 }
+  ],
+  "rewrites": [
+    { "source": "app/:id", "destination": "/app.html" },
+    { "source": "**", "destination": "/index.html" }
+  ],
+  "headers": [
+    { "source": "**/*.js", "headers": [{ "key": "Cache-Control", "value": "max-age=7200" }] },
+    { "source": "**/*.@(jpg|png)", "headers": [{ "key": "Cache-Control", "value": "max-age=60" }] }
+  ],
+  "directoryListing": ["/artifacts/**", "/logs/*.txt"],
+  "cleanUrls": ["/blog/**"],
+  "trailingSlash": true,
+  "etag": true
+}"#;
+
+fn target(site: &Site, path: &str) -> Option<String> {
+    site.find_redirect_rule(path).map(|rule| rule.target())
+}
+
+#[test]
+fn test_vercel_redirects() {
+    let conversion = convert_vercel("app", VERCEL).unwrap();
+    let site = conversion.site;
+
+    assert_eq!(
+        site.redirects.keys().collect::<Vec<_>>(),
+        ["/old", "/blog/*", "/posts/:slug", r"^/p/(?<id>\d+)$"]
+    );
+
+    // Vercel redirects are permanent unless told otherwise, and keep the method
+    let rule = site.find_redirect_rule("/old").unwrap();
+    assert_eq!(rule.status_code(), 308);
+    assert_eq!(rule.target(), "/new");
+
+    let rule = site.find_redirect_rule("/blog/2024/hello").unwrap();
+    assert_eq!(rule.status_code(), 307);
+    assert_eq!(rule.target(), "/news/2024/hello");
+
+    assert_eq!(
+        site.find_redirect_rule("/posts/a").unwrap().status_code(),
+        301
+    );
+    assert_eq!(target(&site, "/p/42"), Some("/posts/42".into()));
+    assert_eq!(target(&site, "/p/latest"), None);
+    assert_eq!(target(&site, "/beta"), None);
+}
+
+#[test]
+fn test_vercel_rewrites_and_headers() {
+    let conversion = convert_vercel("app", VERCEL).unwrap();
+    let site = conversion.site;
+
+    // Rewrites only apply to paths without a file, so a catch-all becomes the fallback file
+    let rewrite = site.find_rewrite_rule("/docs/guide/intro").unwrap();
+    assert_eq!(rewrite.target(), "/documentation/guide/intro");
+    assert!(rewrite.is_files_first());
+    assert!(site.find_rewrite_rule("/api/users").is_none());
+
+    assert!(site.find_rewrite_rule("/settings").is_none());
+    assert_eq!(site.fallback_file.as_deref(), Some("./index.html"));
+    assert_eq!(site.clean_urls, CleanUrls::enabled().with_redirect(true));
+    assert_eq!(site.trailing_slash, TrailingSlash::Never);
+
+    assert_eq!(
+        site.header_rules,
+        [
+            HeaderRule::path("/assets/**")
+                .set("Cache-Control", "public, max-age=31536000, immutable"),
+            HeaderRule::path("/**")
+                .set("X-Frame-Options", "DENY")
+                .set("Link", "</a.css>; rel=preload, </b.js>; rel=preload"),
+        ]
+    );
+}
+
+#[test]
+fn test_vercel_warnings() {
+    let warnings = convert_vercel("app", VERCEL).unwrap().warnings;
+
+    let expected = [
+        "redirects[4]: `/old` is already handled by an earlier rule, skipped",
+        "redirects[5]: conditions (`has` and `missing`) are not supported, skipped",
+        "rewrites[1]: proxying to another origin (`https://api.example.com/:path*`) is not supported, skipped",
+        "headers[2]: `/users/:id(\\d+)` can't be converted to a glob, skipped",
+        "buildCommand: not related to routing, ignored",
+    ];
+    assert_eq!(warnings, expected);
+}
+
+#[test]
+fn test_path_to_regexp_sources() {
+    let redirects = |source: &str| {
+        let input = format!(
+            r#"{{ "redirects": [{{ "source": "{source}", "destination": "/to/:rest*" }}] }}"#
+        );
+        let conversion = convert_vercel("app", &input).unwrap();
+        assert!(conversion.warnings.is_empty(), "{:?}", conversion.warnings);
+        conversion.site
+    };
+
+    let site = redirects("/files/:rest*");
+    assert_eq!(target(&site, "/files"), Some("/to/".into()));
+    assert_eq!(target(&site, "/files/a/b"), Some("/to/a/b".into()));
+
+    let site = redirects("/files/:rest+");
+    assert_eq!(target(&site, "/files"), None);
+    assert_eq!(target(&site, "/files/a/b"), Some("/to/a/b".into()));
+
+    let site = redirects("/files/:rest?");
+    assert_eq!(target(&site, "/files"), Some("/to/".into()));
+    assert_eq!(target(&site, "/files/a"), Some("/to/a".into()));
+    assert_eq!(target(&site, "/files/a/b"), None);
+
+    let site = redirects("/v1.0/:rest-old");
+    assert_eq!(target(&site, "/v1.0/page-old"), Some("/to/page".into()));
+    assert_eq!(target(&site, "/v1x0/page-old"), None);
+}
+
+#[test]
+fn test_serve() {
+    let conversion = convert_serve("app", SERVE).unwrap();
+    let site = conversion.site;
+
+    assert_eq!(site.root, "dist");
+    assert_eq!(site.find_redirect_rule("/old").unwrap().status_code(), 301);
+
+    let rule = site.find_redirect_rule("/temporary/a/b").unwrap();
+    assert_eq!(rule.status_code(), 302);
+    assert_eq!(rule.target(), "/elsewhere");
+
+    assert_eq!(
+        site.find_rewrite_rule("/app/12").unwrap().target(),
+        "/app.html"
+    );
+    assert_eq!(site.fallback_file.as_deref(), Some("dist/index.html"));
+
+    assert_eq!(
+        site.header_rules,
+        [HeaderRule::path("**/*.js").set("Cache-Control", "max-age=7200")]
+    );
+    assert_eq!(site.autoindex, Autoindex::default().with_path("/artifacts"));
+    assert_eq!(site.trailing_slash, TrailingSlash::Always);
+
+    assert_eq!(
+        conversion.warnings,
+        [
+            "redirects[2]: `418` is not a supported redirect status code (expected one of 301, 302, 303, 307, 308 or 410), skipped",
+            "headers[1]: `**/*.@(jpg|png)` uses glob syntax that is not supported, skipped",
+            "directoryListing: `\"/logs/*.txt\"` can't be converted, only globs like `/path/**` are supported",
+            "cleanUrls: `[\"/blog/**\"]` can't be converted, only `true` or `false` are supported",
+            "trailingSlash: only paths to directories are redirected to end with a slash, other paths are served as requested",
+            "etag: not related to routing, ignored",
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_json() {
+    assert!(convert(ImportFormat::Vercel, "app", "{ redirects: [] }").is_err());
+    assert!(convert(ImportFormat::Serve, "app", r#"{ "redirects": {} }"#).is_err());
+    assert_eq!(
+        ImportFormat::from_filename("vercel.json"),
+        Some(ImportFormat::Vercel)
+    );
+    assert_eq!(ImportFormat::from_filename("now.json"), None);
+}
+
+#[test]
+fn test_converted_site_round_trips() {
+    let mut site = convert_vercel("app", VERCEL).unwrap().site;
+    site.add_domain_name("example.com".into());
+
+    let output = site.to_toml_string().unwrap();
+    assert!(!output.contains("[compression]"));
+
+    let parsed = Site::from_string("app".into(), &output).unwrap();
+    assert_eq!(parsed.domain_names, ["example.com"]);
+    assert_eq!(parsed.redirects, site.redirects);
+    assert_eq!(parsed.rewrites, site.rewrites);
+    assert_eq!(parsed.header_rules, site.header_rules);
+    assert_eq!(parsed.fallback_file, site.fallback_file);
+    assert_eq!(
+        parsed.find_redirect_rule("/old"),
+        Some(RedirectRule::new("/new".into(), false, true))
+    );
+}